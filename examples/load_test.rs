@@ -0,0 +1,239 @@
+// Load test harness, see README "Load test"
+//
+// cargo run --release --example load_test -- --url http://127.0.0.1:8080 --scenario sessions \
+//     --concurrency 32 --duration 20 --setup-database-url postgres://...
+//
+// `--setup-database-url` creates the `loadtest` user the scenarios log in with. While the load
+// runs, a probe calls `/health-check` every 50ms: its latency shows whether the workers are free.
+use std::{
+    env,
+    process::exit,
+    time::{Duration, Instant},
+};
+
+use diesel::{pg::PgConnection, sql_query, sql_types::Text, Connection, RunQueryDsl};
+use serde_json::{json, Value};
+
+const USERNAME: &str = "loadtest";
+const PASSWORD: &str = "loadtest-password";
+const PROBE_INTERVAL: Duration = Duration::from_millis(50);
+
+#[derive(Clone, Copy, PartialEq)]
+enum Scenario {
+    // Authenticated `GET /api/auth/sessions`: token check, session touch and listing queries
+    Sessions,
+    // `POST /api/auth/login`: bcrypt verification plus the login history and session inserts
+    Login,
+}
+
+struct Options {
+    url: String,
+    scenario: Scenario,
+    concurrency: usize,
+    duration: Duration,
+    setup_database_url: Option<String>,
+}
+
+#[derive(Default)]
+struct Samples {
+    latencies: Vec<Duration>,
+    errors: usize,
+}
+
+impl Samples {
+    fn merge(&mut self, other: Samples) {
+        self.latencies.extend(other.latencies);
+        self.errors += other.errors;
+    }
+
+    fn percentile(&self, p: f64) -> Duration {
+        if self.latencies.is_empty() {
+            return Duration::ZERO;
+        }
+        let index = ((self.latencies.len() - 1) as f64 * p).round() as usize;
+        self.latencies[index]
+    }
+
+    fn report(&mut self, name: &str, elapsed: Duration) {
+        self.latencies.sort();
+        let count = self.latencies.len();
+        println!(
+            "{:<8} {:>7} requests {:>5} errors {:>9.1} req/s   p50 {:>7.1}ms  p95 {:>7.1}ms  p99 {:>7.1}ms  max {:>7.1}ms",
+            name,
+            count,
+            self.errors,
+            count as f64 / elapsed.as_secs_f64(),
+            self.percentile(0.50).as_secs_f64() * 1000.0,
+            self.percentile(0.95).as_secs_f64() * 1000.0,
+            self.percentile(0.99).as_secs_f64() * 1000.0,
+            self.latencies.last().copied().unwrap_or_default().as_secs_f64() * 1000.0,
+        );
+    }
+}
+
+fn usage() -> ! {
+    eprintln!(
+        "Usage: load_test [--url URL] [--scenario sessions|login] [--concurrency N] \
+         [--duration SECONDS] [--setup-database-url DATABASE_URL]"
+    );
+    exit(2)
+}
+
+fn parse_options() -> Options {
+    let mut options = Options {
+        url: "http://127.0.0.1:8080".to_string(),
+        scenario: Scenario::Sessions,
+        concurrency: 32,
+        duration: Duration::from_secs(20),
+        setup_database_url: None,
+    };
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        let value = args.next().unwrap_or_else(|| usage());
+        match arg.as_str() {
+            "--url" => options.url = value.trim_end_matches('/').to_string(),
+            "--scenario" => {
+                options.scenario = match value.as_str() {
+                    "sessions" => Scenario::Sessions,
+                    "login" => Scenario::Login,
+                    _ => usage(),
+                }
+            }
+            "--concurrency" => options.concurrency = value.parse().unwrap_or_else(|_| usage()),
+            "--duration" => {
+                options.duration = Duration::from_secs(value.parse().unwrap_or_else(|_| usage()))
+            }
+            "--setup-database-url" => options.setup_database_url = Some(value),
+            _ => usage(),
+        }
+    }
+    options
+}
+
+// Create the `loadtest` user once, a regular user so that no second factor is required
+fn create_user(database_url: &str) {
+    let conn =
+        &mut PgConnection::establish(database_url).expect("Failed to connect to the database");
+    let password_hash = bcrypt::hash(PASSWORD, bcrypt::DEFAULT_COST).unwrap();
+    sql_query(
+        "INSERT INTO users (username, email, password, role) \
+         SELECT $1, $2, $3, 'user' WHERE NOT EXISTS (SELECT 1 FROM users WHERE username = $1)",
+    )
+    .bind::<Text, _>(USERNAME)
+    .bind::<Text, _>(format!("{}@platform-cv.test", USERNAME))
+    .bind::<Text, _>(password_hash)
+    .execute(conn)
+    .expect("Failed to create the load test user");
+}
+
+fn login_body() -> Value {
+    json!({ "username_or_email": USERNAME, "password": PASSWORD })
+}
+
+async fn login(client: &reqwest::Client, url: &str) -> String {
+    let response = client
+        .post(format!("{}/api/auth/login", url))
+        .json(&login_body())
+        .send()
+        .await
+        .expect("Login request failed");
+    if !response.status().is_success() {
+        eprintln!(
+            "Login failed with {}, run once with --setup-database-url",
+            response.status()
+        );
+        exit(1);
+    }
+    let body: Value = response.json().await.unwrap();
+    body["data"]["token"].as_str().unwrap().to_string()
+}
+
+async fn load(
+    client: reqwest::Client,
+    url: String,
+    scenario: Scenario,
+    token: String,
+    deadline: Instant,
+) -> Samples {
+    let mut samples = Samples::default();
+    while Instant::now() < deadline {
+        let request = match scenario {
+            Scenario::Sessions => client
+                .get(format!("{}/api/auth/sessions", url))
+                .bearer_auth(&token),
+            Scenario::Login => client
+                .post(format!("{}/api/auth/login", url))
+                .json(&login_body()),
+        };
+        let started = Instant::now();
+        match request.send().await {
+            Ok(response) if response.status().is_success() => {
+                let _ = response.bytes().await;
+                samples.latencies.push(started.elapsed());
+            }
+            _ => samples.errors += 1,
+        }
+    }
+    samples
+}
+
+async fn probe(client: reqwest::Client, url: String, deadline: Instant) -> Samples {
+    let mut samples = Samples::default();
+    while Instant::now() < deadline {
+        let started = Instant::now();
+        match client.get(format!("{}/health-check", url)).send().await {
+            Ok(response) if response.status().is_success() => {
+                samples.latencies.push(started.elapsed())
+            }
+            _ => samples.errors += 1,
+        }
+        actix_rt::time::sleep(PROBE_INTERVAL).await;
+    }
+    samples
+}
+
+#[actix_rt::main]
+async fn main() {
+    let options = parse_options();
+    if let Some(database_url) = &options.setup_database_url {
+        create_user(database_url);
+    }
+
+    let client = reqwest::Client::builder()
+        .pool_max_idle_per_host(options.concurrency)
+        .timeout(Duration::from_secs(30))
+        .build()
+        .unwrap();
+    let token = login(&client, &options.url).await;
+
+    let started = Instant::now();
+    let deadline = started + options.duration;
+    let workers: Vec<_> = (0..options.concurrency)
+        .map(|_| {
+            actix_rt::spawn(load(
+                client.clone(),
+                options.url.clone(),
+                options.scenario,
+                token.clone(),
+                deadline,
+            ))
+        })
+        .collect();
+    let probe = actix_rt::spawn(probe(client.clone(), options.url.clone(), deadline));
+
+    let mut samples = Samples::default();
+    for worker in workers {
+        samples.merge(worker.await.unwrap());
+    }
+    let mut probe_samples = probe.await.unwrap();
+    let elapsed = started.elapsed();
+
+    samples.report(
+        match options.scenario {
+            Scenario::Sessions => "sessions",
+            Scenario::Login => "login",
+        },
+        elapsed,
+    );
+    probe_samples.report("probe", elapsed);
+}
@@ -0,0 +1,5 @@
+-- This file should undo anything in `up.sql`
+ALTER TABLE users
+ADD COLUMN login_session VARCHAR;
+
+DROP TABLE user_sessions;
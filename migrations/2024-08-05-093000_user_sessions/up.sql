@@ -0,0 +1,22 @@
+-- Your SQL goes here
+CREATE TABLE user_sessions (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    session_token VARCHAR NOT NULL UNIQUE,
+    user_agent VARCHAR,
+    ip_address VARCHAR,
+    created_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    last_seen_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    expires_at TIMESTAMP NOT NULL,
+    revoked_at TIMESTAMP
+);
+
+CREATE INDEX idx_user_sessions_user_id ON user_sessions (user_id);
+
+INSERT INTO user_sessions (user_id, session_token, created_at, last_seen_at, expires_at)
+SELECT id, login_session, NOW(), NOW(), NOW() + INTERVAL '7 days'
+FROM users
+WHERE login_session IS NOT NULL AND login_session <> '';
+
+ALTER TABLE users
+DROP COLUMN login_session;
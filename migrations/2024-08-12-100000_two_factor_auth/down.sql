@@ -0,0 +1,7 @@
+-- This file should undo anything in `up.sql`
+DROP TABLE user_recovery_codes;
+
+ALTER TABLE users
+DROP COLUMN IF EXISTS totp_last_step,
+DROP COLUMN IF EXISTS totp_enabled,
+DROP COLUMN IF EXISTS totp_secret;
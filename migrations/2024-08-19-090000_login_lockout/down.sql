@@ -0,0 +1,14 @@
+-- This file should undo anything in `up.sql`
+DROP INDEX IF EXISTS idx_login_history_ip_address;
+
+DELETE FROM login_history WHERE user_id IS NULL;
+
+ALTER TABLE login_history
+DROP COLUMN IF EXISTS ip_address,
+DROP COLUMN IF EXISTS failure_reason,
+DROP COLUMN IF EXISTS success,
+ALTER COLUMN user_id SET NOT NULL;
+
+ALTER TABLE users
+DROP COLUMN IF EXISTS locked_until,
+DROP COLUMN IF EXISTS failed_login_attempts;
@@ -0,0 +1,12 @@
+-- Your SQL goes here
+ALTER TABLE users
+ADD COLUMN failed_login_attempts INTEGER NOT NULL DEFAULT 0,
+ADD COLUMN locked_until TIMESTAMP;
+
+ALTER TABLE login_history
+ALTER COLUMN user_id DROP NOT NULL,
+ADD COLUMN success BOOLEAN NOT NULL DEFAULT TRUE,
+ADD COLUMN failure_reason VARCHAR,
+ADD COLUMN ip_address VARCHAR;
+
+CREATE INDEX idx_login_history_ip_address ON login_history (ip_address, login_timestamp);
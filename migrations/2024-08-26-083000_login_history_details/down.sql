@@ -0,0 +1,7 @@
+-- This file should undo anything in `up.sql`
+DROP INDEX IF EXISTS idx_login_history_login_timestamp;
+DROP INDEX IF EXISTS idx_login_history_user_id;
+
+ALTER TABLE login_history
+DROP COLUMN IF EXISTS auth_method,
+DROP COLUMN IF EXISTS user_agent;
@@ -0,0 +1,7 @@
+-- Your SQL goes here
+ALTER TABLE login_history
+ADD COLUMN user_agent VARCHAR,
+ADD COLUMN auth_method VARCHAR NOT NULL DEFAULT 'password';
+
+CREATE INDEX idx_login_history_user_id ON login_history (user_id, login_timestamp);
+CREATE INDEX idx_login_history_login_timestamp ON login_history (login_timestamp);
@@ -0,0 +1,7 @@
+-- This file should undo anything in `up.sql`
+DROP INDEX IF EXISTS idx_candidate_cv_scan_pending;
+
+ALTER TABLE candidate
+DROP COLUMN IF EXISTS cv_scanned_at,
+DROP COLUMN IF EXISTS cv_scan_signature,
+DROP COLUMN IF EXISTS cv_scan_status;
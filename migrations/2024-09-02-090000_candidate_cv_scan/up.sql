@@ -0,0 +1,7 @@
+-- Malware scan of the CV, files stay in quarantine and can not be downloaded until `clean`
+ALTER TABLE candidate
+ADD COLUMN cv_scan_status VARCHAR NOT NULL DEFAULT 'pending',
+ADD COLUMN cv_scan_signature VARCHAR,
+ADD COLUMN cv_scanned_at TIMESTAMP;
+
+CREATE INDEX idx_candidate_cv_scan_pending ON candidate (cv_scan_status) WHERE cv_scan_status = 'pending';
@@ -0,0 +1,12 @@
+-- This file should undo anything in `up.sql`
+DROP INDEX IF EXISTS idx_candidate_search_vector;
+DROP INDEX IF EXISTS idx_candidate_cv_text_pending;
+
+ALTER TABLE candidate
+DROP COLUMN IF EXISTS search_vector,
+DROP COLUMN IF EXISTS cv_text_retry_at,
+DROP COLUMN IF EXISTS cv_text_error,
+DROP COLUMN IF EXISTS cv_text_attempts,
+DROP COLUMN IF EXISTS cv_text_status,
+DROP COLUMN IF EXISTS cv_page_count,
+DROP COLUMN IF EXISTS cv_text;
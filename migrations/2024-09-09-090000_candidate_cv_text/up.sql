@@ -0,0 +1,20 @@
+-- Text extracted from the CV by the extraction job, searched along with the names and the motivation
+ALTER TABLE candidate
+ADD COLUMN cv_text TEXT,
+ADD COLUMN cv_page_count INTEGER,
+ADD COLUMN cv_text_status VARCHAR NOT NULL DEFAULT 'pending',
+ADD COLUMN cv_text_attempts INTEGER NOT NULL DEFAULT 0,
+ADD COLUMN cv_text_error VARCHAR,
+ADD COLUMN cv_text_retry_at TIMESTAMP;
+
+CREATE INDEX idx_candidate_cv_text_pending ON candidate (cv_text_retry_at) WHERE cv_text_status = 'pending';
+
+-- Kept up to date by Postgres, left out of `schema.rs` and queried through SQL fragments
+ALTER TABLE candidate
+ADD COLUMN search_vector TSVECTOR GENERATED ALWAYS AS (
+    setweight(to_tsvector('french', lastname || ' ' || firstname), 'A') ||
+    setweight(to_tsvector('french', motivation), 'B') ||
+    setweight(to_tsvector('french', coalesce(cv_text, '')), 'C')
+) STORED;
+
+CREATE INDEX idx_candidate_search_vector ON candidate USING GIN (search_vector);
@@ -0,0 +1,3 @@
+-- This file should undo anything in `up.sql`
+ALTER TABLE candidate
+DROP COLUMN IF EXISTS cv_parsed;
@@ -0,0 +1,3 @@
+-- Contact details, skills, experience and education parsed from `cv_text`, see `src/parsing`
+ALTER TABLE candidate
+ADD COLUMN cv_parsed JSONB;
@@ -0,0 +1,4 @@
+-- This file should undo anything in `up.sql`
+DROP TABLE IF EXISTS candidate_skills;
+DROP TABLE IF EXISTS job_offer_skills;
+DROP TABLE IF EXISTS skills;
@@ -0,0 +1,39 @@
+-- Skills of the dictionary (`config/skills.yaml`), added when a CV mentions them or an offer asks for them
+CREATE TABLE skills (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    name VARCHAR NOT NULL UNIQUE,
+    category VARCHAR
+);
+
+CREATE TABLE job_offer_skills (
+    job_offer_id UUID NOT NULL REFERENCES job_offers(id) ON DELETE CASCADE,
+    skill_id UUID NOT NULL REFERENCES skills(id) ON DELETE CASCADE,
+    -- Nice to have otherwise
+    required BOOLEAN NOT NULL DEFAULT TRUE,
+    PRIMARY KEY (job_offer_id, skill_id)
+);
+
+CREATE INDEX idx_job_offer_skills_skill_id ON job_offer_skills (skill_id);
+
+-- Skills found in the CV by the parser
+CREATE TABLE candidate_skills (
+    candidate_id UUID NOT NULL REFERENCES candidate(id) ON DELETE CASCADE,
+    skill_id UUID NOT NULL REFERENCES skills(id) ON DELETE CASCADE,
+    PRIMARY KEY (candidate_id, skill_id)
+);
+
+CREATE INDEX idx_candidate_skills_skill_id ON candidate_skills (skill_id);
+
+-- CVs parsed before this migration, the categories are filled when the skills are found again
+INSERT INTO skills (name)
+SELECT DISTINCT jsonb_array_elements_text(cv_parsed -> 'skills')
+FROM candidate
+WHERE cv_parsed IS NOT NULL
+ON CONFLICT (name) DO NOTHING;
+
+INSERT INTO candidate_skills (candidate_id, skill_id)
+SELECT candidate.id, skills.id
+FROM candidate
+CROSS JOIN LATERAL jsonb_array_elements_text(candidate.cv_parsed -> 'skills') AS parsed(name)
+JOIN skills ON skills.name = parsed.name
+ON CONFLICT DO NOTHING;
@@ -0,0 +1,15 @@
+-- Audit trail of the duplicate candidates merged into another one
+CREATE TABLE candidate_merges (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    survivor_id UUID NOT NULL REFERENCES candidate(id) ON DELETE CASCADE,
+    -- The merged candidate is deleted, its record is kept in `snapshot`
+    merged_id UUID NOT NULL,
+    merged_by UUID REFERENCES users(id) ON DELETE SET NULL,
+    merged_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    -- Fields of the survivor that were empty, filled from the merged candidate
+    filled_fields VARCHAR[] NOT NULL DEFAULT '{}',
+    -- The merged application, its CV file stays in the storage under `snapshot ->> 'file_name'`
+    snapshot JSONB NOT NULL
+);
+
+CREATE INDEX idx_candidate_merges_survivor_id ON candidate_merges (survivor_id);
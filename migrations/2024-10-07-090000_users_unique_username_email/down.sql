@@ -0,0 +1,4 @@
+-- This file should undo anything in `up.sql`
+-- The emails stay lowercased
+DROP INDEX IF EXISTS idx_users_email;
+DROP INDEX IF EXISTS idx_users_username;
@@ -0,0 +1,7 @@
+-- Emails are stored lowercased since the validation of the DTOs, the existing ones are lowercased too.
+-- Fails on accounts sharing a username or an email: rename or delete one of them, then run it again
+UPDATE users SET email = lower(trim(email)) WHERE email <> lower(trim(email));
+UPDATE candidate SET email = lower(trim(email)) WHERE email <> lower(trim(email));
+
+CREATE UNIQUE INDEX idx_users_username ON users (username);
+CREATE UNIQUE INDEX idx_users_email ON users (lower(email));
@@ -0,0 +1,11 @@
+-- This file should undo anything in `up.sql`
+ALTER TABLE company DROP COLUMN IF EXISTS retention_days;
+
+DROP INDEX IF EXISTS idx_candidate_retention;
+
+ALTER TABLE candidate
+DROP COLUMN IF EXISTS anonymised_at,
+DROP COLUMN IF EXISTS consent_ip,
+DROP COLUMN IF EXISTS consented_at,
+DROP COLUMN IF EXISTS consent_version,
+DROP COLUMN IF EXISTS applied_at;
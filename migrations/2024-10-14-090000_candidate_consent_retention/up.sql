@@ -0,0 +1,14 @@
+-- Consent given with the application: version of the privacy notice, time and address of the candidate.
+-- The applications received before this migration are dated from it, their retention starts now
+ALTER TABLE candidate
+ADD COLUMN applied_at TIMESTAMP NOT NULL DEFAULT NOW(),
+ADD COLUMN consent_version VARCHAR,
+ADD COLUMN consented_at TIMESTAMP,
+ADD COLUMN consent_ip VARCHAR,
+-- Set once the personal data is erased, by the retention job or on request
+ADD COLUMN anonymised_at TIMESTAMP;
+
+CREATE INDEX idx_candidate_retention ON candidate (applied_at) WHERE anonymised_at IS NULL;
+
+-- Days the applications are kept before being anonymised, `gdpr.default_retention_days` when null
+ALTER TABLE company ADD COLUMN retention_days INTEGER;
@@ -0,0 +1,12 @@
+-- Download links of the data exports requested by the candidates, sent to the address they applied with.
+-- Only the SHA-256 digest of the token is kept, the link is valid until `expires_at`
+CREATE TABLE data_export_requests (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    email VARCHAR NOT NULL,
+    token_hash VARCHAR NOT NULL UNIQUE,
+    created_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    expires_at TIMESTAMP NOT NULL,
+    downloaded_at TIMESTAMP
+);
+
+CREATE INDEX idx_data_export_requests_email ON data_export_requests (email, created_at);
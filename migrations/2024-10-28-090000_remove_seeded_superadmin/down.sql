@@ -0,0 +1,3 @@
+-- This file should undo anything in `up.sql`
+-- The removed credential is not restored
+SELECT 1;
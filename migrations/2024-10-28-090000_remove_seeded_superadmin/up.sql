@@ -0,0 +1,26 @@
+-- The `superadmin` account of 2024-07-31-070410_insert_user_superadmin has its password hash in the repository.
+-- It is deleted when it never logged in, e.g. on a fresh database. Otherwise its login history is kept: the password,
+-- second factor and sessions are removed, `platform-cv-admin superadmin --username superadmin` sets a new password
+DELETE FROM users
+WHERE username = 'superadmin'
+    AND password = '$2y$10$2FbcAoMDLmpFUz/phu0Pa.0Yhi6B9VU1ag9uRSgcW2lZhlh0U6M2C'
+    AND NOT EXISTS (SELECT 1 FROM login_history WHERE login_history.user_id = users.id);
+
+DELETE FROM user_sessions
+WHERE user_id IN (
+    SELECT id FROM users
+    WHERE username = 'superadmin'
+        AND password = '$2y$10$2FbcAoMDLmpFUz/phu0Pa.0Yhi6B9VU1ag9uRSgcW2lZhlh0U6M2C'
+);
+
+DELETE FROM user_recovery_codes
+WHERE user_id IN (
+    SELECT id FROM users
+    WHERE username = 'superadmin'
+        AND password = '$2y$10$2FbcAoMDLmpFUz/phu0Pa.0Yhi6B9VU1ag9uRSgcW2lZhlh0U6M2C'
+);
+
+UPDATE users
+SET password = NULL, totp_secret = NULL, totp_enabled = FALSE, totp_last_step = NULL
+WHERE username = 'superadmin'
+    AND password = '$2y$10$2FbcAoMDLmpFUz/phu0Pa.0Yhi6B9VU1ag9uRSgcW2lZhlh0U6M2C';
@@ -0,0 +1,454 @@
+// JSON dump of the business data, to move an environment or restore a backup.
+// Sessions and the login history are left out, they are short lived or purged anyway
+use std::collections::{HashMap, HashSet};
+
+use chrono::{NaiveDateTime, Utc};
+use diesel::{prelude::*, sql_types::Text, Connection as _};
+use serde::{Deserialize, Serialize};
+use tracing::info;
+use uuid::Uuid;
+
+use crate::{
+    admin::AdminError,
+    config::db::Connection,
+    models::{
+        candidate::Candidate,
+        company::Company,
+        duplicate::CandidateMerge,
+        job_offer::JobOffer,
+        skill::{CandidateSkill, JobOfferSkill, Skill},
+        user::RoleType,
+    },
+    schema::{
+        candidate, candidate_merges, candidate_skills, company, job_offer_skills, job_offers,
+        skills, user_recovery_codes, users,
+    },
+    utils::validation::{describe, Validate},
+};
+
+// Bumped when the layout of the dump changes
+pub const DUMP_VERSION: u32 = 1;
+
+// Every column of `users`, the API model hides the second factor secrets
+#[derive(Queryable, Selectable, Insertable, Serialize, Deserialize)]
+#[diesel(table_name = users)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct UserRecord {
+    pub id: Uuid,
+    pub company_id: Option<Uuid>,
+    pub username: String,
+    pub email: String,
+    pub password: Option<String>,
+    pub role: RoleType,
+    pub totp_secret: Option<String>,
+    pub totp_enabled: bool,
+    pub totp_last_step: Option<i64>,
+    pub failed_login_attempts: i32,
+    pub locked_until: Option<NaiveDateTime>,
+}
+
+#[derive(Queryable, Selectable, Insertable, Serialize, Deserialize)]
+#[diesel(table_name = user_recovery_codes)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct RecoveryCodeRecord {
+    pub id: Uuid,
+    pub user_id: Uuid,
+    pub code_hash: String,
+    pub created_at: NaiveDateTime,
+    pub used_at: Option<NaiveDateTime>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct Dump {
+    pub version: u32,
+    pub exported_at: NaiveDateTime,
+    pub companies: Vec<Company>,
+    pub users: Vec<UserRecord>,
+    pub recovery_codes: Vec<RecoveryCodeRecord>,
+    pub job_offers: Vec<JobOffer>,
+    pub candidates: Vec<Candidate>,
+    // Missing from the dumps written before the skills taxonomy
+    #[serde(default)]
+    pub skills: Vec<Skill>,
+    #[serde(default)]
+    pub job_offer_skills: Vec<JobOfferSkill>,
+    #[serde(default)]
+    pub candidate_skills: Vec<CandidateSkill>,
+    // Missing from the dumps written before the merge of duplicate candidates
+    #[serde(default)]
+    pub candidate_merges: Vec<CandidateMerge>,
+}
+
+// Rows inserted per table, rows already present are skipped
+#[derive(Debug, Default, PartialEq, Serialize)]
+pub struct ImportReport {
+    pub companies: usize,
+    pub users: usize,
+    pub recovery_codes: usize,
+    pub job_offers: usize,
+    pub candidates: usize,
+    pub skills: usize,
+    pub job_offer_skills: usize,
+    pub candidate_skills: usize,
+    pub candidate_merges: usize,
+}
+
+// Read in one transaction, so that the dump is consistent
+pub fn export(conn: &mut Connection) -> Result<Dump, AdminError> {
+    let dump = conn.transaction::<_, diesel::result::Error, _>(|conn| {
+        Ok(Dump {
+            version: DUMP_VERSION,
+            exported_at: Utc::now().naive_utc(),
+            companies: company::table.order(company::id).load(conn)?,
+            users: users::table
+                .order(users::id)
+                .select(UserRecord::as_select())
+                .load(conn)?,
+            recovery_codes: user_recovery_codes::table
+                .order(user_recovery_codes::id)
+                .select(RecoveryCodeRecord::as_select())
+                .load(conn)?,
+            job_offers: job_offers::table.order(job_offers::id).load(conn)?,
+            candidates: candidate::table.order(candidate::id).load(conn)?,
+            skills: skills::table.order(skills::id).load(conn)?,
+            job_offer_skills: job_offer_skills::table
+                .order((job_offer_skills::job_offer_id, job_offer_skills::skill_id))
+                .load(conn)?,
+            candidate_skills: candidate_skills::table
+                .order((candidate_skills::candidate_id, candidate_skills::skill_id))
+                .load(conn)?,
+            candidate_merges: candidate_merges::table
+                .order(candidate_merges::id)
+                .select(CandidateMerge::as_select())
+                .load(conn)?,
+        })
+    })?;
+    info!(
+        "Exported {} companies, {} users, {} job offers and {} candidates",
+        dump.companies.len(),
+        dump.users.len(),
+        dump.job_offers.len(),
+        dump.candidates.len()
+    );
+    Ok(dump)
+}
+
+diesel::define_sql_function!(fn lower(x: Text) -> Text);
+
+// Records normalised like the ones of the API, "user {id}: {errors}" for each invalid one
+fn validate_all<T: Validate>(
+    kind: &str,
+    records: Vec<T>,
+    id_of: impl Fn(&T) -> Uuid,
+    invalid: &mut Vec<String>,
+) -> Vec<T> {
+    records
+        .into_iter()
+        .filter_map(|record| {
+            let i = id_of(&record);
+            record
+                .validate()
+                .map_err(|errors| invalid.push(format!("{} {}: {}", kind, i, describe(&errors))))
+                .ok()
+        })
+        .collect()
+}
+
+// All or nothing, nothing is imported when a user or a candidate is invalid. Companies are matched
+// on their id or name and users on their id, username or email, existing rows are kept, so that a
+// dump can be replayed or loaded over the seeded data
+pub fn import(mut dump: Dump, conn: &mut Connection) -> Result<ImportReport, AdminError> {
+    if dump.version != DUMP_VERSION {
+        return Err(format!(
+            "Unsupported dump version {} (expected {})",
+            dump.version, DUMP_VERSION
+        )
+        .into());
+    }
+    let mut invalid = Vec::new();
+    dump.users = validate_all("user", dump.users, |user| user.id, &mut invalid);
+    dump.candidates = validate_all(
+        "candidate",
+        dump.candidates,
+        |application| application.id,
+        &mut invalid,
+    );
+    if !invalid.is_empty() {
+        return Err(format!("Invalid dump: {}", invalid.join("; ")).into());
+    }
+    let report = conn.transaction::<_, diesel::result::Error, _>(|conn| {
+        let mut report = ImportReport::default();
+        // Company of the dump -> company of the database
+        let mut company_ids = HashMap::new();
+        for record in &dump.companies {
+            let existing = company::table
+                .filter(company::id.eq(record.id))
+                .or_filter(company::name.eq(&record.name))
+                .select(company::id)
+                .first::<Uuid>(conn)
+                .optional()?;
+            let target = match existing {
+                Some(existing) => existing,
+                None => {
+                    report.companies += diesel::insert_into(company::table)
+                        .values(record)
+                        .execute(conn)?;
+                    record.id
+                }
+            };
+            company_ids.insert(record.id, target);
+        }
+        let company_of = |i: Uuid| company_ids.get(&i).copied().unwrap_or(i);
+
+        let mut imported_users = HashSet::new();
+        for record in &mut dump.users {
+            let exists = users::table
+                .filter(users::id.eq(record.id))
+                .or_filter(users::username.eq(&record.username))
+                .or_filter(lower(users::email).eq(&record.email))
+                .select(users::id)
+                .first::<Uuid>(conn)
+                .optional()?
+                .is_some();
+            if !exists {
+                record.company_id = record.company_id.map(company_of);
+                report.users += diesel::insert_into(users::table)
+                    .values(&*record)
+                    .execute(conn)?;
+                imported_users.insert(record.id);
+            }
+        }
+        // The recovery codes of a kept account stay the ones of the database
+        let recovery_codes: Vec<&RecoveryCodeRecord> = dump
+            .recovery_codes
+            .iter()
+            .filter(|code| imported_users.contains(&code.user_id))
+            .collect();
+        report.recovery_codes = diesel::insert_into(user_recovery_codes::table)
+            .values(recovery_codes)
+            .on_conflict_do_nothing()
+            .execute(conn)?;
+
+        for offer in &mut dump.job_offers {
+            offer.company_id = company_of(offer.company_id);
+        }
+        report.job_offers = diesel::insert_into(job_offers::table)
+            .values(&dump.job_offers)
+            .on_conflict_do_nothing()
+            .execute(conn)?;
+        for application in &mut dump.candidates {
+            application.company_id = company_of(application.company_id);
+        }
+        report.candidates = diesel::insert_into(candidate::table)
+            .values(&dump.candidates)
+            .on_conflict_do_nothing()
+            .execute(conn)?;
+
+        // Skills are matched on their name, the links follow the skills of the database
+        let mut skill_ids = HashMap::new();
+        for record in &dump.skills {
+            let existing = skills::table
+                .filter(skills::name.eq(&record.name))
+                .select(skills::id)
+                .first::<Uuid>(conn)
+                .optional()?;
+            let target = match existing {
+                Some(existing) => existing,
+                None => {
+                    report.skills += diesel::insert_into(skills::table)
+                        .values(record)
+                        .execute(conn)?;
+                    record.id
+                }
+            };
+            skill_ids.insert(record.id, target);
+        }
+        let skill_of = |i: Uuid| skill_ids.get(&i).copied().unwrap_or(i);
+        for link in &mut dump.job_offer_skills {
+            link.skill_id = skill_of(link.skill_id);
+        }
+        report.job_offer_skills = diesel::insert_into(job_offer_skills::table)
+            .values(&dump.job_offer_skills)
+            .on_conflict_do_nothing()
+            .execute(conn)?;
+        for link in &mut dump.candidate_skills {
+            link.skill_id = skill_of(link.skill_id);
+        }
+        report.candidate_skills = diesel::insert_into(candidate_skills::table)
+            .values(&dump.candidate_skills)
+            .on_conflict_do_nothing()
+            .execute(conn)?;
+
+        // The administrator of a merge is forgotten when the account was not imported
+        let user_ids: HashSet<Uuid> = users::table
+            .select(users::id)
+            .load(conn)?
+            .into_iter()
+            .collect();
+        for merge in &mut dump.candidate_merges {
+            merge.merged_by = merge.merged_by.filter(|i| user_ids.contains(i));
+        }
+        report.candidate_merges = diesel::insert_into(candidate_merges::table)
+            .values(&dump.candidate_merges)
+            .on_conflict_do_nothing()
+            .execute(conn)?;
+        Ok(report)
+    })?;
+    info!("Imported {:?}", report);
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        admin::{
+            create_company, create_user, run_migrations,
+            seed::{apply_profile, Profile},
+        },
+        config::db,
+        models::{
+            candidate::{CandidateDTO, ScanStatus},
+            job_offer::JobOfferDTO,
+        },
+        parsing::skills::SkillDictionary,
+    };
+    use testcontainers::{clients, images::postgres::Postgres};
+
+    #[actix_web::test]
+    async fn test_export_import_round_trip() {
+        let docker = clients::Cli::default();
+        let source = docker.run(Postgres::default());
+        let source_pool = db::test_pool(source.get_host_port_ipv4(5432));
+        let conn = &mut source_pool.get().unwrap();
+        run_migrations(conn).unwrap();
+        apply_profile(Profile::Test, conn).unwrap();
+        let acme = create_company("Acme", conn).unwrap().id();
+        create_user(
+            "jane",
+            "jane@acme.test",
+            "pw",
+            RoleType::User,
+            Some("Acme"),
+            conn,
+        )
+        .unwrap();
+        JobOffer::insert(
+            JobOfferDTO {
+                company_id: acme,
+                title: "Rust developer".to_string(),
+                description: "Backend".to_string(),
+                requirements: None,
+                location: "Lyon".to_string(),
+                remote: None,
+                employment_type: "CDI".to_string(),
+                salary: 50000.0,
+                created_at: Utc::now().naive_utc(),
+                updated_at: None,
+            },
+            conn,
+        )
+        .unwrap();
+        Candidate::insert(
+            CandidateDTO {
+                company_id: acme,
+                lastname: "Doe".to_string(),
+                firstname: "John".to_string(),
+                file_name: "cv.pdf".to_string(),
+                phone: "0612345678".to_string(),
+                email: "john@doe.test".to_string(),
+                motivation: "Hello".to_string(),
+            },
+            conn,
+        )
+        .unwrap();
+        let dictionary = SkillDictionary::parse("- name: Rust\n  category: programming").unwrap();
+        let rust = Skill::upsert(&[dictionary.get("Rust").unwrap()], conn).unwrap();
+        let offer = JobOffer::find_by_company_id(acme, conn).unwrap().remove(0);
+        JobOfferSkill::replace(offer.id, &[(rust[0].clone(), true)], conn).unwrap();
+        let john = Candidate::find_by_company_id(acme, conn).unwrap().remove(0);
+        CandidateSkill::replace(john.id, &["Rust".to_string()], &dictionary, conn).unwrap();
+        let again = Candidate::insert(
+            CandidateDTO {
+                company_id: acme,
+                lastname: "Doe".to_string(),
+                firstname: "John".to_string(),
+                file_name: "cv-again.pdf".to_string(),
+                phone: "+33612345678".to_string(),
+                email: "john@doe.test".to_string(),
+                motivation: "Hello again".to_string(),
+            },
+            conn,
+        )
+        .unwrap();
+        Candidate::record_scan(again.id, ScanStatus::Clean, None, conn).unwrap();
+        Candidate::record_text(again.id, "Rust developer".to_string(), Some(1), conn).unwrap();
+        CandidateMerge::merge(john.id, again.id, None, conn).unwrap();
+
+        let json = serde_json::to_string(&export(conn).unwrap()).unwrap();
+        // The second factor secrets travel with the accounts
+        assert!(json.contains("\"totp_secret\""));
+
+        let target = docker.run(Postgres::default());
+        let target_pool = db::test_pool(target.get_host_port_ipv4(5432));
+        let conn = &mut target_pool.get().unwrap();
+        run_migrations(conn).unwrap();
+        apply_profile(Profile::Test, conn).unwrap();
+        let seeded_companies = Company::find_all(conn).unwrap().len();
+        // The seeded companies and accounts of the target are matched, not duplicated
+        let report = import(serde_json::from_str(&json).unwrap(), conn).unwrap();
+        assert_eq!(report.companies, 1);
+        assert_eq!(report.users, 1);
+        assert_eq!(report.job_offers, 1);
+        assert_eq!(report.candidates, 1);
+        assert_eq!(
+            (
+                report.skills,
+                report.job_offer_skills,
+                report.candidate_skills,
+                report.candidate_merges
+            ),
+            (1, 1, 1, 1)
+        );
+        assert_eq!(
+            Skill::find_by_job_offer(offer.id, conn).unwrap(),
+            vec![(rust[0].clone(), true)]
+        );
+        assert_eq!(Company::find_all(conn).unwrap().len(), seeded_companies + 1);
+        assert_eq!(
+            Company::find_entrprise_by_name("Acme", conn).unwrap().id,
+            acme
+        );
+        let jane = users::table
+            .filter(users::username.eq("jane"))
+            .select(UserRecord::as_select())
+            .get_result(conn)
+            .unwrap();
+        assert_eq!(jane.company_id, Some(acme));
+
+        // Replaying the dump changes nothing
+        assert_eq!(
+            import(serde_json::from_str(&json).unwrap(), conn).unwrap(),
+            ImportReport::default()
+        );
+
+        let mut future: Dump = serde_json::from_str(&json).unwrap();
+        future.version = DUMP_VERSION + 1;
+        assert!(import(future, conn).is_err());
+
+        // An account of another id and username with the address of jane is jane
+        let mut renamed: Dump = serde_json::from_str(&json).unwrap();
+        for user in &mut renamed.users {
+            user.id = Uuid::new_v4();
+            user.username = format!("{}-renamed", user.username);
+            user.email = user.email.to_uppercase();
+        }
+        assert_eq!(import(renamed, conn).unwrap(), ImportReport::default());
+        let mut invalid: Dump = serde_json::from_str(&json).unwrap();
+        invalid.users[0].email = "jane.acme.test".to_string();
+        invalid.candidates[0].phone = "12 34".to_string();
+        let error = import(invalid, conn).err().unwrap().to_string();
+        assert!(error.contains("email: The email address is invalid"));
+        assert!(error.contains("phone: "));
+    }
+}
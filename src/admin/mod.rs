@@ -0,0 +1,466 @@
+// Operational tasks of the `platform-cv-admin` binary, every command is safe to replay
+pub mod data;
+pub mod seed;
+
+use std::{error::Error, io};
+
+use chrono::Utc;
+use diesel::{
+    prelude::*, result::Error as DieselError, sql_query, sql_types::BigInt, Connection as _,
+};
+use diesel_migrations::MigrationHarness;
+use serde::Serialize;
+use tracing::{info, warn};
+use uuid::Uuid;
+
+use crate::{
+    config::db::{Connection, MIGRATIONS},
+    models::{
+        candidate::{Candidate, ScanStatus},
+        company::{Company, CompanyDTO},
+        user::{RoleType, User, UserDTO},
+        user_session::UserSession,
+    },
+    parsing::skills::SkillDictionary,
+    schema::users,
+    storage::{scanner::Scanner, CvError, CvStorage},
+    utils::validation::{self, Validate},
+};
+
+pub type AdminError = Box<dyn Error + Send + Sync>;
+
+// Key of the advisory lock serializing the migrations of replicas started together
+const MIGRATION_LOCK_KEY: i64 = 0x706c_6174_666f_726d;
+
+#[derive(Debug, PartialEq)]
+pub enum Outcome {
+    Created(Uuid),
+    Updated(Uuid),
+    Unchanged(Uuid),
+}
+
+impl Outcome {
+    pub fn id(&self) -> Uuid {
+        match self {
+            Outcome::Created(id) | Outcome::Updated(id) | Outcome::Unchanged(id) => *id,
+        }
+    }
+}
+
+fn find_company_id(name: &str, conn: &mut Connection) -> Result<Uuid, AdminError> {
+    match Company::find_entrprise_by_name(name, conn) {
+        Ok(company) => Ok(company.id),
+        Err(DieselError::NotFound) => Err(format!("Company '{}' not found", name).into()),
+        Err(e) => Err(e.into()),
+    }
+}
+
+fn check_password(password: &str) -> Result<(), AdminError> {
+    if password.trim().is_empty() {
+        return Err("The password must not be empty".into());
+    }
+    Ok(())
+}
+
+// Create the super admin, or reset the password, email and lockout of an existing account.
+// A reset revokes every session, `reset_two_factor` also removes the enrolled authenticator
+pub fn upsert_superadmin(
+    username: &str,
+    email: &str,
+    password: &str,
+    company: Option<&str>,
+    reset_two_factor: bool,
+    conn: &mut Connection,
+) -> Result<Outcome, AdminError> {
+    check_password(password)?;
+    let password_hash = User::hash_password(password)?;
+    conn.transaction(|conn| {
+        let company_id = company
+            .map(|name| find_company_id(name, conn))
+            .transpose()?;
+        let account = UserDTO {
+            username: username.to_string(),
+            company_id,
+            email: email.to_string(),
+            password: Some(password_hash),
+            role: RoleType::SuperAdmin,
+        }
+        .validate()
+        .map_err(|errors| validation::describe(&errors))?;
+        let username = account.username.clone();
+        match User::find_user_by_username(&username, conn).optional()? {
+            Some(user) => {
+                User::update(user.id, account, conn)?;
+                User::unlock(user.id, conn)?;
+                UserSession::revoke_all_for_user(user.id, conn)?;
+                if reset_two_factor {
+                    User::disable_totp(user.id, conn)?;
+                }
+                info!("Super admin {} reset", username);
+                Ok(Outcome::Updated(user.id))
+            }
+            None => {
+                let id = diesel::insert_into(users::table)
+                    .values(&account)
+                    .returning(users::id)
+                    .get_result(conn)?;
+                info!("Super admin {} created", username);
+                Ok(Outcome::Created(id))
+            }
+        }
+    })
+}
+
+pub fn create_company(name: &str, conn: &mut Connection) -> Result<Outcome, AdminError> {
+    if name.trim().is_empty() {
+        return Err("The company name must not be empty".into());
+    }
+    if let Some(company) = Company::find_entrprise_by_name(name, conn).optional()? {
+        return Ok(Outcome::Unchanged(company.id));
+    }
+    Company::insert(
+        CompanyDTO {
+            name: name.to_string(),
+        },
+        conn,
+    )?;
+    info!("Company {} created", name);
+    Ok(Outcome::Created(find_company_id(name, conn)?))
+}
+
+// An existing username is left untouched, passwords are only reset with `upsert_superadmin`
+pub fn create_user(
+    username: &str,
+    email: &str,
+    password: &str,
+    role: RoleType,
+    company: Option<&str>,
+    conn: &mut Connection,
+) -> Result<Outcome, AdminError> {
+    if let Some(user) = User::find_user_by_username(username.trim(), conn).optional()? {
+        return Ok(Outcome::Unchanged(user.id));
+    }
+    check_password(password)?;
+    if role != RoleType::SuperAdmin && company.is_none() {
+        return Err(format!("A company is required for the {} role", role).into());
+    }
+    let company_id = company
+        .map(|name| find_company_id(name, conn))
+        .transpose()?;
+    let account = UserDTO {
+        username: username.to_string(),
+        company_id,
+        email: email.to_string(),
+        password: Some(User::hash_password(password)?),
+        role,
+    }
+    .validate()
+    .map_err(|errors| validation::describe(&errors))?;
+    let id = diesel::insert_into(users::table)
+        .values(&account)
+        .returning(users::id)
+        .get_result(conn)?;
+    info!("User {} created", username);
+    Ok(Outcome::Created(id))
+}
+
+pub fn purge_sessions(conn: &mut Connection) -> Result<usize, AdminError> {
+    let purged = UserSession::purge_expired(Utc::now().naive_utc(), conn)?;
+    info!("{} expired or revoked sessions purged", purged);
+    Ok(purged)
+}
+
+#[derive(Debug, Default, PartialEq, Serialize)]
+pub struct ScanReport {
+    pub clean: usize,
+    pub infected: usize,
+    // Scanner still unavailable, left in quarantine
+    pub pending: usize,
+    // No quarantined file, e.g. seeded candidates
+    pub missing: usize,
+}
+
+// Scan the CVs left in quarantine while the scanner was unavailable
+pub fn scan_pending(
+    storage: &CvStorage,
+    scanner: &dyn Scanner,
+    conn: &mut Connection,
+) -> Result<ScanReport, AdminError> {
+    let mut report = ScanReport::default();
+    for candidate in Candidate::find_pending_scan(conn)? {
+        let content = match storage.read_quarantined(&candidate.file_name) {
+            Ok(content) => content,
+            Err(CvError::InvalidName) => {
+                report.missing += 1;
+                continue;
+            }
+            Err(CvError::Io(e)) if e.kind() == io::ErrorKind::NotFound => {
+                warn!(
+                    "CV of candidate {} missing from the quarantine",
+                    candidate.id
+                );
+                report.missing += 1;
+                continue;
+            }
+            Err(e) => return Err(e.into()),
+        };
+        let outcome = storage.scan(&candidate.file_name, &content, scanner)?;
+        match outcome.status {
+            ScanStatus::Clean => report.clean += 1,
+            ScanStatus::Infected => report.infected += 1,
+            ScanStatus::Pending => report.pending += 1,
+        }
+        Candidate::record_scan(candidate.id, outcome.status, outcome.signature, conn)?;
+    }
+    info!("Quarantined CVs scanned: {:?}", report);
+    Ok(report)
+}
+
+// Parse the extracted CV texts again, e.g. after a change of the skills dictionary. Only the
+// fields still empty are prefilled, the skills of the candidates are replaced, or added to for the
+// candidates with merges
+pub fn parse_cvs(skills: &SkillDictionary, conn: &mut Connection) -> Result<usize, AdminError> {
+    let candidates = Candidate::find_extracted(conn)?;
+    for candidate in &candidates {
+        Candidate::parse_cv(candidate.id, skills, conn)?;
+    }
+    info!("CVs parsed: {}", candidates.len());
+    Ok(candidates.len())
+}
+
+// Run `f` holding the migration lock, replicas started together wait for the first one
+fn with_migration_lock<T>(
+    conn: &mut Connection,
+    f: impl FnOnce(&mut Connection) -> Result<T, AdminError>,
+) -> Result<T, AdminError> {
+    sql_query("SELECT pg_advisory_lock($1)")
+        .bind::<BigInt, _>(MIGRATION_LOCK_KEY)
+        .execute(conn)?;
+    let result = f(conn);
+    sql_query("SELECT pg_advisory_unlock($1)")
+        .bind::<BigInt, _>(MIGRATION_LOCK_KEY)
+        .execute(conn)?;
+    result
+}
+
+// Versions of the migrations applied
+pub fn run_migrations(conn: &mut Connection) -> Result<Vec<String>, AdminError> {
+    with_migration_lock(conn, |conn| {
+        let applied = conn.run_pending_migrations(MIGRATIONS)?;
+        Ok(applied.iter().map(ToString::to_string).collect())
+    })
+}
+
+// Versions of the migrations reverted, the most recent first
+pub fn revert_migrations(steps: usize, conn: &mut Connection) -> Result<Vec<String>, AdminError> {
+    with_migration_lock(conn, |conn| {
+        let mut reverted = Vec::new();
+        for _ in 0..steps {
+            if conn.applied_migrations()?.is_empty() {
+                break;
+            }
+            reverted.push(conn.revert_last_migration(MIGRATIONS)?.to_string());
+        }
+        Ok(reverted)
+    })
+}
+
+pub fn pending_migrations(conn: &mut Connection) -> Result<Vec<String>, AdminError> {
+    let pending = conn.pending_migrations(MIGRATIONS)?;
+    Ok(pending
+        .iter()
+        .map(|migration| migration.name().to_string())
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        config::db,
+        models::candidate::CandidateDTO,
+        storage::{scanner::ScanVerdict, validation::SizeLimits},
+    };
+    use testcontainers::{clients, images::postgres::Postgres};
+
+    #[actix_web::test]
+    async fn test_accounts_and_companies_are_idempotent() {
+        let docker = clients::Cli::default();
+        let postgres = docker.run(Postgres::default());
+        let pool = db::test_pool(postgres.get_host_port_ipv4(5432));
+        let conn = &mut pool.get().unwrap();
+        run_migrations(conn).unwrap();
+        assert!(pending_migrations(conn).unwrap().is_empty());
+
+        let company = create_company("Acme", conn).unwrap();
+        assert!(matches!(company, Outcome::Created(_)));
+        assert_eq!(
+            create_company("Acme", conn).unwrap(),
+            Outcome::Unchanged(company.id())
+        );
+
+        let created =
+            upsert_superadmin("superadmin", "root@acme.test", "pw", None, false, conn).unwrap();
+        assert!(matches!(created, Outcome::Created(_)));
+        // An existing account is reset, not duplicated
+        let superadmin = upsert_superadmin(
+            "superadmin",
+            "ops@acme.test",
+            "first-password",
+            Some("Acme"),
+            true,
+            conn,
+        )
+        .unwrap();
+        assert_eq!(superadmin, Outcome::Updated(created.id()));
+        let user = User::find_by_id(superadmin.id(), conn).unwrap();
+        assert_eq!(user.email, "ops@acme.test");
+        assert_eq!(user.company_id, Some(company.id()));
+        assert!(bcrypt::verify("first-password", user.password.as_deref().unwrap()).unwrap());
+        assert!(upsert_superadmin("superadmin", "ops@acme.test", " ", None, false, conn).is_err());
+
+        assert!(create_user("jane", "jane@acme.test", "pw", RoleType::Admin, None, conn).is_err());
+        assert!(create_user(
+            "jane",
+            "jane@acme.test",
+            "pw",
+            RoleType::Admin,
+            Some("Nope"),
+            conn
+        )
+        .is_err());
+        let jane = create_user(
+            "jane",
+            "jane@acme.test",
+            "pw",
+            RoleType::Admin,
+            Some("Acme"),
+            conn,
+        )
+        .unwrap();
+        assert!(matches!(jane, Outcome::Created(_)));
+        assert_eq!(
+            create_user(
+                "jane",
+                "jane@acme.test",
+                "other",
+                RoleType::User,
+                Some("Acme"),
+                conn
+            )
+            .unwrap(),
+            Outcome::Unchanged(jane.id())
+        );
+        assert_eq!(
+            User::find_by_id(jane.id(), conn).unwrap().role,
+            RoleType::Admin
+        );
+
+        let reverted = revert_migrations(1, conn).unwrap();
+        assert_eq!(reverted.len(), 1);
+        assert_eq!(pending_migrations(conn).unwrap().len(), 1);
+        assert_eq!(run_migrations(conn).unwrap(), reverted);
+    }
+
+    // Reports the files containing `EICAR` as infected
+    struct EicarScanner;
+
+    impl Scanner for EicarScanner {
+        fn scan(&self, content: &[u8]) -> Result<ScanVerdict, crate::storage::scanner::ScanError> {
+            if content.windows(5).any(|window| window == b"EICAR") {
+                Ok(ScanVerdict::Infected {
+                    signature: "Eicar-Signature".to_string(),
+                })
+            } else {
+                Ok(ScanVerdict::Clean)
+            }
+        }
+    }
+
+    #[actix_web::test]
+    async fn test_scan_pending() {
+        let docker = clients::Cli::default();
+        let postgres = docker.run(Postgres::default());
+        let pool = db::test_pool(postgres.get_host_port_ipv4(5432));
+        let conn = &mut pool.get().unwrap();
+        run_migrations(conn).unwrap();
+        let company = create_company("Acme", conn).unwrap().id();
+        let root = std::env::temp_dir().join(format!("platform-cv-scan-{}", Uuid::new_v4()));
+        let storage = CvStorage::local(
+            &root,
+            SizeLimits {
+                document: 4096,
+                image: 4096,
+            },
+        );
+        let apply = |content: &[u8], conn: &mut Connection| {
+            let stored = storage.store(company, content).unwrap();
+            Candidate::insert(
+                CandidateDTO {
+                    company_id: company,
+                    lastname: "Doe".to_string(),
+                    firstname: "Jane".to_string(),
+                    file_name: stored.file_name,
+                    phone: "0612345678".to_string(),
+                    email: format!("{}@doe.test", Uuid::new_v4()),
+                    motivation: "Hello".to_string(),
+                },
+                conn,
+            )
+            .unwrap()
+        };
+        let clean = apply(b"%PDF-1.4\n%%EOF", conn);
+        let infected = apply(b"%PDF-1.4\nEICAR\n%%EOF", conn);
+        assert_eq!(clean.cv_scan_status, ScanStatus::Pending);
+
+        let report = scan_pending(&storage, &EicarScanner, conn).unwrap();
+        assert_eq!(
+            report,
+            ScanReport {
+                clean: 1,
+                infected: 1,
+                ..Default::default()
+            }
+        );
+        let clean = Candidate::find_by_id(clean.id, conn).unwrap();
+        assert_eq!(clean.cv_scan_status, ScanStatus::Clean);
+        assert!(clean.cv_scanned_at.is_some());
+        assert_eq!(storage.backend().list().unwrap(), vec![clean.file_name]);
+        let infected = Candidate::find_by_id(infected.id, conn).unwrap();
+        assert_eq!(infected.cv_scan_status, ScanStatus::Infected);
+        assert_eq!(
+            infected.cv_scan_signature.as_deref(),
+            Some("Eicar-Signature")
+        );
+
+        // Nothing left to scan
+        assert_eq!(
+            scan_pending(&storage, &EicarScanner, conn).unwrap(),
+            ScanReport::default()
+        );
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[actix_web::test]
+    async fn test_purge_sessions() {
+        let docker = clients::Cli::default();
+        let postgres = docker.run(Postgres::default());
+        let pool = db::test_pool(postgres.get_host_port_ipv4(5432));
+        let conn = &mut pool.get().unwrap();
+        run_migrations(conn).unwrap();
+        seed::apply_profile(seed::Profile::Test, conn).unwrap();
+        let user = User::find_user_by_username("user", conn).unwrap();
+
+        let client = Default::default();
+        UserSession::create(user.id, "active", &client, 3600, conn).unwrap();
+        UserSession::create(user.id, "expired", &client, -1, conn).unwrap();
+        let revoked = UserSession::create(user.id, "revoked", &client, 3600, conn).unwrap();
+        UserSession::revoke(revoked.id, user.id, conn).unwrap();
+
+        assert_eq!(purge_sessions(conn).unwrap(), 2);
+        let remaining = UserSession::find_active_by_user_id(user.id, conn).unwrap();
+        assert_eq!(remaining.len(), 1);
+        assert_eq!(remaining[0].session_token, "active");
+        assert_eq!(purge_sessions(conn).unwrap(), 0);
+    }
+}
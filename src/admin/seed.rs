@@ -0,0 +1,404 @@
+// Seed data, kept out of the migrations: fixtures of `seeds/` loaded by `platform-cv-admin seed`.
+// Rows are matched on a natural key (company name, username, offer title, candidate email)
+// and updated in place, so that a profile can be loaded again after editing it
+use std::{fmt, str::FromStr};
+
+use chrono::Utc;
+use diesel::{prelude::*, Connection as _};
+use serde::{Deserialize, Serialize};
+use tracing::info;
+use uuid::Uuid;
+
+use crate::{
+    admin::{create_company, AdminError, Outcome},
+    config::db::Connection,
+    models::{
+        candidate::{Candidate, CandidateDTO},
+        job_offer::{JobOffer, JobOfferDTO},
+        user::{RoleType, User, UserDTO},
+    },
+    schema::{candidate, company, job_offers},
+    utils::validation::{self, Validate},
+};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Profile {
+    Dev,
+    Demo,
+    Test,
+}
+
+impl Profile {
+    // Embedded, the binary can seed a database without the repository
+    pub fn fixture(&self) -> &'static str {
+        match self {
+            Profile::Dev => include_str!("../../seeds/dev.yaml"),
+            Profile::Demo => include_str!("../../seeds/demo.yaml"),
+            Profile::Test => include_str!("../../seeds/test.yaml"),
+        }
+    }
+}
+
+impl FromStr for Profile {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Profile, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "dev" | "development" => Ok(Profile::Dev),
+            "demo" => Ok(Profile::Demo),
+            "test" => Ok(Profile::Test),
+            _ => Err(format!("'{}' is not a seed profile (dev, demo, test)", s)),
+        }
+    }
+}
+
+impl fmt::Display for Profile {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}",
+            match self {
+                Profile::Dev => "dev",
+                Profile::Demo => "demo",
+                Profile::Test => "test",
+            }
+        )
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Fixture {
+    #[serde(default)]
+    pub companies: Vec<CompanySeed>,
+    #[serde(default)]
+    pub users: Vec<UserSeed>,
+    #[serde(default)]
+    pub job_offers: Vec<JobOfferSeed>,
+    #[serde(default)]
+    pub candidates: Vec<CandidateSeed>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct CompanySeed {
+    pub name: String,
+}
+
+// Plain password, hashed when loaded
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct UserSeed {
+    pub username: String,
+    pub email: String,
+    pub password: String,
+    pub role: RoleType,
+    pub company: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct JobOfferSeed {
+    pub company: String,
+    pub title: String,
+    pub description: String,
+    pub requirements: Option<String>,
+    pub location: String,
+    pub remote: Option<String>,
+    pub employment_type: String,
+    pub salary: f64,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct CandidateSeed {
+    pub company: String,
+    pub lastname: String,
+    pub firstname: String,
+    pub file_name: String,
+    pub phone: String,
+    pub email: String,
+    pub motivation: String,
+}
+
+#[derive(Debug, Default, PartialEq, Serialize)]
+pub struct Tally {
+    pub created: usize,
+    pub updated: usize,
+    pub unchanged: usize,
+}
+
+impl Tally {
+    fn count(&mut self, outcome: &Outcome) {
+        match outcome {
+            Outcome::Created(_) => self.created += 1,
+            Outcome::Updated(_) => self.updated += 1,
+            Outcome::Unchanged(_) => self.unchanged += 1,
+        }
+    }
+}
+
+#[derive(Debug, Default, PartialEq, Serialize)]
+pub struct SeedReport {
+    pub companies: Tally,
+    pub users: Tally,
+    pub job_offers: Tally,
+    pub candidates: Tally,
+}
+
+// YAML, JSON documents being valid YAML as well
+pub fn parse(source: &str) -> Result<Fixture, AdminError> {
+    serde_yaml::from_str(source).map_err(|e| format!("Invalid fixture: {}", e).into())
+}
+
+fn company_id(name: &str, conn: &mut Connection) -> Result<Uuid, AdminError> {
+    company::table
+        .filter(company::name.eq(name))
+        .select(company::id)
+        .first(conn)
+        .optional()?
+        .ok_or_else(|| format!("Company '{}' is not declared in the fixture", name).into())
+}
+
+fn upsert_user(seed: &UserSeed, conn: &mut Connection) -> Result<Outcome, AdminError> {
+    let i_company = seed
+        .company
+        .as_deref()
+        .map(|name| company_id(name, conn))
+        .transpose()?;
+    // Checked on the seed, the password is hashed once it is known to be needed
+    let checked = UserDTO {
+        username: seed.username.clone(),
+        company_id: i_company,
+        email: seed.email.clone(),
+        password: None,
+        role: seed.role.clone(),
+    }
+    .validate()
+    .map_err(|errors| {
+        format!(
+            "User '{}': {}",
+            seed.username,
+            validation::describe(&errors)
+        )
+    })?;
+    let existing = User::find_user_by_username(&checked.username, conn).optional()?;
+    if let Some(user) = &existing {
+        // bcrypt salts every hash, the stored one is kept while the password still matches
+        let same_password = user
+            .password
+            .as_deref()
+            .is_some_and(|stored| bcrypt::verify(&seed.password, stored).unwrap_or(false));
+        if same_password
+            && user.email == checked.email
+            && user.role == checked.role
+            && user.company_id == i_company
+        {
+            return Ok(Outcome::Unchanged(user.id));
+        }
+    }
+    let username = checked.username.clone();
+    let account = UserDTO {
+        password: Some(User::hash_password(&seed.password)?),
+        ..checked
+    };
+    match existing {
+        Some(user) => {
+            User::update(user.id, account, conn)?;
+            Ok(Outcome::Updated(user.id))
+        }
+        None => {
+            User::insert(account, conn)?;
+            Ok(Outcome::Created(
+                User::find_user_by_username(&username, conn)?.id,
+            ))
+        }
+    }
+}
+
+fn upsert_job_offer(seed: &JobOfferSeed, conn: &mut Connection) -> Result<Outcome, AdminError> {
+    let i_company = company_id(&seed.company, conn)?;
+    let existing = job_offers::table
+        .filter(job_offers::company_id.eq(i_company))
+        .filter(job_offers::title.eq(&seed.title))
+        .first::<JobOffer>(conn)
+        .optional()?;
+    let offer = JobOfferDTO {
+        company_id: i_company,
+        title: seed.title.clone(),
+        description: seed.description.clone(),
+        requirements: seed.requirements.clone(),
+        location: seed.location.clone(),
+        remote: seed.remote.clone(),
+        employment_type: seed.employment_type.clone(),
+        salary: seed.salary,
+        created_at: existing
+            .as_ref()
+            .map_or_else(|| Utc::now().naive_utc(), |offer| offer.created_at),
+        updated_at: None,
+    };
+    match existing {
+        Some(current)
+            if current.description == offer.description
+                && current.requirements == offer.requirements
+                && current.location == offer.location
+                && current.remote == offer.remote
+                && current.employment_type == offer.employment_type
+                && current.salary == offer.salary =>
+        {
+            Ok(Outcome::Unchanged(current.id))
+        }
+        Some(current) => {
+            JobOffer::update(current.id, offer, conn)?;
+            Ok(Outcome::Updated(current.id))
+        }
+        None => {
+            let id = diesel::insert_into(job_offers::table)
+                .values(&offer)
+                .returning(job_offers::id)
+                .get_result(conn)?;
+            Ok(Outcome::Created(id))
+        }
+    }
+}
+
+fn upsert_candidate(seed: &CandidateSeed, conn: &mut Connection) -> Result<Outcome, AdminError> {
+    let i_company = company_id(&seed.company, conn)?;
+    let application = CandidateDTO {
+        company_id: i_company,
+        lastname: seed.lastname.clone(),
+        firstname: seed.firstname.clone(),
+        file_name: seed.file_name.clone(),
+        phone: seed.phone.clone(),
+        email: seed.email.clone(),
+        motivation: seed.motivation.clone(),
+    }
+    .validate()
+    .map_err(|errors| {
+        format!(
+            "Candidate '{}': {}",
+            seed.email,
+            validation::describe(&errors)
+        )
+    })?;
+    let existing = candidate::table
+        .filter(candidate::company_id.eq(i_company))
+        .filter(candidate::email.eq(&application.email))
+        .first::<Candidate>(conn)
+        .optional()?;
+    match existing {
+        Some(current)
+            if current.lastname == application.lastname
+                && current.firstname == application.firstname
+                && current.file_name == application.file_name
+                && current.phone == application.phone
+                && current.motivation == application.motivation =>
+        {
+            Ok(Outcome::Unchanged(current.id))
+        }
+        Some(current) => {
+            Candidate::update(current.id, application, conn)?;
+            Ok(Outcome::Updated(current.id))
+        }
+        None => {
+            let id = diesel::insert_into(candidate::table)
+                .values(&application)
+                .returning(candidate::id)
+                .get_result(conn)?;
+            Ok(Outcome::Created(id))
+        }
+    }
+}
+
+// All or nothing, a fixture referencing an unknown company leaves the database untouched
+pub fn apply(fixture: &Fixture, conn: &mut Connection) -> Result<SeedReport, AdminError> {
+    let report = conn.transaction(|conn| {
+        let mut report = SeedReport::default();
+        for seed in &fixture.companies {
+            report.companies.count(&create_company(&seed.name, conn)?);
+        }
+        for seed in &fixture.users {
+            report.users.count(&upsert_user(seed, conn)?);
+        }
+        for seed in &fixture.job_offers {
+            report.job_offers.count(&upsert_job_offer(seed, conn)?);
+        }
+        for seed in &fixture.candidates {
+            report.candidates.count(&upsert_candidate(seed, conn)?);
+        }
+        Ok::<_, AdminError>(report)
+    })?;
+    info!("Seeded {:?}", report);
+    Ok(report)
+}
+
+pub fn apply_profile(profile: Profile, conn: &mut Connection) -> Result<SeedReport, AdminError> {
+    apply(&parse(profile.fixture())?, conn)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{admin::run_migrations, config::db, models::company::Company};
+    use testcontainers::{clients, images::postgres::Postgres};
+
+    #[actix_web::test]
+    async fn test_profiles_parse() {
+        for profile in [Profile::Dev, Profile::Demo, Profile::Test] {
+            let fixture = parse(profile.fixture()).unwrap();
+            assert!(fixture
+                .users
+                .iter()
+                .any(|user| user.role == RoleType::SuperAdmin));
+            assert_eq!(profile.to_string().parse::<Profile>().unwrap(), profile);
+        }
+        assert!(parse("companies:\n  - name: Acme\n    city: Lyon\n").is_err());
+        // JSON fixtures go through the same parser
+        let fixture = parse(r#"{"companies": [{"name": "Acme"}]}"#).unwrap();
+        assert_eq!(fixture.companies[0].name, "Acme");
+    }
+
+    #[actix_web::test]
+    async fn test_apply_is_idempotent() {
+        let docker = clients::Cli::default();
+        let postgres = docker.run(Postgres::default());
+        let pool = db::test_pool(postgres.get_host_port_ipv4(5432));
+        let conn = &mut pool.get().unwrap();
+        run_migrations(conn).unwrap();
+        // Migrations create no account, the seeded super admin of the early ones is removed
+        assert_eq!(Company::find_all(conn).unwrap().len(), 9);
+        assert!(User::find_all(conn).unwrap().is_empty());
+
+        let report = apply_profile(Profile::Dev, conn).unwrap();
+        assert_eq!(report.companies.created, 2);
+        assert_eq!(report.users.created, 4);
+        assert_eq!(report.job_offers.created, 1);
+        assert_eq!(report.candidates.created, 1);
+
+        let report = apply_profile(Profile::Dev, conn).unwrap();
+        assert_eq!(report.users.unchanged, 4);
+        assert_eq!(report.job_offers.unchanged, 1);
+        assert_eq!(report.candidates.unchanged, 1);
+        assert_eq!(report.users.created + report.users.updated, 0);
+
+        let edited = parse(
+            "users:\n  - username: acme-admin\n    email: boss@acme.test\n    password: acme-admin\n    role: admin\n    company: Acme\n",
+        )
+        .unwrap();
+        assert_eq!(apply(&edited, conn).unwrap().users.updated, 1);
+        assert_eq!(
+            User::find_user_by_username("acme-admin", conn)
+                .unwrap()
+                .email,
+            "boss@acme.test"
+        );
+
+        // Nothing is written when a row references an undeclared company
+        let invalid = parse(
+            "companies:\n  - name: Umbrella\njob_offers:\n  - company: Nope\n    title: T\n    description: D\n    location: L\n    employment_type: CDI\n    salary: 1\n",
+        )
+        .unwrap();
+        assert!(apply(&invalid, conn).is_err());
+        assert!(Company::find_entrprise_by_name("Umbrella", conn).is_err());
+    }
+}
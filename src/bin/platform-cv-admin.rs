@@ -0,0 +1,471 @@
+// Operational commands, see README "Admin CLI".
+//
+// Meant for container entrypoints: the database is awaited with the retry settings of the
+// server, every command can be replayed, passwords never go through the command line, logs go
+// to stderr and any failure exits with a non-zero status.
+use std::{
+    fs::{self, OpenOptions},
+    io::{self, Read, Write},
+    os::unix::fs::OpenOptionsExt,
+    path::PathBuf,
+    process::ExitCode,
+};
+
+use clap::{Args, Parser, Subcommand};
+use platform_cv::{
+    admin::{
+        self, data,
+        seed::{self, Profile},
+        AdminError, Outcome,
+    },
+    config::{
+        db,
+        settings::{Settings, StorageBackend},
+    },
+    models::user::RoleType,
+    parsing::skills::SkillDictionary,
+    storage::{backend, scanner, CvStorage},
+    utils::logging::{self, RedactingWriter},
+};
+use tracing::error;
+
+// Variable read when neither `--password-file` nor `--password-stdin` is given
+const PASSWORD_ENV: &str = "PLATFORM_CV_ADMIN_PASSWORD";
+
+#[derive(Parser)]
+#[command(
+    name = "platform-cv-admin",
+    version,
+    about = "Platform CV operational tasks"
+)]
+struct Cli {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Create the super admin, or reset its password, email and lockout when it exists
+    Superadmin {
+        #[arg(long)]
+        username: String,
+        #[arg(long)]
+        email: String,
+        /// Name of an existing company
+        #[arg(long)]
+        company: Option<String>,
+        /// Also remove the enrolled authenticator and recovery codes
+        #[arg(long)]
+        reset_two_factor: bool,
+        #[command(flatten)]
+        password: PasswordSource,
+    },
+    /// Manage companies
+    #[command(subcommand)]
+    Company(CompanyCommand),
+    /// Manage users
+    #[command(subcommand)]
+    User(UserCommand),
+    /// Apply, revert or list the database migrations
+    #[command(subcommand)]
+    Migrate(MigrateCommand),
+    /// Delete the expired and revoked sessions
+    PurgeSessions,
+    /// Scan the CVs left in quarantine while the malware scanner was unavailable
+    ScanPending,
+    /// Parse the extracted CV texts again, e.g. after editing the skills dictionary
+    ParseCvs,
+    /// Manage the CV files storage
+    #[command(subcommand)]
+    Storage(StorageCommand),
+    /// Load the fixtures of a profile (dev, demo, test) or of a YAML/JSON file
+    Seed {
+        #[arg(required_unless_present = "file", conflicts_with = "file")]
+        profile: Option<Profile>,
+        #[arg(long)]
+        file: Option<PathBuf>,
+    },
+    /// Write the companies, users, job offers and candidates as JSON
+    Export {
+        /// Created with 0600 permissions, `-` for stdout
+        #[arg(long, short)]
+        output: PathBuf,
+    },
+    /// Load a file written by `export`, rows already present are kept
+    Import {
+        /// `-` for stdin
+        #[arg(long, short)]
+        input: PathBuf,
+    },
+}
+
+#[derive(Subcommand)]
+enum CompanyCommand {
+    /// Create a company, nothing is done when the name exists
+    Create { name: String },
+}
+
+#[derive(Subcommand)]
+enum UserCommand {
+    /// Create a user, nothing is done when the username exists
+    Create {
+        #[arg(long)]
+        username: String,
+        #[arg(long)]
+        email: String,
+        /// superadmin, admin or user
+        #[arg(long, value_parser = |role: &str| role.parse::<RoleType>())]
+        role: RoleType,
+        /// Name of an existing company, required unless the role is superadmin
+        #[arg(long)]
+        company: Option<String>,
+        #[command(flatten)]
+        password: PasswordSource,
+    },
+}
+
+#[derive(Subcommand)]
+enum MigrateCommand {
+    /// Apply the pending migrations
+    Run,
+    /// Revert the latest migrations
+    Revert {
+        #[arg(long, default_value_t = 1)]
+        steps: usize,
+    },
+    /// List the migrations not applied yet
+    Pending,
+}
+
+#[derive(Subcommand)]
+enum StorageCommand {
+    /// Copy the CV files missing from a backend, both read from the `storage` settings
+    Migrate {
+        /// local or s3
+        #[arg(long, value_parser = |backend: &str| backend.parse::<StorageBackend>())]
+        from: StorageBackend,
+        #[arg(long, value_parser = |backend: &str| backend.parse::<StorageBackend>())]
+        to: StorageBackend,
+        /// Delete each file from the source once copied and read back identical
+        #[arg(long)]
+        delete_source: bool,
+    },
+}
+
+#[derive(Args)]
+struct PasswordSource {
+    /// File holding the password, e.g. a docker secret
+    #[arg(long, conflicts_with = "password_stdin")]
+    password_file: Option<PathBuf>,
+    /// Read the password from stdin
+    #[arg(long)]
+    password_stdin: bool,
+}
+
+impl PasswordSource {
+    fn read(&self) -> Result<String, AdminError> {
+        let password = if let Some(path) = &self.password_file {
+            fs::read_to_string(path)
+                .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?
+        } else if self.password_stdin {
+            let mut password = String::new();
+            io::stdin().read_to_string(&mut password)?;
+            password
+        } else {
+            std::env::var(PASSWORD_ENV).map_err(|_| {
+                format!(
+                    "No password given, use --password-file, --password-stdin or {}",
+                    PASSWORD_ENV
+                )
+            })?
+        };
+        // Secret files and here-strings end with a newline
+        Ok(password.trim_end_matches(['\r', '\n']).to_string())
+    }
+}
+
+fn print_outcome(kind: &str, name: &str, outcome: &Outcome) {
+    let status = match outcome {
+        Outcome::Created(_) => "created",
+        Outcome::Updated(_) => "updated",
+        Outcome::Unchanged(_) => "already exists",
+    };
+    println!("{} {} {}: {}", kind, name, status, outcome.id());
+}
+
+fn print_lines(lines: &[String], empty: &str) {
+    if lines.is_empty() {
+        println!("{}", empty);
+    }
+    for line in lines {
+        println!("{}", line);
+    }
+}
+
+fn export(output: &PathBuf, conn: &mut db::Connection) -> Result<(), AdminError> {
+    let dump = data::export(conn)?;
+    // Password hashes and TOTP secrets, only the owner may read the file
+    let mut writer: Box<dyn Write> = if output.as_os_str() == "-" {
+        Box::new(io::stdout().lock())
+    } else {
+        Box::new(
+            OpenOptions::new()
+                .write(true)
+                .create(true)
+                .truncate(true)
+                .mode(0o600)
+                .open(output)
+                .map_err(|e| format!("Failed to create {}: {}", output.display(), e))?,
+        )
+    };
+    serde_json::to_writer_pretty(&mut writer, &dump)?;
+    writer.flush()?;
+    Ok(())
+}
+
+fn import(input: &PathBuf, conn: &mut db::Connection) -> Result<(), AdminError> {
+    let dump = if input.as_os_str() == "-" {
+        serde_json::from_reader(io::stdin().lock())?
+    } else {
+        let file = fs::File::open(input)
+            .map_err(|e| format!("Failed to open {}: {}", input.display(), e))?;
+        serde_json::from_reader(io::BufReader::new(file))?
+    };
+    let report = data::import(dump, conn)?;
+    println!("{}", serde_json::to_string(&report)?);
+    Ok(())
+}
+
+fn storage(command: StorageCommand, settings: &Settings) -> Result<(), AdminError> {
+    match command {
+        StorageCommand::Migrate {
+            from,
+            to,
+            delete_source,
+        } => {
+            if from == to {
+                return Err("--from and --to must be different backends".into());
+            }
+            let report = backend::migrate(
+                backend::open(from, settings).as_ref(),
+                backend::open(to, settings).as_ref(),
+                delete_source,
+            )?;
+            println!("{}", serde_json::to_string(&report)?);
+        }
+    }
+    Ok(())
+}
+
+fn execute(command: Command, settings: &Settings) -> Result<(), AdminError> {
+    // Files only, the database is not needed
+    let command = match command {
+        Command::Storage(command) => return storage(command, settings),
+        command => command,
+    };
+    let mut database = settings.database.clone();
+    // A single connection, the pool minimum of the server is not needed here
+    database.pool_size = 1;
+    database.min_idle = None;
+    let pool = db::connect_with_retry(&database)?;
+    let conn = &mut pool.get()?;
+
+    match command {
+        Command::Superadmin {
+            username,
+            email,
+            company,
+            reset_two_factor,
+            password,
+        } => {
+            let outcome = admin::upsert_superadmin(
+                &username,
+                &email,
+                &password.read()?,
+                company.as_deref(),
+                reset_two_factor,
+                conn,
+            )?;
+            print_outcome("Super admin", &username, &outcome);
+        }
+        Command::Company(CompanyCommand::Create { name }) => {
+            let outcome = admin::create_company(&name, conn)?;
+            print_outcome("Company", &name, &outcome);
+        }
+        Command::User(UserCommand::Create {
+            username,
+            email,
+            role,
+            company,
+            password,
+        }) => {
+            let outcome = admin::create_user(
+                &username,
+                &email,
+                &password.read()?,
+                role,
+                company.as_deref(),
+                conn,
+            )?;
+            print_outcome("User", &username, &outcome);
+        }
+        Command::Migrate(MigrateCommand::Run) => {
+            print_lines(&admin::run_migrations(conn)?, "No pending migration");
+        }
+        Command::Migrate(MigrateCommand::Revert { steps }) => {
+            print_lines(
+                &admin::revert_migrations(steps, conn)?,
+                "No migration to revert",
+            );
+        }
+        Command::Migrate(MigrateCommand::Pending) => {
+            print_lines(&admin::pending_migrations(conn)?, "No pending migration");
+        }
+        Command::PurgeSessions => {
+            println!("{} sessions purged", admin::purge_sessions(conn)?);
+        }
+        Command::ScanPending => {
+            let report = admin::scan_pending(
+                &CvStorage::from_settings(settings),
+                scanner::from_settings(&settings.scanner).as_ref(),
+                conn,
+            )?;
+            println!("{}", serde_json::to_string(&report)?);
+        }
+        Command::ParseCvs => {
+            let skills = SkillDictionary::from_settings(&settings.extraction)?;
+            println!("{} CVs parsed", admin::parse_cvs(&skills, conn)?);
+        }
+        Command::Storage(_) => unreachable!("storage commands run without a database"),
+        Command::Seed { profile, file } => {
+            let fixture = match (profile, file) {
+                (Some(profile), _) => seed::parse(profile.fixture())?,
+                (None, Some(path)) => seed::parse(
+                    &fs::read_to_string(&path)
+                        .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?,
+                )?,
+                (None, None) => unreachable!("clap requires a profile or a file"),
+            };
+            println!("{}", serde_json::to_string(&seed::apply(&fixture, conn)?)?);
+        }
+        Command::Export { output } => export(&output, conn)?,
+        Command::Import { input } => import(&input, conn)?,
+    }
+    Ok(())
+}
+
+fn main() -> ExitCode {
+    let cli = Cli::parse();
+    dotenv::dotenv().ok();
+    logging::init_with_writer(RedactingWriter::STDERR);
+
+    let result = Settings::load()
+        .map_err(AdminError::from)
+        .and_then(|settings| execute(cli.command, &settings));
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            error!("❌ {}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::CommandFactory;
+
+    #[test]
+    fn test_cli_arguments() {
+        Cli::command().debug_assert();
+
+        let cli = Cli::try_parse_from(["platform-cv-admin", "seed", "demo"]).unwrap();
+        assert!(matches!(
+            cli.command,
+            Command::Seed {
+                profile: Some(Profile::Demo),
+                file: None
+            }
+        ));
+        assert!(Cli::try_parse_from(["platform-cv-admin", "seed"]).is_err());
+        assert!(Cli::try_parse_from(["platform-cv-admin", "seed", "production"]).is_err());
+
+        let cli = Cli::try_parse_from([
+            "platform-cv-admin",
+            "storage",
+            "migrate",
+            "--from",
+            "local",
+            "--to",
+            "S3",
+            "--delete-source",
+        ])
+        .unwrap();
+        assert!(matches!(
+            cli.command,
+            Command::Storage(StorageCommand::Migrate {
+                from: StorageBackend::Local,
+                to: StorageBackend::S3,
+                delete_source: true
+            })
+        ));
+        assert!(Cli::try_parse_from([
+            "platform-cv-admin",
+            "storage",
+            "migrate",
+            "--from",
+            "local",
+            "--to",
+            "ftp",
+        ])
+        .is_err());
+
+        let cli = Cli::try_parse_from([
+            "platform-cv-admin",
+            "user",
+            "create",
+            "--username",
+            "jane",
+            "--email",
+            "jane@acme.test",
+            "--role",
+            "Admin",
+            "--company",
+            "Acme",
+        ])
+        .unwrap();
+        assert!(matches!(
+            cli.command,
+            Command::User(UserCommand::Create {
+                role: RoleType::Admin,
+                ..
+            })
+        ));
+
+        // Passwords never go through the command line
+        assert!(Cli::try_parse_from([
+            "platform-cv-admin",
+            "superadmin",
+            "--username",
+            "root",
+            "--email",
+            "root@acme.test",
+            "--password",
+            "secret",
+        ])
+        .is_err());
+        assert!(Cli::try_parse_from([
+            "platform-cv-admin",
+            "user",
+            "create",
+            "--username",
+            "jane",
+            "--email",
+            "jane@acme.test",
+            "--role",
+            "owner",
+        ])
+        .is_err());
+    }
+}
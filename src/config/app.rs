@@ -1,16 +1,142 @@
 use actix_files::Files;
+use actix_multipart::form::MultipartFormConfig;
 use actix_web::web;
-use log::info;
+use tracing::info;
+
+use crate::{controller::*, error::AppError};
+
+// Limits of multipart forms, whose errors are answered with problem details as well
+pub fn multipart_config(limit: usize) -> MultipartFormConfig {
+    MultipartFormConfig::default()
+        .total_limit(limit)
+        .memory_limit(limit)
+        .error_handler(|err, _| AppError::from(err).into())
+}
 
-use crate::controller::*;
 //Config server
 pub fn config_services(conf: &mut web::ServiceConfig) {
     info!("Configuring routes...");
-    conf.service(
-        web::resource("/health-check").route(web::get().to(front_controller::health_check)),
-    )
-    .service(web::resource("/").route(web::get().to(front_controller::homepage)))
-    .service(Files::new("/uploads", "uploads").show_files_listing())
-    .service(Files::new("/assets", "assets").show_files_listing())
-    .default_service(web::to(front_controller::handler_404));
+    // Extractor failures are answered with problem details like every other API error
+    conf.app_data(web::JsonConfig::default().error_handler(|err, _| AppError::from(err).into()))
+        .app_data(web::QueryConfig::default().error_handler(|err, _| AppError::from(err).into()))
+        .app_data(web::PathConfig::default().error_handler(|err, _| AppError::from(err).into()))
+        .service(
+            web::resource("/health-check").route(web::get().to(front_controller::health_check)),
+        )
+        .service(web::resource("/metrics").route(web::get().to(metrics_controller::metrics)))
+        .service(
+            web::resource("/.well-known/jwks.json").route(web::get().to(auth_controller::jwks)),
+        )
+        // Full paths, every API route must be documented in `config::openapi`
+        .service(
+            web::resource("/api/openapi.json").route(web::get().to(docs_controller::openapi_json)),
+        )
+        .service(web::resource("/api/docs").route(web::get().to(docs_controller::api_docs)))
+        .service(web::resource("/api/auth/login").route(web::post().to(auth_controller::login)))
+        .service(web::resource("/api/auth/logout").route(web::post().to(auth_controller::logout)))
+        .service(
+            web::resource("/api/auth/sessions")
+                .route(web::get().to(session_controller::list_sessions)),
+        )
+        .service(
+            web::resource("/api/auth/sessions/{id}")
+                .route(web::delete().to(session_controller::revoke_session)),
+        )
+        .service(
+            web::resource("/api/auth/login-history")
+                .route(web::get().to(login_history_controller::my_login_history)),
+        )
+        .service(
+            web::resource("/api/auth/2fa/enroll")
+                .route(web::post().to(two_factor_controller::enroll)),
+        )
+        .service(
+            web::resource("/api/auth/2fa/confirm")
+                .route(web::post().to(two_factor_controller::confirm)),
+        )
+        .service(
+            web::resource("/api/auth/2fa/recovery-codes")
+                .route(web::post().to(two_factor_controller::regenerate_recovery_codes)),
+        )
+        .service(
+            web::resource("/api/auth/2fa/disable")
+                .route(web::post().to(two_factor_controller::disable)),
+        )
+        .service(
+            web::resource("/api/admin/login-history")
+                .route(web::get().to(login_history_controller::audit_login_history)),
+        )
+        .service(
+            web::resource("/api/admin/users/{id}/sessions")
+                .route(web::delete().to(session_controller::revoke_user_sessions)),
+        )
+        .service(
+            web::resource("/api/admin/users/{id}/unlock")
+                .route(web::post().to(session_controller::unlock_user)),
+        )
+        .service(
+            web::resource("/api/candidates").route(web::post().to(candidate_controller::apply)),
+        )
+        .service(
+            web::resource("/api/candidates/data-export")
+                .route(web::post().to(privacy_controller::request_export))
+                .route(web::get().to(privacy_controller::download_export)),
+        )
+        .service(
+            web::resource("/api/companies/{id}/privacy")
+                .route(web::get().to(privacy_controller::privacy_notice)),
+        )
+        .service(
+            web::resource("/api/admin/companies/{id}/retention")
+                .route(web::get().to(privacy_controller::retention))
+                .route(web::put().to(privacy_controller::update_retention)),
+        )
+        .service(
+            web::resource("/api/admin/candidates/search")
+                .route(web::get().to(candidate_controller::search)),
+        )
+        .service(
+            web::resource("/api/admin/candidates/duplicates")
+                .route(web::get().to(candidate_controller::duplicates)),
+        )
+        .service(
+            web::resource("/api/admin/candidates/{id}/merge")
+                .route(web::post().to(candidate_controller::merge)),
+        )
+        .service(
+            web::resource("/api/admin/candidates/{id}/merges")
+                .route(web::get().to(candidate_controller::merges)),
+        )
+        .service(
+            web::resource("/api/admin/candidates/{id}/merges/{merge_id}/cv")
+                .route(web::get().to(candidate_controller::download_merged_cv)),
+        )
+        .service(
+            web::resource("/api/admin/candidates/{id}/cv")
+                .route(web::get().to(candidate_controller::download_cv)),
+        )
+        .service(
+            web::resource("/api/admin/candidates/{id}/data-export")
+                .route(web::get().to(privacy_controller::export_candidate_data)),
+        )
+        .service(
+            web::resource("/api/admin/candidates/{id}/personal-data")
+                .route(web::delete().to(privacy_controller::erase_personal_data)),
+        )
+        .service(
+            web::resource("/api/admin/candidates/{id}/job-offers")
+                .route(web::get().to(candidate_controller::ranked_job_offers)),
+        )
+        .service(
+            web::resource("/api/admin/job-offers/{id}/skills")
+                .route(web::get().to(job_offer_controller::list_skills))
+                .route(web::put().to(job_offer_controller::replace_skills)),
+        )
+        .service(
+            web::resource("/api/admin/job-offers/{id}/candidates")
+                .route(web::get().to(job_offer_controller::ranked_candidates)),
+        )
+        .service(web::resource("/").route(web::get().to(front_controller::homepage)))
+        .service(Files::new("/assets", "assets").show_files_listing())
+        .default_service(web::to(front_controller::handler_404));
 }
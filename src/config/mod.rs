@@ -1,2 +1,5 @@
 pub mod app;
 pub mod db;
+pub mod openapi;
+pub mod settings;
+pub mod signing_keys;
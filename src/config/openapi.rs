@@ -0,0 +1,262 @@
+// OpenAPI 3 description of the JSON API, generated from the handlers and DTOs
+use utoipa::{
+    openapi::security::{HttpAuthScheme, HttpBuilder, SecurityScheme},
+    Modify, OpenApi,
+};
+
+use crate::{
+    controller::{
+        auth_controller,
+        candidate_controller::{self, ApplicationReceived, ApplicationUpload},
+        front_controller, job_offer_controller, login_history_controller,
+        privacy_controller::{self, PrivacyNotice, RetentionPolicy, RetentionUpdate},
+        session_controller, two_factor_controller,
+    },
+    error::{FieldError, ProblemDetails},
+    jobs::candidate_retention::ErasureReport,
+    models::{
+        candidate::{CandidateDTO, CandidateMatch, ScanStatus, TextStatus},
+        company::CompanyDTO,
+        data_export::DataExportDTO,
+        duplicate::{
+            DuplicateCandidate, DuplicatePair, DuplicateReason, MergeRecord, MergeRequest,
+            MergedApplication,
+        },
+        job_offer::JobOfferDTO,
+        skill::{OfferSkill, OfferSkills, RankedCandidate, RankedJobOffer, SkillMatch},
+        user::{LoginDTO, RoleType, UserDTO},
+        user_token::TokenBodyResponse,
+    },
+};
+
+#[derive(OpenApi)]
+#[openapi(
+    info(title = "Plateforme CV API"),
+    paths(
+        front_controller::health_check,
+        auth_controller::login,
+        auth_controller::logout,
+        auth_controller::jwks,
+        session_controller::list_sessions,
+        session_controller::revoke_session,
+        session_controller::revoke_user_sessions,
+        session_controller::unlock_user,
+        login_history_controller::my_login_history,
+        login_history_controller::audit_login_history,
+        two_factor_controller::enroll,
+        two_factor_controller::confirm,
+        two_factor_controller::regenerate_recovery_codes,
+        two_factor_controller::disable,
+        candidate_controller::apply,
+        candidate_controller::search,
+        candidate_controller::download_cv,
+        candidate_controller::ranked_job_offers,
+        candidate_controller::duplicates,
+        candidate_controller::merge,
+        candidate_controller::merges,
+        candidate_controller::download_merged_cv,
+        privacy_controller::privacy_notice,
+        privacy_controller::retention,
+        privacy_controller::update_retention,
+        privacy_controller::erase_personal_data,
+        privacy_controller::request_export,
+        privacy_controller::download_export,
+        privacy_controller::export_candidate_data,
+        job_offer_controller::list_skills,
+        job_offer_controller::replace_skills,
+        job_offer_controller::ranked_candidates,
+    ),
+    components(schemas(
+        UserDTO,
+        LoginDTO,
+        RoleType,
+        TokenBodyResponse,
+        CompanyDTO,
+        JobOfferDTO,
+        CandidateDTO,
+        ScanStatus,
+        TextStatus,
+        CandidateMatch,
+        OfferSkill,
+        OfferSkills,
+        SkillMatch,
+        RankedCandidate,
+        RankedJobOffer,
+        DuplicateCandidate,
+        DuplicateReason,
+        DuplicatePair,
+        MergeRequest,
+        MergedApplication,
+        MergeRecord,
+        PrivacyNotice,
+        RetentionPolicy,
+        RetentionUpdate,
+        ErasureReport,
+        DataExportDTO,
+        ApplicationUpload,
+        ApplicationReceived,
+        ProblemDetails,
+        FieldError,
+    )),
+    modifiers(&BearerAuth),
+    tags(
+        (name = "auth", description = "Login, logout and signing keys"),
+        (name = "sessions", description = "Sessions and login history of the caller"),
+        (name = "two-factor", description = "TOTP enrolment and recovery codes"),
+        (name = "admin", description = "Management of the users of a company"),
+        (name = "candidates", description = "Applications, their CV files, the candidate search and the merge of duplicates"),
+        (name = "privacy", description = "Consent to the privacy notice, retention of the applications, export and erasure of the personal data"),
+        (name = "job-offers", description = "Skills of the job offers and the candidates matching them"),
+        (name = "health", description = "Liveness probe"),
+    )
+)]
+pub struct ApiDoc;
+
+// Tokens returned by `POST /api/auth/login`, sent as `Authorization: Bearer <token>`
+struct BearerAuth;
+
+impl Modify for BearerAuth {
+    fn modify(&self, openapi: &mut utoipa::openapi::OpenApi) {
+        if let Some(components) = openapi.components.as_mut() {
+            components.add_security_scheme(
+                "bearer_auth",
+                SecurityScheme::Http(
+                    HttpBuilder::new()
+                        .scheme(HttpAuthScheme::Bearer)
+                        .bearer_format("JWT")
+                        .build(),
+                ),
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::app::config_services;
+    use actix_web::{
+        dev::Service,
+        http::{Method, StatusCode},
+        test::{call_service, init_service, TestRequest},
+        App,
+    };
+    use std::{cell::RefCell, collections::BTreeSet, rc::Rc};
+
+    // Pages, their assets, the Prometheus scrape and the documentation itself are not part of the
+    // JSON API
+    const UNDOCUMENTED: [&str; 5] = ["/", "/assets", "/metrics", "/api/docs", "/api/openapi.json"];
+    const METHODS: [Method; 5] = [
+        Method::GET,
+        Method::POST,
+        Method::PUT,
+        Method::PATCH,
+        Method::DELETE,
+    ];
+
+    // (path, method) of every resource registered by `config_services`. The patterns are read from
+    // the resource map of the app, which has no other way to list them, and each method is tried:
+    // a resource answers `405` to the methods it has no route for. No application data is
+    // registered, the handlers fail in their extractors and change nothing
+    async fn registered_routes() -> BTreeSet<(String, String)> {
+        let resource_map = Rc::new(RefCell::new(String::new()));
+        let captured = resource_map.clone();
+        let app = init_service(App::new().configure(config_services).wrap_fn(
+            move |req, service| {
+                *captured.borrow_mut() = format!("{:?}", req.request().resource_map());
+                service.call(req)
+            },
+        ))
+        .await;
+        call_service(&app, TestRequest::get().uri("/").to_request()).await;
+
+        let patterns: BTreeSet<String> = resource_map
+            .borrow()
+            .split("Single(\"")
+            .skip(1)
+            .map(|chunk| chunk[..chunk.find('"').unwrap()].to_string())
+            .filter(|pattern| !pattern.is_empty())
+            .collect();
+        let mut routes = BTreeSet::new();
+        for pattern in patterns {
+            let uri = pattern
+                .split('/')
+                .map(|segment| match segment.starts_with('{') {
+                    true => "00000000-0000-0000-0000-000000000000",
+                    false => segment,
+                })
+                .collect::<Vec<_>>()
+                .join("/");
+            for method in METHODS {
+                let req = TestRequest::default()
+                    .method(method.clone())
+                    .uri(&uri)
+                    .to_request();
+                let resp = call_service(&app, req).await;
+                if resp.status() != StatusCode::METHOD_NOT_ALLOWED {
+                    routes.insert((pattern.clone(), method.as_str().to_lowercase()));
+                }
+            }
+        }
+        routes
+    }
+
+    fn documented_routes() -> BTreeSet<(String, String)> {
+        let spec = serde_json::to_value(ApiDoc::openapi()).unwrap();
+        let mut routes = BTreeSet::new();
+        for (path, item) in spec["paths"].as_object().unwrap() {
+            for method in item.as_object().unwrap().keys() {
+                routes.insert((path.to_string(), method.to_string()));
+            }
+        }
+        routes
+    }
+
+    #[actix_web::test]
+    async fn test_every_route_is_documented() {
+        let registered: BTreeSet<_> = registered_routes()
+            .await
+            .into_iter()
+            .filter(|(path, _)| !UNDOCUMENTED.contains(&path.as_str()))
+            .collect();
+        assert!(registered.contains(&("/api/auth/login".to_string(), "post".to_string())));
+
+        let documented = documented_routes();
+        let missing: Vec<_> = registered.difference(&documented).collect();
+        assert!(
+            missing.is_empty(),
+            "routes missing from the spec: {:?}",
+            missing
+        );
+        let unknown: Vec<_> = documented.difference(&registered).collect();
+        assert!(
+            unknown.is_empty(),
+            "documented routes not registered: {:?}",
+            unknown
+        );
+    }
+
+    #[test]
+    fn test_spec_components() {
+        let spec = serde_json::to_value(ApiDoc::openapi()).unwrap();
+        assert!(spec["openapi"].as_str().unwrap().starts_with("3."));
+        for schema in [
+            "UserDTO",
+            "LoginDTO",
+            "JobOfferDTO",
+            "CandidateDTO",
+            "CompanyDTO",
+            "TokenBodyResponse",
+        ] {
+            assert!(
+                spec["components"]["schemas"].get(schema).is_some(),
+                "{} missing",
+                schema
+            );
+        }
+        assert_eq!(
+            spec["components"]["securitySchemes"]["bearer_auth"]["scheme"],
+            "bearer"
+        );
+    }
+}
@@ -1,4 +1,107 @@
 pub const SERVER_STARTED: &str = "✅ Server started successfully";
 pub const DATABASE_STARTED: &str = "✅ Connected to database and table created !";
 pub const PATH_UPLOAD_CV: &str = "uploads/cv";
+pub const PATH_OPENAPI_SPEC: &str = "/api/openapi.json";
 pub const MESSAGE_SIGNUP_SUCCESS: &str = "Signup successfully";
+
+// Messages
+pub const MESSAGE_OK: &str = "ok";
+pub const MESSAGE_LOGIN_SUCCESS: &str = "Login successfully";
+pub const MESSAGE_LOGIN_FAILED: &str = "Wrong username or password, please try again";
+pub const MESSAGE_LOGOUT_SUCCESS: &str = "Logout successfully";
+pub const MESSAGE_INVALID_TOKEN: &str = "Invalid token, please login again";
+pub const MESSAGE_FORBIDDEN: &str = "You are not allowed to perform this action";
+pub const MESSAGE_SESSION_NOT_FOUND: &str = "Session not found";
+pub const MESSAGE_SESSION_REVOKED: &str = "Session revoked successfully";
+pub const MESSAGE_USER_NOT_FOUND: &str = "User not found";
+pub const MESSAGE_TWO_FACTOR_REQUIRED: &str = "Two-factor authentication code required";
+pub const MESSAGE_TWO_FACTOR_ENROLMENT_REQUIRED: &str =
+    "Two-factor authentication must be enabled for this account";
+pub const MESSAGE_TWO_FACTOR_ALREADY_ENABLED: &str = "Two-factor authentication is already enabled";
+pub const MESSAGE_TWO_FACTOR_NOT_ENABLED: &str = "Two-factor authentication is not enabled";
+pub const MESSAGE_TWO_FACTOR_ENABLED: &str = "Two-factor authentication enabled successfully";
+pub const MESSAGE_TWO_FACTOR_DISABLED: &str = "Two-factor authentication disabled successfully";
+pub const MESSAGE_INVALID_TWO_FACTOR_CODE: &str = "Invalid two-factor authentication code";
+pub const MESSAGE_ACCOUNT_LOCKED: &str = "Account temporarily locked after too many failed logins";
+pub const MESSAGE_TOO_MANY_LOGIN_ATTEMPTS: &str = "Too many failed logins, please try again later";
+pub const MESSAGE_ACCOUNT_UNLOCKED: &str = "Account unlocked successfully";
+pub const MESSAGE_INTERNAL_SERVER_ERROR: &str = "Internal Server Error";
+pub const MESSAGE_RESOURCE_NOT_FOUND: &str = "Resource not found";
+pub const MESSAGE_RESOURCE_ALREADY_EXISTS: &str = "Resource already exists";
+pub const MESSAGE_VALIDATION_FAILED: &str = "Validation failed";
+pub const MESSAGE_PAGE_NOT_FOUND: &str = "Nothing here..";
+pub const MESSAGE_USER_ALREADY_REGISTERED: &str = "User is already registered";
+pub const MESSAGE_PASSWORD_REQUIRED: &str = "Password is required";
+pub const MESSAGE_FIELD_REQUIRED: &str = "This field is required";
+pub const MESSAGE_FIELD_TOO_LONG: &str = "This field is too long";
+pub const MESSAGE_INVALID_EMAIL: &str = "The email address is invalid";
+pub const MESSAGE_INVALID_PHONE: &str =
+    "The phone number is invalid, expected a French number or an international one starting with +";
+
+// CV files
+pub const MESSAGE_CV_EMPTY: &str = "The CV file is empty";
+pub const MESSAGE_CV_TOO_LARGE: &str = "The CV file is too large";
+pub const MESSAGE_CV_UNSUPPORTED_TYPE: &str =
+    "The CV must be a PDF, DOCX or ODT document or a PNG, JPEG or WebP image";
+pub const MESSAGE_CV_PDF_JAVASCRIPT: &str = "PDF files containing JavaScript are not accepted";
+pub const MESSAGE_CV_PDF_ENCRYPTED: &str = "Encrypted PDF files are not accepted";
+pub const MESSAGE_CV_INFECTED: &str = "The CV file was rejected by the malware scanner";
+pub const MESSAGE_CV_NOT_SCANNED: &str = "The CV file has not been scanned clean yet";
+pub const MESSAGE_COMPANY_NOT_FOUND: &str = "Company not found";
+pub const MESSAGE_CANDIDATE_NOT_FOUND: &str = "Candidate not found";
+pub const MESSAGE_APPLICATION_RECEIVED: &str = "Application received";
+pub const MESSAGE_SEARCH_QUERY_REQUIRED: &str = "The search query is required";
+pub const MESSAGE_JOB_OFFER_NOT_FOUND: &str = "Job offer not found";
+pub const MESSAGE_UNKNOWN_SKILL: &str =
+    "Unknown skill, it must be a name or alias of the skills dictionary";
+pub const MESSAGE_DUPLICATE_SKILL: &str = "This skill is already listed";
+pub const MESSAGE_TOO_MANY_SKILLS: &str = "Too many skills";
+pub const MESSAGE_COMPANY_REQUIRED: &str = "The company is required";
+pub const MESSAGE_MERGE_SAME_CANDIDATE: &str = "A candidate cannot be merged into itself";
+pub const MESSAGE_MERGE_OTHER_COMPANY: &str = "The candidates applied to different companies";
+pub const MESSAGE_MERGE_CV_PENDING: &str =
+    "The CV of the duplicate is still being scanned or extracted, merge it once it is done";
+pub const MESSAGE_MERGE_NOT_FOUND: &str = "Merge not found";
+pub const MESSAGE_CANDIDATES_MERGED: &str = "Candidates merged";
+pub const MESSAGE_CONSENT_REQUIRED: &str = "The current privacy notice must be accepted to apply";
+pub const MESSAGE_CANDIDATE_ANONYMISED: &str = "The personal data of the candidate was erased";
+pub const MESSAGE_PERSONAL_DATA_ERASED: &str = "Personal data erased";
+pub const MESSAGE_INVALID_RETENTION: &str = "The retention must be between 30 and 3650 days";
+pub const MESSAGE_DATA_EXPORT_REQUESTED: &str =
+    "If applications were sent with this address, a download link was emailed to it";
+pub const MESSAGE_DATA_EXPORT_LINK_INVALID: &str = "The download link is invalid or has expired";
+
+// Two-factor authentication
+pub const TOTP_ISSUER: &str = "Platform CV";
+
+// Login throttling
+pub const LOGIN_MAX_FAILED_ATTEMPTS: i32 = 5;
+pub const LOGIN_LOCKOUT_BASE_SECONDS: i64 = 60;
+pub const LOGIN_LOCKOUT_MAX_SECONDS: i64 = 60 * 60;
+pub const LOGIN_MAX_FAILED_ATTEMPTS_PER_IP: i64 = 20;
+pub const LOGIN_IP_WINDOW_SECONDS: i64 = 15 * 60;
+pub const LOGIN_FAILURE_INVALID_PASSWORD: &str = "invalid_password";
+pub const LOGIN_FAILURE_INVALID_TWO_FACTOR_CODE: &str = "invalid_two_factor_code";
+pub const LOGIN_FAILURE_UNKNOWN_USER: &str = "unknown_user";
+pub const LOGIN_FAILURE_PASSWORD_NOT_SET: &str = "password_not_set";
+
+// Login history retention
+pub const LOGIN_HISTORY_RETENTION_DAYS: i64 = 90;
+pub const LOGIN_HISTORY_RETENTION_INTERVAL_SECONDS: u64 = 60 * 60 * 24;
+
+// Candidate retention, two years unless the company sets its own
+pub const CANDIDATE_RETENTION_DAYS: i32 = 730;
+pub const CANDIDATE_RETENTION_DAYS_RANGE: std::ops::RangeInclusive<i32> = 30..=3650;
+// One data export email per address in this window
+pub const DATA_EXPORT_REQUEST_INTERVAL_SECONDS: i64 = 5 * 60;
+pub const DATA_EXPORT_EMAIL_SUBJECT: &str = "Your data export";
+
+// CV text extraction, a claimed CV is extracted again after this delay if its run stopped
+pub const CV_TEXT_CLAIM_SECONDS: i64 = 10 * 60;
+
+// Headers
+pub const AUTHORIZATION: &str = "Authorization";
+pub const BEARER: &str = "bearer";
+
+// Misc
+pub const EMPTY: &str = "";
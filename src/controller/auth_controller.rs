@@ -0,0 +1,91 @@
+use actix_web::{web, HttpRequest, HttpResponse};
+
+use crate::{
+    config::{
+        db::{self, Pool},
+        settings::Settings,
+        signing_keys::SigningKeys,
+    },
+    constants,
+    error::{AppError, ProblemDetails},
+    models::{
+        response::ResponseBody,
+        user::{LoginDTO, User},
+        user_token::{TokenBodyResponse, UserToken},
+    },
+    utils::{auth::SessionUser, token_utils},
+};
+
+// POST api/auth/login
+#[utoipa::path(
+    post,
+    path = "/api/auth/login",
+    tag = "auth",
+    request_body = LoginDTO,
+    responses(
+        (status = 200, description = "Bearer token of the new session", body = ResponseBody<TokenBodyResponse>),
+        (status = 400, description = "Second factor required or invalid", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 401, description = "Wrong credentials", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 429, description = "Account locked after too many failures", body = ProblemDetails, content_type = "application/problem+json"),
+    )
+)]
+pub async fn login(
+    req: HttpRequest,
+    login_dto: web::Json<LoginDTO>,
+    pool: web::Data<Pool>,
+    keys: web::Data<SigningKeys>,
+    settings: web::Data<Settings>,
+) -> Result<HttpResponse, AppError> {
+    let client = token_utils::client_info(&req);
+    let max_age = settings.auth.token_max_age;
+    let login = login_dto.into_inner();
+    // bcrypt is as blocking as the queries, it runs on the same thread pool
+    let logged_user = db::run(&pool, move |conn| {
+        User::login(login, &client, max_age, conn)
+    })
+    .await?;
+    let token = UserToken::generate_token(&logged_user, max_age, &keys);
+    Ok(HttpResponse::Ok().json(ResponseBody::new(
+        constants::MESSAGE_LOGIN_SUCCESS,
+        TokenBodyResponse {
+            token,
+            token_type: "bearer".to_string(),
+        },
+    )))
+}
+
+// POST api/auth/logout
+#[utoipa::path(
+    post,
+    path = "/api/auth/logout",
+    tag = "auth",
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "Session revoked", body = ResponseBody<String>),
+        (status = 401, description = "Missing or invalid token", body = ProblemDetails, content_type = "application/problem+json"),
+    )
+)]
+pub async fn logout(auth: SessionUser, pool: web::Data<Pool>) -> Result<HttpResponse, AppError> {
+    db::run(&pool, move |conn| {
+        User::logout(auth.user.id, &auth.session.session_token, conn);
+        Ok::<_, AppError>(())
+    })
+    .await?;
+    Ok(HttpResponse::Ok().json(ResponseBody::new(
+        constants::MESSAGE_LOGOUT_SUCCESS,
+        constants::EMPTY,
+    )))
+}
+
+// GET .well-known/jwks.json
+#[utoipa::path(
+    get,
+    path = "/.well-known/jwks.json",
+    tag = "auth",
+    responses(
+        (status = 200, description = "Public signing keys as a JSON Web Key Set", content_type = "application/json"),
+    )
+)]
+pub async fn jwks(keys: web::Data<SigningKeys>) -> HttpResponse {
+    HttpResponse::Ok().json(keys.jwks())
+}
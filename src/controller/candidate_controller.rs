@@ -0,0 +1,551 @@
+use std::io::{self, Read};
+
+use actix_multipart::form::{bytes::Bytes, text::Text, MultipartForm};
+use actix_web::{
+    http::header::{self, ContentDisposition, DispositionParam, DispositionType},
+    web, HttpRequest, HttpResponse,
+};
+use diesel::Connection as _;
+use futures::{stream, Stream};
+use serde::Serialize;
+use tracing::{info, warn};
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use crate::{
+    config::{
+        db::{self, Pool},
+        settings::GdprSettings,
+    },
+    constants,
+    error::{AppError, FieldError, ProblemDetails},
+    models::{
+        candidate::{Candidate, CandidateDTO, CandidateMatch, CandidateSearch, ScanStatus},
+        company::Company,
+        duplicate::{CandidateMerge, DuplicatePair, DuplicateSearch, MergeRecord, MergeRequest},
+        pagination::{Page, PaginationParams},
+        response::ResponseBody,
+        skill::RankedJobOffer,
+        user::RoleType,
+    },
+    storage::{backend::Download, scanner::Scanner, validation::CvFormat, CvError, CvStorage},
+    utils::{auth::AuthenticatedUser, metrics::METRICS, token_utils, validation::Validate},
+};
+
+// Read on the blocking thread pool, a CV is never held whole in memory
+const DOWNLOAD_CHUNK_SIZE: usize = 64 * 1024;
+
+// Candidate of the company of the administrator, any candidate for super admins
+pub(crate) async fn managed_candidate(
+    auth: &AuthenticatedUser,
+    i_candidate: Uuid,
+    pool: &Pool,
+) -> Result<Candidate, AppError> {
+    let candidate = db::run(pool, move |conn| {
+        Candidate::find_by_id(i_candidate, conn)
+            .map_err(AppError::not_found(constants::MESSAGE_CANDIDATE_NOT_FOUND))
+    })
+    .await?;
+    if !auth.can_manage_company(candidate.company_id) {
+        return Err(AppError::Forbidden {
+            error_message: constants::MESSAGE_FORBIDDEN.to_string(),
+        });
+    }
+    Ok(candidate)
+}
+
+#[derive(MultipartForm)]
+pub struct ApplicationForm {
+    pub company_id: Text<Uuid>,
+    pub lastname: Text<String>,
+    pub firstname: Text<String>,
+    pub phone: Text<String>,
+    pub email: Text<String>,
+    pub motivation: Text<String>,
+    pub consent_version: Text<String>,
+    pub file: Bytes,
+}
+
+// `ApplicationForm` as documented in the OpenAPI spec
+#[derive(ToSchema)]
+#[allow(dead_code)]
+pub struct ApplicationUpload {
+    company_id: Uuid,
+    lastname: String,
+    firstname: String,
+    phone: String,
+    email: String,
+    motivation: String,
+    // Version of the privacy notice accepted by the candidate, `GET api/companies/{id}/privacy`
+    consent_version: String,
+    // PDF, DOCX, ODT, PNG, JPEG or WebP
+    #[schema(value_type = String, format = Binary)]
+    file: Vec<u8>,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct ApplicationReceived {
+    pub id: Uuid,
+    // `pending` when the scanner could not be reached, the CV is scanned again later
+    pub cv_scan_status: ScanStatus,
+}
+
+// POST api/candidates
+#[utoipa::path(
+    post,
+    path = "/api/candidates",
+    tag = "candidates",
+    request_body(content = ApplicationUpload, content_type = "multipart/form-data"),
+    responses(
+        (status = 201, description = "Application saved, the CV is released once scanned clean", body = ResponseBody<ApplicationReceived>),
+        (status = 404, description = "Unknown company", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 413, description = "CV over the size limit", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 422, description = "Missing, invalid or too long field, privacy notice not accepted, unsupported or infected CV", body = ProblemDetails, content_type = "application/problem+json"),
+    )
+)]
+pub async fn apply(
+    req: HttpRequest,
+    form: MultipartForm<ApplicationForm>,
+    pool: web::Data<Pool>,
+    storage: web::Data<CvStorage>,
+    scanner: web::Data<dyn Scanner>,
+    gdpr: web::Data<GdprSettings>,
+) -> Result<HttpResponse, AppError> {
+    let form = form.into_inner();
+    // The notice accepted must be the current one, an outdated form is refused
+    let consent_version = gdpr.consent_version.clone();
+    if form.consent_version.trim() != consent_version {
+        return Err(AppError::validation(vec![FieldError {
+            field: "consent_version".to_string(),
+            message: constants::MESSAGE_CONSENT_REQUIRED.to_string(),
+        }]));
+    }
+    let i_company = form.company_id.into_inner();
+    db::run(&pool, move |conn| {
+        Company::find_by_id(i_company, conn)
+            .map_err(AppError::not_found(constants::MESSAGE_COMPANY_NOT_FOUND))
+    })
+    .await?;
+
+    // Checked before the CV is stored, `file_name` is set once it is
+    let mut application = CandidateDTO {
+        company_id: i_company,
+        lastname: form.lastname.into_inner(),
+        firstname: form.firstname.into_inner(),
+        file_name: String::new(),
+        phone: form.phone.into_inner(),
+        email: form.email.into_inner(),
+        motivation: form.motivation.into_inner(),
+    }
+    .validate()
+    .map_err(AppError::validation)?;
+
+    let content = form.file.data;
+    // Written to the quarantine, then released or deleted depending on the scan
+    let cleanup = storage.clone();
+    let (stored, outcome) = web::block(move || {
+        let stored = storage.store(i_company, &content)?;
+        let outcome = storage.scan(&stored.file_name, &content, scanner.get_ref())?;
+        Ok::<_, CvError>((stored, outcome))
+    })
+    .await??;
+    if outcome.status == ScanStatus::Infected {
+        return Err(CvError::Infected.into());
+    }
+
+    let file_name = stored.file_name;
+    application.file_name = file_name.clone();
+    let ip_address = token_utils::client_info(&req).ip_address;
+    // No application is stored without its consent
+    let candidate = match db::run(&pool, move |conn| {
+        conn.transaction(|conn| {
+            let candidate = Candidate::insert(application, conn)?;
+            Candidate::record_consent(candidate.id, &consent_version, ip_address, conn)?;
+            Candidate::record_scan(candidate.id, outcome.status, outcome.signature, conn)
+        })
+    })
+    .await
+    {
+        Ok(candidate) => candidate,
+        Err(e) => {
+            // No application refers to the CV, nothing would ever erase it
+            let orphan = file_name.clone();
+            if !matches!(
+                web::block(move || cleanup.delete(&orphan)).await,
+                Ok(Ok(()))
+            ) {
+                warn!(
+                    "CV {} of an application not saved left in the storage",
+                    file_name
+                );
+            }
+            return Err(e);
+        }
+    };
+    METRICS.record_application(candidate.company_id);
+    info!(
+        "Application {} received, CV {}",
+        candidate.id, candidate.cv_scan_status
+    );
+    Ok(HttpResponse::Created().json(ResponseBody::new(
+        constants::MESSAGE_APPLICATION_RECEIVED,
+        ApplicationReceived {
+            id: candidate.id,
+            cv_scan_status: candidate.cv_scan_status,
+        },
+    )))
+}
+
+// GET api/admin/candidates/search
+#[utoipa::path(
+    get,
+    path = "/api/admin/candidates/search",
+    tag = "candidates",
+    security(("bearer_auth" = [])),
+    params(CandidateSearch),
+    responses(
+        (status = 200, description = "Candidates matching the query in their names, motivation or CV text, most relevant first", body = ResponseBody<Page<CandidateMatch>>),
+        (status = 401, description = "Missing or invalid token", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 403, description = "Not an administrator, or company of another administrator", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 422, description = "Blank query", body = ProblemDetails, content_type = "application/problem+json"),
+    )
+)]
+pub async fn search(
+    auth: AuthenticatedUser,
+    search: web::Query<CandidateSearch>,
+    pool: web::Data<Pool>,
+) -> Result<HttpResponse, AppError> {
+    let search = search.into_inner();
+    let params = search.pagination();
+    let query = search.q.trim().to_string();
+    if query.is_empty() {
+        return Err(AppError::validation(vec![FieldError {
+            field: "q".to_string(),
+            message: constants::MESSAGE_SEARCH_QUERY_REQUIRED.to_string(),
+        }]));
+    }
+    let forbidden = || AppError::Forbidden {
+        error_message: constants::MESSAGE_FORBIDDEN.to_string(),
+    };
+    // `None` for super admins searching every company
+    let company = match (&auth.user.role, auth.user.company_id) {
+        (RoleType::SuperAdmin, _) => search.company_id,
+        (RoleType::Admin, Some(i_company)) => Some(i_company),
+        _ => return Err(forbidden()),
+    };
+    if search.company_id.is_some() && search.company_id != company {
+        return Err(forbidden());
+    }
+    let page = db::run(&pool, move |conn| {
+        Candidate::search(&query, company, &params, conn)
+            .map(|(items, total)| Page::new(items, &params, total))
+    })
+    .await?;
+    Ok(HttpResponse::Ok().json(ResponseBody::new(constants::MESSAGE_OK, page)))
+}
+
+// GET api/admin/candidates/{id}/job-offers
+#[utoipa::path(
+    get,
+    path = "/api/admin/candidates/{id}/job-offers",
+    tag = "candidates",
+    security(("bearer_auth" = [])),
+    params(("id" = Uuid, Path, description = "Candidate id"), PaginationParams),
+    responses(
+        (status = 200, description = "Offers of the company asking for at least one skill of the candidate, best score first, with the matched and missing skills", body = ResponseBody<Page<RankedJobOffer>>),
+        (status = 401, description = "Missing or invalid token", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 403, description = "Candidate of another company", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 404, description = "Unknown candidate", body = ProblemDetails, content_type = "application/problem+json"),
+    )
+)]
+pub async fn ranked_job_offers(
+    auth: AuthenticatedUser,
+    candidate_id: web::Path<Uuid>,
+    params: web::Query<PaginationParams>,
+    pool: web::Data<Pool>,
+) -> Result<HttpResponse, AppError> {
+    let candidate = managed_candidate(&auth, candidate_id.into_inner(), &pool).await?;
+    let params = params.into_inner();
+    let page = db::run(&pool, move |conn| {
+        RankedJobOffer::for_candidate(&candidate, &params, conn)
+            .map(|(items, total)| Page::new(items, &params, total))
+    })
+    .await?;
+    Ok(HttpResponse::Ok().json(ResponseBody::new(constants::MESSAGE_OK, page)))
+}
+
+// GET api/admin/candidates/duplicates
+#[utoipa::path(
+    get,
+    path = "/api/admin/candidates/duplicates",
+    tag = "candidates",
+    security(("bearer_auth" = [])),
+    params(DuplicateSearch),
+    responses(
+        (status = 200, description = "Pairs of candidates of the company sharing an email or a phone number, or with similar names, the most likely duplicates first", body = ResponseBody<Page<DuplicatePair>>),
+        (status = 401, description = "Missing or invalid token", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 403, description = "Not an administrator, or company of another administrator", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 422, description = "Company missing for a super admin", body = ProblemDetails, content_type = "application/problem+json"),
+    )
+)]
+pub async fn duplicates(
+    auth: AuthenticatedUser,
+    search: web::Query<DuplicateSearch>,
+    pool: web::Data<Pool>,
+) -> Result<HttpResponse, AppError> {
+    let search = search.into_inner();
+    let params = search.pagination();
+    let forbidden = || AppError::Forbidden {
+        error_message: constants::MESSAGE_FORBIDDEN.to_string(),
+    };
+    let company = match (&auth.user.role, auth.user.company_id) {
+        (RoleType::SuperAdmin, _) => search.company_id.ok_or_else(|| {
+            AppError::validation(vec![FieldError {
+                field: "company_id".to_string(),
+                message: constants::MESSAGE_COMPANY_REQUIRED.to_string(),
+            }])
+        })?,
+        (RoleType::Admin, Some(i_company)) => i_company,
+        _ => return Err(forbidden()),
+    };
+    if search
+        .company_id
+        .is_some_and(|i_company| i_company != company)
+    {
+        return Err(forbidden());
+    }
+    let page = db::run(&pool, move |conn| {
+        DuplicatePair::find_by_company(company, &params, conn)
+            .map(|(items, total)| Page::new(items, &params, total))
+    })
+    .await?;
+    Ok(HttpResponse::Ok().json(ResponseBody::new(constants::MESSAGE_OK, page)))
+}
+
+// POST api/admin/candidates/{id}/merge
+#[utoipa::path(
+    post,
+    path = "/api/admin/candidates/{id}/merge",
+    tag = "candidates",
+    security(("bearer_auth" = [])),
+    params(("id" = Uuid, Path, description = "Candidate kept")),
+    request_body = MergeRequest,
+    responses(
+        (status = 200, description = "Duplicate merged into the candidate and deleted: its skills, CV text and earlier merges are moved, the empty fields of the candidate filled, and its application and CV kept in the merge record. The statistics then count a single application", body = ResponseBody<MergeRecord>),
+        (status = 401, description = "Missing or invalid token", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 403, description = "Candidate of another company", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 404, description = "Unknown candidate or duplicate", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 409, description = "CV of the duplicate still being scanned or extracted", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 422, description = "Same candidate, duplicate of another company, or candidate whose personal data was erased", body = ProblemDetails, content_type = "application/problem+json"),
+    )
+)]
+pub async fn merge(
+    auth: AuthenticatedUser,
+    candidate_id: web::Path<Uuid>,
+    body: web::Json<MergeRequest>,
+    pool: web::Data<Pool>,
+) -> Result<HttpResponse, AppError> {
+    let survivor = managed_candidate(&auth, candidate_id.into_inner(), &pool).await?;
+    let i_duplicate = body.into_inner().duplicate_id;
+    let invalid = |message: &str| {
+        AppError::validation(vec![FieldError {
+            field: "duplicate_id".to_string(),
+            message: message.to_string(),
+        }])
+    };
+    if i_duplicate == survivor.id {
+        return Err(invalid(constants::MESSAGE_MERGE_SAME_CANDIDATE));
+    }
+    let duplicate = db::run(&pool, move |conn| {
+        Candidate::find_by_id(i_duplicate, conn)
+            .map_err(AppError::not_found(constants::MESSAGE_CANDIDATE_NOT_FOUND))
+    })
+    .await?;
+    if duplicate.company_id != survivor.company_id {
+        return Err(invalid(constants::MESSAGE_MERGE_OTHER_COMPANY));
+    }
+
+    let merged_by = auth.user.id;
+    let (_, merge) = db::run(&pool, move |conn| {
+        CandidateMerge::merge(survivor.id, duplicate.id, Some(merged_by), conn)
+            .map_err(AppError::from)
+    })
+    .await?;
+    info!(
+        "Candidate {} merged into {} by {}",
+        merge.merged_id, merge.survivor_id, merged_by
+    );
+    Ok(HttpResponse::Ok().json(ResponseBody::new(
+        constants::MESSAGE_CANDIDATES_MERGED,
+        MergeRecord::from(merge),
+    )))
+}
+
+// GET api/admin/candidates/{id}/merges
+#[utoipa::path(
+    get,
+    path = "/api/admin/candidates/{id}/merges",
+    tag = "candidates",
+    security(("bearer_auth" = [])),
+    params(("id" = Uuid, Path, description = "Candidate id")),
+    responses(
+        (status = 200, description = "Candidates merged into this one and their applications, the most recent merge first", body = ResponseBody<Vec<MergeRecord>>),
+        (status = 401, description = "Missing or invalid token", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 403, description = "Candidate of another company", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 404, description = "Unknown candidate", body = ProblemDetails, content_type = "application/problem+json"),
+    )
+)]
+pub async fn merges(
+    auth: AuthenticatedUser,
+    candidate_id: web::Path<Uuid>,
+    pool: web::Data<Pool>,
+) -> Result<HttpResponse, AppError> {
+    let candidate = managed_candidate(&auth, candidate_id.into_inner(), &pool).await?;
+    let records: Vec<MergeRecord> = db::run(&pool, move |conn| {
+        CandidateMerge::find_by_survivor(candidate.id, conn)
+    })
+    .await?
+    .into_iter()
+    .map(MergeRecord::from)
+    .collect();
+    Ok(HttpResponse::Ok().json(ResponseBody::new(constants::MESSAGE_OK, records)))
+}
+
+// GET api/admin/candidates/{id}/merges/{merge_id}/cv
+#[utoipa::path(
+    get,
+    path = "/api/admin/candidates/{id}/merges/{merge_id}/cv",
+    tag = "candidates",
+    security(("bearer_auth" = [])),
+    params(
+        ("id" = Uuid, Path, description = "Candidate id"),
+        ("merge_id" = Uuid, Path, description = "Merge id"),
+    ),
+    responses(
+        (status = 200, description = "CV file of the merged application, sent as an attachment", content_type = "application/octet-stream"),
+        (status = 307, description = "Redirect to a presigned URL of the CV, when `storage.s3.presign_expiry_seconds` is set"),
+        (status = 401, description = "Missing or invalid token", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 403, description = "Candidate of another company", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 404, description = "Unknown candidate, or merge of another candidate", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 409, description = "CV not scanned clean", body = ProblemDetails, content_type = "application/problem+json"),
+    )
+)]
+pub async fn download_merged_cv(
+    auth: AuthenticatedUser,
+    path: web::Path<(Uuid, Uuid)>,
+    pool: web::Data<Pool>,
+    storage: web::Data<CvStorage>,
+) -> Result<HttpResponse, AppError> {
+    let (candidate_id, merge_id) = path.into_inner();
+    let candidate = managed_candidate(&auth, candidate_id, &pool).await?;
+    let merge = db::run(&pool, move |conn| {
+        CandidateMerge::find_by_id(merge_id, conn)
+            .map_err(AppError::not_found(constants::MESSAGE_MERGE_NOT_FOUND))
+    })
+    .await?;
+    // Merges of another candidate are hidden like unknown ones
+    let file_name = merge
+        .file_name()
+        .filter(|_| merge.survivor_id == candidate.id)
+        .ok_or_else(|| AppError::NotFound {
+            error_message: constants::MESSAGE_MERGE_NOT_FOUND.to_string(),
+        })?;
+    let merged_id = merge.merged_id;
+    let application = MergeRecord::from(merge).application;
+    send_cv(storage, file_name, application.cv_scan_status, merged_id).await
+}
+
+// GET api/admin/candidates/{id}/cv
+#[utoipa::path(
+    get,
+    path = "/api/admin/candidates/{id}/cv",
+    tag = "candidates",
+    security(("bearer_auth" = [])),
+    params(("id" = Uuid, Path, description = "Candidate id")),
+    responses(
+        (status = 200, description = "CV file, sent as an attachment", content_type = "application/octet-stream"),
+        (status = 307, description = "Redirect to a presigned URL of the CV, when `storage.s3.presign_expiry_seconds` is set"),
+        (status = 401, description = "Missing or invalid token", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 403, description = "Candidate of another company", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 404, description = "Unknown candidate, or candidate whose personal data was erased", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 409, description = "CV not scanned clean", body = ProblemDetails, content_type = "application/problem+json"),
+    )
+)]
+pub async fn download_cv(
+    auth: AuthenticatedUser,
+    candidate_id: web::Path<Uuid>,
+    pool: web::Data<Pool>,
+    storage: web::Data<CvStorage>,
+) -> Result<HttpResponse, AppError> {
+    let candidate = managed_candidate(&auth, candidate_id.into_inner(), &pool).await?;
+    if candidate.anonymised_at.is_some() {
+        return Err(AppError::NotFound {
+            error_message: constants::MESSAGE_CANDIDATE_ANONYMISED.to_string(),
+        });
+    }
+    send_cv(
+        storage,
+        candidate.file_name,
+        candidate.cv_scan_status,
+        candidate.id,
+    )
+    .await
+}
+
+// Attachment named after `i_application`, pending and infected files are never served
+async fn send_cv(
+    storage: web::Data<CvStorage>,
+    file_name: String,
+    scan_status: ScanStatus,
+    i_application: Uuid,
+) -> Result<HttpResponse, AppError> {
+    if scan_status != ScanStatus::Clean {
+        return Err(AppError::Conflict {
+            error_message: constants::MESSAGE_CV_NOT_SCANNED.to_string(),
+        });
+    }
+
+    let format = CvFormat::from_file_name(&file_name).ok_or(CvError::InvalidName)?;
+    let attachment_name = format!("cv-{}.{}", i_application, format.extension());
+    let download = Download {
+        file_name: &attachment_name,
+        content_type: format.content_type(),
+    };
+    if let Some(url) = storage.download_url(&file_name, &download)? {
+        return Ok(HttpResponse::TemporaryRedirect()
+            .insert_header((header::LOCATION, url))
+            .insert_header((header::CACHE_CONTROL, "no-store"))
+            .finish());
+    }
+
+    let reader = web::block(move || storage.open(&file_name)).await??;
+    Ok(HttpResponse::Ok()
+        .content_type(format.content_type())
+        .insert_header(ContentDisposition {
+            disposition: DispositionType::Attachment,
+            parameters: vec![DispositionParam::Filename(attachment_name)],
+        })
+        .insert_header((header::X_CONTENT_TYPE_OPTIONS, "nosniff"))
+        .insert_header((header::CACHE_CONTROL, "no-store"))
+        .streaming(read_chunks(reader)))
+}
+
+pub(crate) fn read_chunks(
+    reader: Box<dyn Read + Send>,
+) -> impl Stream<Item = io::Result<web::Bytes>> {
+    stream::try_unfold(reader, |mut reader| async move {
+        let (reader, chunk) = web::block(move || {
+            let mut chunk = vec![0; DOWNLOAD_CHUNK_SIZE];
+            let read = reader.read(&mut chunk);
+            let chunk = read.map(|length| {
+                chunk.truncate(length);
+                chunk
+            });
+            (reader, chunk)
+        })
+        .await
+        .map_err(io::Error::other)?;
+        let chunk = chunk?;
+        Ok((!chunk.is_empty()).then(|| (web::Bytes::from(chunk), reader)))
+    })
+}
@@ -0,0 +1,25 @@
+use actix_web::HttpResponse;
+use utoipa::OpenApi;
+
+use crate::{
+    config::openapi::ApiDoc,
+    constants,
+    error::PageError,
+    templates::{self, front_template::ApiDocsTemplate},
+};
+
+// GET api/openapi.json
+pub async fn openapi_json() -> HttpResponse {
+    HttpResponse::Ok().json(ApiDoc::openapi())
+}
+
+// GET api/docs
+pub async fn api_docs() -> Result<HttpResponse, PageError> {
+    let template = ApiDocsTemplate {
+        spec_url: constants::PATH_OPENAPI_SPEC,
+    };
+    let response_body = templates::render(&template)?;
+    Ok(HttpResponse::Ok()
+        .content_type("text/html")
+        .body(response_body))
+}
@@ -1,23 +1,39 @@
-use actix_web::HttpResponse;
-use askama::Template;
+use actix_web::{HttpRequest, HttpResponse, ResponseError};
 
-use crate::templates::front_template::*;
+use crate::{
+    constants,
+    error::{AppError, PageError},
+    templates::{self, front_template::*},
+};
 
 // GET HOMEPAGE
-pub async fn homepage() -> HttpResponse {
+pub async fn homepage() -> Result<HttpResponse, PageError> {
     let template = HomeTemplate {};
-    let response_body = template.render().unwrap();
-    HttpResponse::Ok()
+    let response_body = templates::render(&template)?;
+    Ok(HttpResponse::Ok()
         .content_type("text/html")
-        .body(response_body)
+        .body(response_body))
 }
 
 // GET HEALTH CHECK
+#[utoipa::path(
+    get,
+    path = "/health-check",
+    tag = "health",
+    responses((status = 200, description = "Service is up", body = String, content_type = "text/plain"))
+)]
 pub async fn health_check() -> HttpResponse {
     HttpResponse::Ok().body("Health check OK")
 }
 
-// fallback route
-pub async fn handler_404() -> HttpResponse {
-    HttpResponse::NotFound().body("404 : Nothing here..")
+// fallback route, problem details under /api and an error page everywhere else
+pub async fn handler_404(req: HttpRequest) -> HttpResponse {
+    let error = AppError::NotFound {
+        error_message: constants::MESSAGE_PAGE_NOT_FOUND.to_string(),
+    };
+    if req.path().starts_with("/api/") {
+        error.error_response()
+    } else {
+        PageError(error).error_response()
+    }
 }
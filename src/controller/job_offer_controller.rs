@@ -0,0 +1,183 @@
+use actix_web::{web, HttpResponse};
+use uuid::Uuid;
+
+use crate::{
+    config::db::{self, Pool},
+    constants,
+    error::{AppError, FieldError, ProblemDetails},
+    models::{
+        job_offer::JobOffer,
+        pagination::{Page, PaginationParams},
+        response::ResponseBody,
+        skill::{JobOfferSkill, OfferSkill, OfferSkills, RankedCandidate, Skill},
+    },
+    parsing::skills::{self as dictionary, SkillDictionary},
+    utils::auth::AuthenticatedUser,
+};
+
+// Skills a job offer may list
+const MAX_OFFER_SKILLS: usize = 50;
+
+// Offer of the company of the administrator, any offer for super admins
+async fn managed_offer(
+    auth: &AuthenticatedUser,
+    i_offer: Uuid,
+    pool: &Pool,
+) -> Result<JobOffer, AppError> {
+    let offer = db::run(pool, move |conn| {
+        JobOffer::find_by_id(i_offer, conn)
+            .map_err(AppError::not_found(constants::MESSAGE_JOB_OFFER_NOT_FOUND))
+    })
+    .await?;
+    if !auth.can_manage_company(offer.company_id) {
+        return Err(AppError::Forbidden {
+            error_message: constants::MESSAGE_FORBIDDEN.to_string(),
+        });
+    }
+    Ok(offer)
+}
+
+fn offer_skills(skills: Vec<(Skill, bool)>) -> Vec<OfferSkill> {
+    skills
+        .into_iter()
+        .map(|(skill, required)| OfferSkill {
+            name: skill.name,
+            required,
+        })
+        .collect()
+}
+
+// GET api/admin/job-offers/{id}/skills
+#[utoipa::path(
+    get,
+    path = "/api/admin/job-offers/{id}/skills",
+    tag = "job-offers",
+    security(("bearer_auth" = [])),
+    params(("id" = Uuid, Path, description = "Job offer id")),
+    responses(
+        (status = 200, description = "Skills of the offer, required ones first", body = ResponseBody<Vec<OfferSkill>>),
+        (status = 401, description = "Missing or invalid token", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 403, description = "Offer of another company", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 404, description = "Unknown job offer", body = ProblemDetails, content_type = "application/problem+json"),
+    )
+)]
+pub async fn list_skills(
+    auth: AuthenticatedUser,
+    offer_id: web::Path<Uuid>,
+    pool: web::Data<Pool>,
+) -> Result<HttpResponse, AppError> {
+    let offer = managed_offer(&auth, offer_id.into_inner(), &pool).await?;
+    let skills = db::run(&pool, move |conn| Skill::find_by_job_offer(offer.id, conn)).await?;
+    Ok(HttpResponse::Ok().json(ResponseBody::new(
+        constants::MESSAGE_OK,
+        offer_skills(skills),
+    )))
+}
+
+// PUT api/admin/job-offers/{id}/skills
+#[utoipa::path(
+    put,
+    path = "/api/admin/job-offers/{id}/skills",
+    tag = "job-offers",
+    security(("bearer_auth" = [])),
+    params(("id" = Uuid, Path, description = "Job offer id")),
+    request_body = OfferSkills,
+    responses(
+        (status = 200, description = "Skills of the offer replaced, returned with the names of the dictionary", body = ResponseBody<Vec<OfferSkill>>),
+        (status = 401, description = "Missing or invalid token", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 403, description = "Offer of another company", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 404, description = "Unknown job offer", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 422, description = "Unknown or duplicate skill, or too many skills", body = ProblemDetails, content_type = "application/problem+json"),
+    )
+)]
+pub async fn replace_skills(
+    auth: AuthenticatedUser,
+    offer_id: web::Path<Uuid>,
+    body: web::Json<OfferSkills>,
+    pool: web::Data<Pool>,
+    dictionary: web::Data<SkillDictionary>,
+) -> Result<HttpResponse, AppError> {
+    let offer = managed_offer(&auth, offer_id.into_inner(), &pool).await?;
+    let wanted = body.into_inner().skills;
+    if wanted.len() > MAX_OFFER_SKILLS {
+        return Err(AppError::validation(vec![FieldError {
+            field: "skills".to_string(),
+            message: constants::MESSAGE_TOO_MANY_SKILLS.to_string(),
+        }]));
+    }
+    // Names and aliases resolved by the dictionary, `postgres` and `PostgreSQL` are the same skill
+    let mut errors = Vec::new();
+    let mut found: Vec<(&dictionary::Skill, bool)> = Vec::new();
+    for (index, skill) in wanted.iter().enumerate() {
+        let message = match dictionary.get(&skill.name) {
+            None => constants::MESSAGE_UNKNOWN_SKILL,
+            Some(known) if found.iter().any(|(other, _)| other.name == known.name) => {
+                constants::MESSAGE_DUPLICATE_SKILL
+            }
+            Some(known) => {
+                found.push((known, skill.required));
+                continue;
+            }
+        };
+        errors.push(FieldError {
+            field: format!("skills[{}].name", index),
+            message: message.to_string(),
+        });
+    }
+    if !errors.is_empty() {
+        return Err(AppError::validation(errors));
+    }
+
+    let found: Vec<(dictionary::Skill, bool)> = found
+        .into_iter()
+        .map(|(skill, required)| (skill.clone(), required))
+        .collect();
+    let skills = db::run(&pool, move |conn| {
+        let names: Vec<&dictionary::Skill> = found.iter().map(|(skill, _)| skill).collect();
+        let upserted = Skill::upsert(&names, conn)?;
+        let offer_skills: Vec<(Skill, bool)> = found
+            .iter()
+            .filter_map(|(wanted, required)| {
+                let skill = upserted.iter().find(|skill| skill.name == wanted.name)?;
+                Some((skill.clone(), *required))
+            })
+            .collect();
+        JobOfferSkill::replace(offer.id, &offer_skills, conn)?;
+        Skill::find_by_job_offer(offer.id, conn)
+    })
+    .await?;
+    Ok(HttpResponse::Ok().json(ResponseBody::new(
+        constants::MESSAGE_OK,
+        offer_skills(skills),
+    )))
+}
+
+// GET api/admin/job-offers/{id}/candidates
+#[utoipa::path(
+    get,
+    path = "/api/admin/job-offers/{id}/candidates",
+    tag = "job-offers",
+    security(("bearer_auth" = [])),
+    params(("id" = Uuid, Path, description = "Job offer id"), PaginationParams),
+    responses(
+        (status = 200, description = "Candidates of the company holding at least one skill of the offer, best score first, with the matched and missing skills", body = ResponseBody<Page<RankedCandidate>>),
+        (status = 401, description = "Missing or invalid token", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 403, description = "Offer of another company", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 404, description = "Unknown job offer", body = ProblemDetails, content_type = "application/problem+json"),
+    )
+)]
+pub async fn ranked_candidates(
+    auth: AuthenticatedUser,
+    offer_id: web::Path<Uuid>,
+    params: web::Query<PaginationParams>,
+    pool: web::Data<Pool>,
+) -> Result<HttpResponse, AppError> {
+    let offer = managed_offer(&auth, offer_id.into_inner(), &pool).await?;
+    let params = params.into_inner();
+    let page = db::run(&pool, move |conn| {
+        RankedCandidate::for_job_offer(&offer, &params, conn)
+            .map(|(items, total)| Page::new(items, &params, total))
+    })
+    .await?;
+    Ok(HttpResponse::Ok().json(ResponseBody::new(constants::MESSAGE_OK, page)))
+}
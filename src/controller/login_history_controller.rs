@@ -0,0 +1,82 @@
+use actix_web::{web, HttpResponse};
+
+use crate::{
+    config::db::{self, Pool},
+    constants,
+    error::{AppError, ProblemDetails},
+    models::{
+        login_history::{LoginHistory, LoginHistoryFilter},
+        pagination::{Page, PaginationParams},
+        response::ResponseBody,
+        user::RoleType,
+    },
+    utils::auth::AuthenticatedUser,
+};
+
+// GET api/auth/login-history
+#[utoipa::path(
+    get,
+    path = "/api/auth/login-history",
+    tag = "sessions",
+    security(("bearer_auth" = [])),
+    params(PaginationParams),
+    responses(
+        (status = 200, description = "Login attempts of the caller, newest first", body = ResponseBody<Page<LoginHistory>>),
+        (status = 401, description = "Missing or invalid token", body = ProblemDetails, content_type = "application/problem+json"),
+    )
+)]
+pub async fn my_login_history(
+    auth: AuthenticatedUser,
+    params: web::Query<PaginationParams>,
+    pool: web::Data<Pool>,
+) -> Result<HttpResponse, AppError> {
+    let params = params.into_inner();
+    let user_id = auth.user.id;
+    let page = db::run(&pool, move |conn| {
+        LoginHistory::find_by_user_id(user_id, &params, conn)
+            .map(|(items, total)| Page::new(items, &params, total))
+    })
+    .await?;
+    Ok(HttpResponse::Ok().json(ResponseBody::new(constants::MESSAGE_OK, page)))
+}
+
+// GET api/admin/login-history
+#[utoipa::path(
+    get,
+    path = "/api/admin/login-history",
+    tag = "admin",
+    security(("bearer_auth" = [])),
+    params(LoginHistoryFilter),
+    responses(
+        (status = 200, description = "Login attempts visible to the caller, newest first", body = ResponseBody<Page<LoginHistory>>),
+        (status = 401, description = "Missing or invalid token", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 403, description = "Not an administrator", body = ProblemDetails, content_type = "application/problem+json"),
+    )
+)]
+pub async fn audit_login_history(
+    auth: AuthenticatedUser,
+    filter: web::Query<LoginHistoryFilter>,
+    pool: web::Data<Pool>,
+) -> Result<HttpResponse, AppError> {
+    let params = filter.pagination();
+    let user_id = filter.user_id;
+    // `None` for super admins, who see every company
+    let company = match (&auth.user.role, auth.user.company_id) {
+        (RoleType::SuperAdmin, _) => None,
+        (RoleType::Admin, Some(i_company)) => Some(i_company),
+        _ => {
+            return Err(AppError::Forbidden {
+                error_message: constants::MESSAGE_FORBIDDEN.to_string(),
+            })
+        }
+    };
+    let page = db::run(&pool, move |conn| {
+        match company {
+            None => LoginHistory::find_all(user_id, &params, conn),
+            Some(i_company) => LoginHistory::find_by_company_id(i_company, user_id, &params, conn),
+        }
+        .map(|(items, total)| Page::new(items, &params, total))
+    })
+    .await?;
+    Ok(HttpResponse::Ok().json(ResponseBody::new(constants::MESSAGE_OK, page)))
+}
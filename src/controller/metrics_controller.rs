@@ -0,0 +1,11 @@
+use actix_web::{web, HttpResponse};
+
+use crate::{config::db::Pool, utils::metrics::METRICS};
+
+// GET metrics
+pub async fn metrics(pool: web::Data<Pool>) -> HttpResponse {
+    METRICS.observe_pool(&pool);
+    HttpResponse::Ok()
+        .content_type(prometheus::TEXT_FORMAT)
+        .body(METRICS.render())
+}
@@ -1 +1,10 @@
+pub mod auth_controller;
+pub mod candidate_controller;
+pub mod docs_controller;
 pub mod front_controller;
+pub mod job_offer_controller;
+pub mod login_history_controller;
+pub mod metrics_controller;
+pub mod privacy_controller;
+pub mod session_controller;
+pub mod two_factor_controller;
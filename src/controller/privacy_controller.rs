@@ -0,0 +1,379 @@
+use actix_web::{
+    http::header::{self, ContentDisposition, DispositionParam, DispositionType},
+    web, HttpResponse,
+};
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use tracing::{error, info};
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use crate::{
+    config::{
+        db::{self, Pool},
+        settings::GdprSettings,
+    },
+    constants,
+    controller::candidate_controller::{managed_candidate, read_chunks},
+    error::{AppError, FieldError, ProblemDetails},
+    jobs::candidate_retention::{self, ErasureReport},
+    mail::{Email, Mailer},
+    models::{
+        candidate::Candidate,
+        company::Company,
+        data_export::{DataExportDTO, DataExportDownload, DataExportRequest, SubjectAccessExport},
+        response::ResponseBody,
+    },
+    storage::{export, CvError, CvStorage},
+    utils::{auth::AuthenticatedUser, contact::normalize_email},
+};
+
+// Shown to the candidates before they apply
+#[derive(Serialize, ToSchema)]
+pub struct PrivacyNotice {
+    // Sent back as `consent_version` with the application
+    pub consent_version: String,
+    // Days the application is kept before its personal data is erased
+    pub retention_days: i32,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct RetentionPolicy {
+    // `None` when the company keeps the default retention
+    pub retention_days: Option<i32>,
+    pub default_retention_days: i32,
+}
+
+#[derive(Deserialize, ToSchema)]
+pub struct RetentionUpdate {
+    // Between 30 and 3650 days, `null` to go back to the default retention
+    pub retention_days: Option<i32>,
+}
+
+async fn find_company(i_company: Uuid, pool: &Pool) -> Result<Company, AppError> {
+    db::run(pool, move |conn| {
+        Company::find_by_id(i_company, conn)
+            .map_err(AppError::not_found(constants::MESSAGE_COMPANY_NOT_FOUND))
+    })
+    .await
+}
+
+fn check_manager(auth: &AuthenticatedUser, i_company: Uuid) -> Result<(), AppError> {
+    if auth.can_manage_company(i_company) {
+        Ok(())
+    } else {
+        Err(AppError::Forbidden {
+            error_message: constants::MESSAGE_FORBIDDEN.to_string(),
+        })
+    }
+}
+
+// ZIP of the applications, built on the blocking thread pool
+async fn send_export(
+    candidates: Vec<Candidate>,
+    pool: &Pool,
+    storage: web::Data<CvStorage>,
+) -> Result<HttpResponse, AppError> {
+    let export = db::run(pool, move |conn| {
+        SubjectAccessExport::collect(candidates, conn)
+    })
+    .await?;
+    let attachment_name = format!("data-export-{}.zip", export.exported_at.format("%Y-%m-%d"));
+    let (archive, length) = web::block(move || {
+        let archive = export::archive(&storage, &export)?;
+        let length = archive.metadata()?.len();
+        Ok::<_, CvError>((archive, length))
+    })
+    .await??;
+    Ok(HttpResponse::Ok()
+        .content_type("application/zip")
+        .insert_header(ContentDisposition {
+            disposition: DispositionType::Attachment,
+            parameters: vec![DispositionParam::Filename(attachment_name)],
+        })
+        .insert_header((header::CACHE_CONTROL, "no-store"))
+        .no_chunking(length)
+        .streaming(read_chunks(Box::new(archive))))
+}
+
+// GET api/companies/{id}/privacy
+#[utoipa::path(
+    get,
+    path = "/api/companies/{id}/privacy",
+    tag = "privacy",
+    params(("id" = Uuid, Path, description = "Company id")),
+    responses(
+        (status = 200, description = "Version of the privacy notice to accept and retention of the applications", body = ResponseBody<PrivacyNotice>),
+        (status = 404, description = "Unknown company", body = ProblemDetails, content_type = "application/problem+json"),
+    )
+)]
+pub async fn privacy_notice(
+    company_id: web::Path<Uuid>,
+    pool: web::Data<Pool>,
+    gdpr: web::Data<GdprSettings>,
+) -> Result<HttpResponse, AppError> {
+    let company = find_company(company_id.into_inner(), &pool).await?;
+    Ok(HttpResponse::Ok().json(ResponseBody::new(
+        constants::MESSAGE_OK,
+        PrivacyNotice {
+            consent_version: gdpr.consent_version.clone(),
+            retention_days: company
+                .retention_days
+                .unwrap_or(gdpr.default_retention_days),
+        },
+    )))
+}
+
+// GET api/admin/companies/{id}/retention
+#[utoipa::path(
+    get,
+    path = "/api/admin/companies/{id}/retention",
+    tag = "privacy",
+    security(("bearer_auth" = [])),
+    params(("id" = Uuid, Path, description = "Company id")),
+    responses(
+        (status = 200, description = "Retention of the applications of the company", body = ResponseBody<RetentionPolicy>),
+        (status = 401, description = "Missing or invalid token", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 403, description = "Another company", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 404, description = "Unknown company", body = ProblemDetails, content_type = "application/problem+json"),
+    )
+)]
+pub async fn retention(
+    auth: AuthenticatedUser,
+    company_id: web::Path<Uuid>,
+    pool: web::Data<Pool>,
+    gdpr: web::Data<GdprSettings>,
+) -> Result<HttpResponse, AppError> {
+    let company = find_company(company_id.into_inner(), &pool).await?;
+    check_manager(&auth, company.id)?;
+    Ok(HttpResponse::Ok().json(ResponseBody::new(
+        constants::MESSAGE_OK,
+        RetentionPolicy {
+            retention_days: company.retention_days,
+            default_retention_days: gdpr.default_retention_days,
+        },
+    )))
+}
+
+// PUT api/admin/companies/{id}/retention
+#[utoipa::path(
+    put,
+    path = "/api/admin/companies/{id}/retention",
+    tag = "privacy",
+    security(("bearer_auth" = [])),
+    params(("id" = Uuid, Path, description = "Company id")),
+    request_body = RetentionUpdate,
+    responses(
+        (status = 200, description = "Retention saved, the applications older than it are anonymised by the next run of the retention job", body = ResponseBody<RetentionPolicy>),
+        (status = 401, description = "Missing or invalid token", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 403, description = "Another company", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 404, description = "Unknown company", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 422, description = "Retention out of range", body = ProblemDetails, content_type = "application/problem+json"),
+    )
+)]
+pub async fn update_retention(
+    auth: AuthenticatedUser,
+    company_id: web::Path<Uuid>,
+    body: web::Json<RetentionUpdate>,
+    pool: web::Data<Pool>,
+    gdpr: web::Data<GdprSettings>,
+) -> Result<HttpResponse, AppError> {
+    let company = find_company(company_id.into_inner(), &pool).await?;
+    check_manager(&auth, company.id)?;
+    let days = body.into_inner().retention_days;
+    if days.is_some_and(|days| !constants::CANDIDATE_RETENTION_DAYS_RANGE.contains(&days)) {
+        return Err(AppError::validation(vec![FieldError {
+            field: "retention_days".to_string(),
+            message: constants::MESSAGE_INVALID_RETENTION.to_string(),
+        }]));
+    }
+    let company = db::run(&pool, move |conn| {
+        Company::set_retention(company.id, days, conn)
+    })
+    .await?;
+    info!(
+        "Retention of company {} set to {:?} days by {}",
+        company.id, company.retention_days, auth.user.id
+    );
+    Ok(HttpResponse::Ok().json(ResponseBody::new(
+        constants::MESSAGE_OK,
+        RetentionPolicy {
+            retention_days: company.retention_days,
+            default_retention_days: gdpr.default_retention_days,
+        },
+    )))
+}
+
+// DELETE api/admin/candidates/{id}/personal-data
+#[utoipa::path(
+    delete,
+    path = "/api/admin/candidates/{id}/personal-data",
+    tag = "privacy",
+    security(("bearer_auth" = [])),
+    params(("id" = Uuid, Path, description = "Candidate id")),
+    responses(
+        (status = 200, description = "CV files, merged applications and personal fields erased. The anonymised application stays in the statistics", body = ResponseBody<ErasureReport>),
+        (status = 401, description = "Missing or invalid token", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 403, description = "Candidate of another company", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 404, description = "Unknown candidate", body = ProblemDetails, content_type = "application/problem+json"),
+    )
+)]
+pub async fn erase_personal_data(
+    auth: AuthenticatedUser,
+    candidate_id: web::Path<Uuid>,
+    pool: web::Data<Pool>,
+    storage: web::Data<CvStorage>,
+) -> Result<HttpResponse, AppError> {
+    let candidate = managed_candidate(&auth, candidate_id.into_inner(), &pool).await?;
+    let report = db::run(&pool, move |conn| {
+        candidate_retention::erase(&storage, candidate.id, conn)
+    })
+    .await?;
+    info!(
+        "Personal data of candidate {} erased by {}: {:?}",
+        report.candidate_id, auth.user.id, report
+    );
+    Ok(HttpResponse::Ok().json(ResponseBody::new(
+        constants::MESSAGE_PERSONAL_DATA_ERASED,
+        report,
+    )))
+}
+
+// POST api/candidates/data-export
+#[utoipa::path(
+    post,
+    path = "/api/candidates/data-export",
+    tag = "privacy",
+    request_body = DataExportDTO,
+    responses(
+        (status = 202, description = "A download link is emailed to the address when applications were sent with it, the answer is the same otherwise", body = ResponseBody<String>),
+        (status = 422, description = "Invalid email", body = ProblemDetails, content_type = "application/problem+json"),
+    )
+)]
+pub async fn request_export(
+    body: web::Json<DataExportDTO>,
+    pool: web::Data<Pool>,
+    mailer: web::Data<dyn Mailer>,
+    gdpr: web::Data<GdprSettings>,
+) -> Result<HttpResponse, AppError> {
+    let address = normalize_email(&body.email).ok_or_else(|| {
+        AppError::validation(vec![FieldError {
+            field: "email".to_string(),
+            message: constants::MESSAGE_INVALID_EMAIL.to_string(),
+        }])
+    })?;
+    let now = Utc::now().naive_utc();
+    let expiry = now + chrono::Duration::seconds(gdpr.export_link_ttl_seconds);
+    let since = now - chrono::Duration::seconds(constants::DATA_EXPORT_REQUEST_INTERVAL_SECONDS);
+    let recipient = address.clone();
+    // No link for unknown addresses, nor more than one per interval
+    let token = db::run(&pool, move |conn| {
+        if Candidate::find_by_email(&address, conn)?.is_empty() {
+            return Ok(None);
+        }
+        DataExportRequest::create(&address, since, expiry, conn)
+    })
+    .await?;
+
+    if let Some(token) = token {
+        let email = Email {
+            to: recipient,
+            subject: constants::DATA_EXPORT_EMAIL_SUBJECT.to_string(),
+            body: format!(
+                "You asked for a copy of the data kept about your applications.\n\n\
+                 Download it from the link below, valid until {} UTC:\n{}\n\n\
+                 If you did not ask for it, ignore this email.",
+                expiry.format("%Y-%m-%d %H:%M"),
+                gdpr.export_link.replace("{token}", &token)
+            ),
+        };
+        // Sent after the answer, its time would tell the addresses that applied. A failure is
+        // reported like an unknown address, the candidate asks again
+        actix_web::rt::spawn(async move {
+            match web::block(move || mailer.send(&email)).await {
+                Ok(Ok(())) => {}
+                Ok(Err(e)) => error!("Data export link not sent: {}", e),
+                Err(e) => error!("Data export link not sent: {}", e),
+            }
+        });
+    }
+    Ok(HttpResponse::Accepted().json(ResponseBody::new(
+        constants::MESSAGE_DATA_EXPORT_REQUESTED,
+        constants::EMPTY,
+    )))
+}
+
+// GET api/candidates/data-export
+#[utoipa::path(
+    get,
+    path = "/api/candidates/data-export",
+    tag = "privacy",
+    params(DataExportDownload),
+    responses(
+        (status = 200, description = "ZIP of the applications sent with the address of the link, in every company: `data.json` and the CV files", content_type = "application/zip"),
+        (status = 404, description = "Unknown or expired link", body = ProblemDetails, content_type = "application/problem+json"),
+    )
+)]
+pub async fn download_export(
+    query: web::Query<DataExportDownload>,
+    pool: web::Data<Pool>,
+    storage: web::Data<CvStorage>,
+) -> Result<HttpResponse, AppError> {
+    let token = query.into_inner().token;
+    let candidates = db::run(&pool, move |conn| {
+        match DataExportRequest::redeem(&token, conn)? {
+            Some(address) => Candidate::find_by_email(&address, conn).map(Some),
+            None => Ok(None),
+        }
+    })
+    .await?
+    .ok_or_else(|| AppError::NotFound {
+        error_message: constants::MESSAGE_DATA_EXPORT_LINK_INVALID.to_string(),
+    })?;
+    info!(
+        "Data export of {} applications downloaded",
+        candidates.len()
+    );
+    send_export(candidates, &pool, storage).await
+}
+
+// GET api/admin/candidates/{id}/data-export
+#[utoipa::path(
+    get,
+    path = "/api/admin/candidates/{id}/data-export",
+    tag = "privacy",
+    security(("bearer_auth" = [])),
+    params(("id" = Uuid, Path, description = "Candidate id")),
+    responses(
+        (status = 200, description = "ZIP of the applications sent with the email of the candidate to the companies of the caller, every company for super admins", content_type = "application/zip"),
+        (status = 401, description = "Missing or invalid token", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 403, description = "Candidate of another company", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 404, description = "Unknown candidate, or candidate whose personal data was erased", body = ProblemDetails, content_type = "application/problem+json"),
+    )
+)]
+pub async fn export_candidate_data(
+    auth: AuthenticatedUser,
+    candidate_id: web::Path<Uuid>,
+    pool: web::Data<Pool>,
+    storage: web::Data<CvStorage>,
+) -> Result<HttpResponse, AppError> {
+    let candidate = managed_candidate(&auth, candidate_id.into_inner(), &pool).await?;
+    if candidate.anonymised_at.is_some() {
+        return Err(AppError::NotFound {
+            error_message: constants::MESSAGE_CANDIDATE_ANONYMISED.to_string(),
+        });
+    }
+    let address = candidate.email.clone();
+    let mut candidates = if address.is_empty() {
+        vec![candidate]
+    } else {
+        db::run(&pool, move |conn| Candidate::find_by_email(&address, conn)).await?
+    };
+    candidates.retain(|application| auth.can_manage_company(application.company_id));
+    info!(
+        "Data export of {} applications made by {}",
+        candidates.len(),
+        auth.user.id
+    );
+    send_export(candidates, &pool, storage).await
+}
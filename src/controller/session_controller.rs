@@ -0,0 +1,149 @@
+use actix_web::{web, HttpResponse};
+use uuid::Uuid;
+
+use crate::{
+    config::db::{self, Pool},
+    constants,
+    error::{AppError, ProblemDetails},
+    models::{
+        response::ResponseBody,
+        user::User,
+        user_session::{UserSession, UserSessionDTO},
+    },
+    utils::auth::AuthenticatedUser,
+};
+
+// GET api/auth/sessions
+#[utoipa::path(
+    get,
+    path = "/api/auth/sessions",
+    tag = "sessions",
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "Active sessions of the caller", body = ResponseBody<Vec<UserSessionDTO>>),
+        (status = 401, description = "Missing or invalid token", body = ProblemDetails, content_type = "application/problem+json"),
+    )
+)]
+pub async fn list_sessions(
+    auth: AuthenticatedUser,
+    pool: web::Data<Pool>,
+) -> Result<HttpResponse, AppError> {
+    let user_id = auth.user.id;
+    let sessions: Vec<UserSessionDTO> = db::run(&pool, move |conn| {
+        UserSession::find_active_by_user_id(user_id, conn)
+    })
+    .await?
+    .iter()
+    .map(|session| session.to_dto(&auth.session.session_token))
+    .collect();
+    Ok(HttpResponse::Ok().json(ResponseBody::new(constants::MESSAGE_OK, sessions)))
+}
+
+// DELETE api/auth/sessions/{id}
+#[utoipa::path(
+    delete,
+    path = "/api/auth/sessions/{id}",
+    tag = "sessions",
+    security(("bearer_auth" = [])),
+    params(("id" = Uuid, Path, description = "Session id")),
+    responses(
+        (status = 200, description = "Session revoked", body = ResponseBody<String>),
+        (status = 401, description = "Missing or invalid token", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 404, description = "Unknown session", body = ProblemDetails, content_type = "application/problem+json"),
+    )
+)]
+pub async fn revoke_session(
+    auth: AuthenticatedUser,
+    session_id: web::Path<Uuid>,
+    pool: web::Data<Pool>,
+) -> Result<HttpResponse, AppError> {
+    let user_id = auth.user.id;
+    let revoked = db::run(&pool, move |conn| {
+        UserSession::revoke(session_id.into_inner(), user_id, conn)
+    })
+    .await?;
+    match revoked {
+        0 => Err(AppError::NotFound {
+            error_message: constants::MESSAGE_SESSION_NOT_FOUND.to_string(),
+        }),
+        _ => Ok(HttpResponse::Ok().json(ResponseBody::new(
+            constants::MESSAGE_SESSION_REVOKED,
+            constants::EMPTY,
+        ))),
+    }
+}
+
+async fn find_managed_user(
+    auth: &AuthenticatedUser,
+    user_id: Uuid,
+    pool: &Pool,
+) -> Result<User, AppError> {
+    let target = db::run(pool, move |conn| {
+        User::find_by_id(user_id, conn)
+            .map_err(AppError::not_found(constants::MESSAGE_USER_NOT_FOUND))
+    })
+    .await?;
+    if !auth.can_manage(&target) {
+        return Err(AppError::Forbidden {
+            error_message: constants::MESSAGE_FORBIDDEN.to_string(),
+        });
+    }
+    Ok(target)
+}
+
+// DELETE api/admin/users/{id}/sessions
+#[utoipa::path(
+    delete,
+    path = "/api/admin/users/{id}/sessions",
+    tag = "admin",
+    security(("bearer_auth" = [])),
+    params(("id" = Uuid, Path, description = "User id")),
+    responses(
+        (status = 200, description = "Number of revoked sessions", body = ResponseBody<usize>),
+        (status = 401, description = "Missing or invalid token", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 403, description = "User managed by someone else", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 404, description = "Unknown user", body = ProblemDetails, content_type = "application/problem+json"),
+    )
+)]
+pub async fn revoke_user_sessions(
+    auth: AuthenticatedUser,
+    user_id: web::Path<Uuid>,
+    pool: web::Data<Pool>,
+) -> Result<HttpResponse, AppError> {
+    let target = find_managed_user(&auth, user_id.into_inner(), &pool).await?;
+    let revoked = db::run(&pool, move |conn| {
+        UserSession::revoke_all_for_user(target.id, conn)
+    })
+    .await?;
+    Ok(HttpResponse::Ok().json(ResponseBody::new(
+        constants::MESSAGE_SESSION_REVOKED,
+        revoked,
+    )))
+}
+
+// POST api/admin/users/{id}/unlock
+#[utoipa::path(
+    post,
+    path = "/api/admin/users/{id}/unlock",
+    tag = "admin",
+    security(("bearer_auth" = [])),
+    params(("id" = Uuid, Path, description = "User id")),
+    responses(
+        (status = 200, description = "Account unlocked", body = ResponseBody<String>),
+        (status = 401, description = "Missing or invalid token", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 403, description = "User managed by someone else", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 404, description = "Unknown user", body = ProblemDetails, content_type = "application/problem+json"),
+    )
+)]
+pub async fn unlock_user(
+    auth: AuthenticatedUser,
+    user_id: web::Path<Uuid>,
+    pool: web::Data<Pool>,
+) -> Result<HttpResponse, AppError> {
+    let target = find_managed_user(&auth, user_id.into_inner(), &pool).await?;
+    db::run(&pool, move |conn| User::unlock(target.id, conn)).await?;
+    Ok(HttpResponse::Ok().json(ResponseBody::new(
+        constants::MESSAGE_ACCOUNT_UNLOCKED,
+        constants::EMPTY,
+    )))
+}
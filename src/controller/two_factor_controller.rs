@@ -0,0 +1,185 @@
+use actix_web::{web, HttpResponse};
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use crate::{
+    config::db::{self, Pool},
+    constants,
+    error::{AppError, ProblemDetails},
+    models::{recovery_code::RecoveryCode, response::ResponseBody, user::User},
+    utils::{
+        auth::{AuthenticatedUser, SessionUser},
+        totp,
+    },
+};
+
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct TwoFactorCodeDTO {
+    pub code: String,
+}
+
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct TwoFactorEnrolmentDTO {
+    pub secret: String,
+    pub otpauth_uri: String,
+}
+
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct RecoveryCodesDTO {
+    pub recovery_codes: Vec<String>,
+}
+
+fn invalid_code() -> AppError {
+    AppError::BadRequest {
+        error_message: constants::MESSAGE_INVALID_TWO_FACTOR_CODE.to_string(),
+    }
+}
+
+// POST api/auth/2fa/enroll
+#[utoipa::path(
+    post,
+    path = "/api/auth/2fa/enroll",
+    tag = "two-factor",
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "Secret to add to the authenticator app", body = ResponseBody<TwoFactorEnrolmentDTO>),
+        (status = 400, description = "Two-factor authentication already enabled", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 401, description = "Missing or invalid token", body = ProblemDetails, content_type = "application/problem+json"),
+    )
+)]
+pub async fn enroll(auth: SessionUser, pool: web::Data<Pool>) -> Result<HttpResponse, AppError> {
+    if auth.user.totp_enabled {
+        return Err(AppError::BadRequest {
+            error_message: constants::MESSAGE_TWO_FACTOR_ALREADY_ENABLED.to_string(),
+        });
+    }
+    let user_id = auth.user.id;
+    let secret = db::run(&pool, move |conn| User::begin_totp_enrolment(user_id, conn)).await?;
+    let otpauth_uri = totp::otpauth_uri(&secret, &auth.user.email, constants::TOTP_ISSUER);
+    Ok(HttpResponse::Ok().json(ResponseBody::new(
+        constants::MESSAGE_OK,
+        TwoFactorEnrolmentDTO {
+            secret,
+            otpauth_uri,
+        },
+    )))
+}
+
+// POST api/auth/2fa/confirm
+#[utoipa::path(
+    post,
+    path = "/api/auth/2fa/confirm",
+    tag = "two-factor",
+    security(("bearer_auth" = [])),
+    request_body = TwoFactorCodeDTO,
+    responses(
+        (status = 200, description = "Two-factor authentication enabled", body = ResponseBody<RecoveryCodesDTO>),
+        (status = 400, description = "Invalid code or already enabled", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 401, description = "Missing or invalid token", body = ProblemDetails, content_type = "application/problem+json"),
+    )
+)]
+pub async fn confirm(
+    auth: SessionUser,
+    body: web::Json<TwoFactorCodeDTO>,
+    pool: web::Data<Pool>,
+) -> Result<HttpResponse, AppError> {
+    if auth.user.totp_enabled {
+        return Err(AppError::BadRequest {
+            error_message: constants::MESSAGE_TWO_FACTOR_ALREADY_ENABLED.to_string(),
+        });
+    }
+    let confirmed = db::run(&pool, move |conn| {
+        User::confirm_totp_enrolment(&auth.user, &body.code, Utc::now().timestamp(), conn)
+    })
+    .await?;
+    match confirmed {
+        Some(recovery_codes) => Ok(HttpResponse::Ok().json(ResponseBody::new(
+            constants::MESSAGE_TWO_FACTOR_ENABLED,
+            RecoveryCodesDTO { recovery_codes },
+        ))),
+        None => Err(invalid_code()),
+    }
+}
+
+// POST api/auth/2fa/recovery-codes
+#[utoipa::path(
+    post,
+    path = "/api/auth/2fa/recovery-codes",
+    tag = "two-factor",
+    security(("bearer_auth" = [])),
+    request_body = TwoFactorCodeDTO,
+    responses(
+        (status = 200, description = "New recovery codes, the previous ones are revoked", body = ResponseBody<RecoveryCodesDTO>),
+        (status = 400, description = "Invalid code or not enabled", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 401, description = "Missing or invalid token", body = ProblemDetails, content_type = "application/problem+json"),
+    )
+)]
+pub async fn regenerate_recovery_codes(
+    auth: AuthenticatedUser,
+    body: web::Json<TwoFactorCodeDTO>,
+    pool: web::Data<Pool>,
+) -> Result<HttpResponse, AppError> {
+    if !auth.user.totp_enabled {
+        return Err(AppError::BadRequest {
+            error_message: constants::MESSAGE_TWO_FACTOR_NOT_ENABLED.to_string(),
+        });
+    }
+    let recovery_codes = db::run(&pool, move |conn| {
+        if User::verify_second_factor(&auth.user, &body.code, Utc::now().timestamp(), conn)
+            .is_none()
+        {
+            return Err(invalid_code());
+        }
+        Ok(RecoveryCode::regenerate(auth.user.id, conn)?)
+    })
+    .await?;
+    Ok(HttpResponse::Ok().json(ResponseBody::new(
+        constants::MESSAGE_OK,
+        RecoveryCodesDTO { recovery_codes },
+    )))
+}
+
+// POST api/auth/2fa/disable
+#[utoipa::path(
+    post,
+    path = "/api/auth/2fa/disable",
+    tag = "two-factor",
+    security(("bearer_auth" = [])),
+    request_body = TwoFactorCodeDTO,
+    responses(
+        (status = 200, description = "Two-factor authentication disabled", body = ResponseBody<String>),
+        (status = 400, description = "Invalid code or not enabled", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 401, description = "Missing or invalid token", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 403, description = "Mandatory for administrators", body = ProblemDetails, content_type = "application/problem+json"),
+    )
+)]
+pub async fn disable(
+    auth: AuthenticatedUser,
+    body: web::Json<TwoFactorCodeDTO>,
+    pool: web::Data<Pool>,
+) -> Result<HttpResponse, AppError> {
+    if auth.user.role.requires_two_factor() {
+        return Err(AppError::Forbidden {
+            error_message: constants::MESSAGE_TWO_FACTOR_ENROLMENT_REQUIRED.to_string(),
+        });
+    }
+    if !auth.user.totp_enabled {
+        return Err(AppError::BadRequest {
+            error_message: constants::MESSAGE_TWO_FACTOR_NOT_ENABLED.to_string(),
+        });
+    }
+    db::run(&pool, move |conn| {
+        if User::verify_second_factor(&auth.user, &body.code, Utc::now().timestamp(), conn)
+            .is_none()
+        {
+            return Err(invalid_code());
+        }
+        Ok(User::disable_totp(auth.user.id, conn)?)
+    })
+    .await?;
+    Ok(HttpResponse::Ok().json(ResponseBody::new(
+        constants::MESSAGE_TWO_FACTOR_DISABLED,
+        constants::EMPTY,
+    )))
+}
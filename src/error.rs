@@ -0,0 +1,498 @@
+use actix_multipart::MultipartError;
+use actix_web::{
+    error::{BlockingError, JsonPayloadError, PathError, PayloadError, QueryPayloadError},
+    http::StatusCode,
+    HttpResponse, ResponseError,
+};
+use chrono::NaiveDateTime;
+use diesel::{
+    r2d2::PoolError,
+    result::{DatabaseErrorKind, Error as DieselError},
+};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use tracing::error;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use crate::{
+    constants,
+    templates::{self, front_template::ErrorTemplate},
+};
+
+pub const PROBLEM_JSON: &str = "application/problem+json";
+
+// Invalid field of a request body
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct FieldError {
+    pub field: String,
+    pub message: String,
+}
+
+#[derive(Debug)]
+pub enum AppError {
+    Unauthorized {
+        error_message: String,
+    },
+    Forbidden {
+        error_message: String,
+    },
+    NotFound {
+        error_message: String,
+    },
+    BadRequest {
+        error_message: String,
+    },
+    Conflict {
+        error_message: String,
+    },
+    UnprocessableEntity {
+        error_message: String,
+        errors: Vec<FieldError>,
+    },
+    PayloadTooLarge {
+        error_message: String,
+    },
+    TooManyRequests {
+        error_message: String,
+    },
+    // The message is logged along with a correlation id and never sent to the client
+    InternalServerError {
+        error_message: String,
+    },
+}
+
+// RFC 7807 problem details
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct ProblemDetails {
+    #[serde(rename = "type")]
+    pub problem_type: String,
+    pub title: String,
+    pub status: u16,
+    pub detail: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub correlation_id: Option<Uuid>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub errors: Vec<FieldError>,
+}
+
+impl AppError {
+    pub fn validation(errors: Vec<FieldError>) -> AppError {
+        AppError::UnprocessableEntity {
+            error_message: constants::MESSAGE_VALIDATION_FAILED.to_string(),
+            errors,
+        }
+    }
+
+    // Map a missing row to a 404 with `message`, other database errors stay internal
+    pub fn not_found(message: &str) -> impl Fn(DieselError) -> AppError + '_ {
+        move |error| match error {
+            DieselError::NotFound => AppError::NotFound {
+                error_message: message.to_string(),
+            },
+            error => AppError::from(error),
+        }
+    }
+
+    fn error_message(&self) -> &str {
+        match self {
+            AppError::Unauthorized { error_message }
+            | AppError::Forbidden { error_message }
+            | AppError::NotFound { error_message }
+            | AppError::BadRequest { error_message }
+            | AppError::Conflict { error_message }
+            | AppError::UnprocessableEntity { error_message, .. }
+            | AppError::PayloadTooLarge { error_message }
+            | AppError::TooManyRequests { error_message }
+            | AppError::InternalServerError { error_message } => error_message,
+        }
+    }
+
+    pub fn problem(&self) -> ProblemDetails {
+        let status = self.status_code();
+        let mut problem = ProblemDetails {
+            problem_type: "about:blank".to_string(),
+            title: status.canonical_reason().unwrap_or("Error").to_string(),
+            status: status.as_u16(),
+            detail: self.error_message().to_string(),
+            correlation_id: None,
+            errors: Vec::new(),
+        };
+        match self {
+            AppError::InternalServerError { error_message } => {
+                let correlation_id = Uuid::new_v4();
+                error!("[{}] {}", correlation_id, error_message);
+                problem.detail = constants::MESSAGE_INTERNAL_SERVER_ERROR.to_string();
+                problem.correlation_id = Some(correlation_id);
+            }
+            AppError::UnprocessableEntity { errors, .. } => problem.errors = errors.clone(),
+            _ => {}
+        }
+        problem
+    }
+}
+
+impl fmt::Display for AppError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.error_message())
+    }
+}
+
+impl ResponseError for AppError {
+    fn status_code(&self) -> StatusCode {
+        match self {
+            AppError::Unauthorized { .. } => StatusCode::UNAUTHORIZED,
+            AppError::Forbidden { .. } => StatusCode::FORBIDDEN,
+            AppError::NotFound { .. } => StatusCode::NOT_FOUND,
+            AppError::BadRequest { .. } => StatusCode::BAD_REQUEST,
+            AppError::Conflict { .. } => StatusCode::CONFLICT,
+            AppError::UnprocessableEntity { .. } => StatusCode::UNPROCESSABLE_ENTITY,
+            AppError::PayloadTooLarge { .. } => StatusCode::PAYLOAD_TOO_LARGE,
+            AppError::TooManyRequests { .. } => StatusCode::TOO_MANY_REQUESTS,
+            AppError::InternalServerError { .. } => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+
+    fn error_response(&self) -> HttpResponse {
+        HttpResponse::build(self.status_code())
+            .content_type(PROBLEM_JSON)
+            .json(self.problem())
+    }
+}
+
+impl From<DieselError> for AppError {
+    fn from(error: DieselError) -> Self {
+        match error {
+            DieselError::NotFound => AppError::NotFound {
+                error_message: constants::MESSAGE_RESOURCE_NOT_FOUND.to_string(),
+            },
+            DieselError::DatabaseError(DatabaseErrorKind::UniqueViolation, _) => {
+                AppError::Conflict {
+                    error_message: constants::MESSAGE_RESOURCE_ALREADY_EXISTS.to_string(),
+                }
+            }
+            error => AppError::InternalServerError {
+                error_message: error.to_string(),
+            },
+        }
+    }
+}
+
+impl From<PoolError> for AppError {
+    fn from(error: PoolError) -> Self {
+        AppError::InternalServerError {
+            error_message: error.to_string(),
+        }
+    }
+}
+
+impl From<BlockingError> for AppError {
+    fn from(error: BlockingError) -> Self {
+        AppError::InternalServerError {
+            error_message: error.to_string(),
+        }
+    }
+}
+
+impl From<askama::Error> for AppError {
+    fn from(error: askama::Error) -> Self {
+        AppError::InternalServerError {
+            error_message: error.to_string(),
+        }
+    }
+}
+
+// Well-formed JSON with wrong or missing fields is a validation error, anything else a bad request
+impl From<JsonPayloadError> for AppError {
+    fn from(error: JsonPayloadError) -> Self {
+        match error {
+            JsonPayloadError::Deserialize(e) if e.is_data() => AppError::UnprocessableEntity {
+                error_message: e.to_string(),
+                errors: Vec::new(),
+            },
+            error => AppError::BadRequest {
+                error_message: error.to_string(),
+            },
+        }
+    }
+}
+
+// A form over the upload limit is a 413, a missing or malformed field a validation error
+impl From<MultipartError> for AppError {
+    fn from(error: MultipartError) -> Self {
+        match error {
+            MultipartError::Payload(PayloadError::Overflow) => AppError::PayloadTooLarge {
+                error_message: constants::MESSAGE_CV_TOO_LARGE.to_string(),
+            },
+            MultipartError::MissingField(field) => AppError::validation(vec![FieldError {
+                message: format!("{} is required", field),
+                field,
+            }]),
+            MultipartError::Field { name, source } => AppError::validation(vec![FieldError {
+                field: name,
+                message: source.to_string(),
+            }]),
+            error => AppError::BadRequest {
+                error_message: error.to_string(),
+            },
+        }
+    }
+}
+
+impl From<QueryPayloadError> for AppError {
+    fn from(error: QueryPayloadError) -> Self {
+        AppError::BadRequest {
+            error_message: error.to_string(),
+        }
+    }
+}
+
+impl From<PathError> for AppError {
+    fn from(error: PathError) -> Self {
+        AppError::NotFound {
+            error_message: error.to_string(),
+        }
+    }
+}
+
+// Error of an HTML route, rendered as an error page instead of problem details
+#[derive(Debug)]
+pub struct PageError(pub AppError);
+
+impl fmt::Display for PageError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+impl<E: Into<AppError>> From<E> for PageError {
+    fn from(error: E) -> Self {
+        PageError(error.into())
+    }
+}
+
+impl ResponseError for PageError {
+    fn status_code(&self) -> StatusCode {
+        self.0.status_code()
+    }
+
+    fn error_response(&self) -> HttpResponse {
+        let problem = self.0.problem();
+        let template = ErrorTemplate {
+            status: problem.status,
+            title: problem.title,
+            detail: problem.detail,
+            correlation_id: problem.correlation_id.map(|id| id.to_string()),
+        };
+        match templates::render(&template) {
+            Ok(body) => HttpResponse::build(self.status_code())
+                .content_type("text/html")
+                .body(body),
+            Err(e) => {
+                error!("Failed to render the error page: {}", e);
+                HttpResponse::build(self.status_code()).body(template.detail)
+            }
+        }
+    }
+}
+
+// Why `User::login` refused to open a session
+#[derive(Debug)]
+pub enum AuthError {
+    UnknownUser,
+    InvalidPassword,
+    PasswordNotSet,
+    TwoFactorRequired,
+    InvalidTwoFactorCode,
+    AccountLocked { until: NaiveDateTime },
+    TooManyAttempts,
+    Database(DieselError),
+}
+
+impl fmt::Display for AuthError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AuthError::UnknownUser => write!(f, "unknown user"),
+            AuthError::InvalidPassword => write!(f, "invalid password"),
+            AuthError::PasswordNotSet => write!(f, "password not set"),
+            AuthError::TwoFactorRequired => write!(f, "two-factor code required"),
+            AuthError::InvalidTwoFactorCode => write!(f, "invalid two-factor code"),
+            AuthError::AccountLocked { until } => write!(f, "account locked until {}", until),
+            AuthError::TooManyAttempts => write!(f, "too many failed attempts"),
+            AuthError::Database(e) => write!(f, "database error: {}", e),
+        }
+    }
+}
+
+impl From<DieselError> for AuthError {
+    fn from(error: DieselError) -> Self {
+        AuthError::Database(error)
+    }
+}
+
+// Unknown users, wrong and missing passwords share the same message to avoid user enumeration
+impl From<AuthError> for AppError {
+    fn from(error: AuthError) -> Self {
+        match error {
+            AuthError::UnknownUser | AuthError::InvalidPassword | AuthError::PasswordNotSet => {
+                AppError::Unauthorized {
+                    error_message: constants::MESSAGE_LOGIN_FAILED.to_string(),
+                }
+            }
+            AuthError::TwoFactorRequired => AppError::Unauthorized {
+                error_message: constants::MESSAGE_TWO_FACTOR_REQUIRED.to_string(),
+            },
+            AuthError::InvalidTwoFactorCode => AppError::Unauthorized {
+                error_message: constants::MESSAGE_INVALID_TWO_FACTOR_CODE.to_string(),
+            },
+            AuthError::AccountLocked { until } => AppError::TooManyRequests {
+                error_message: format!(
+                    "{} until {} UTC",
+                    constants::MESSAGE_ACCOUNT_LOCKED,
+                    until.format("%Y-%m-%d %H:%M:%S")
+                ),
+            },
+            AuthError::TooManyAttempts => AppError::TooManyRequests {
+                error_message: constants::MESSAGE_TOO_MANY_LOGIN_ATTEMPTS.to_string(),
+            },
+            AuthError::Database(e) => AppError::InternalServerError {
+                error_message: e.to_string(),
+            },
+        }
+    }
+}
+
+// Why `CandidateMerge::merge` refused to merge two candidates
+#[derive(Debug)]
+pub enum MergeError {
+    CandidateNotFound,
+    // Personal data of one of the candidates erased
+    Anonymised,
+    // Scanned or extracted after the merge, the CV of the duplicate would never be searched
+    CvPending,
+    Database(DieselError),
+}
+
+impl fmt::Display for MergeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MergeError::CandidateNotFound => write!(f, "candidate not found"),
+            MergeError::Anonymised => write!(f, "candidate anonymised"),
+            MergeError::CvPending => write!(f, "CV of the duplicate still processed"),
+            MergeError::Database(e) => write!(f, "database error: {}", e),
+        }
+    }
+}
+
+impl From<DieselError> for MergeError {
+    fn from(error: DieselError) -> Self {
+        match error {
+            DieselError::NotFound => MergeError::CandidateNotFound,
+            error => MergeError::Database(error),
+        }
+    }
+}
+
+impl From<MergeError> for AppError {
+    fn from(error: MergeError) -> Self {
+        match error {
+            MergeError::CandidateNotFound => AppError::NotFound {
+                error_message: constants::MESSAGE_CANDIDATE_NOT_FOUND.to_string(),
+            },
+            MergeError::Anonymised => AppError::validation(vec![FieldError {
+                field: "duplicate_id".to_string(),
+                message: constants::MESSAGE_CANDIDATE_ANONYMISED.to_string(),
+            }]),
+            MergeError::CvPending => AppError::Conflict {
+                error_message: constants::MESSAGE_MERGE_CV_PENDING.to_string(),
+            },
+            MergeError::Database(e) => AppError::InternalServerError {
+                error_message: e.to_string(),
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::{body::to_bytes, http::header};
+
+    async fn problem_of(error: &impl ResponseError) -> (StatusCode, String, ProblemDetails) {
+        let response = error.error_response();
+        let status = response.status();
+        let content_type = response
+            .headers()
+            .get(header::CONTENT_TYPE)
+            .unwrap()
+            .to_str()
+            .unwrap()
+            .to_string();
+        let body = to_bytes(response.into_body()).await.unwrap();
+        (status, content_type, serde_json::from_slice(&body).unwrap())
+    }
+
+    #[test]
+    fn test_diesel_errors_mapping() {
+        assert_eq!(
+            AppError::from(DieselError::NotFound).status_code(),
+            StatusCode::NOT_FOUND
+        );
+        let unique = DieselError::DatabaseError(
+            DatabaseErrorKind::UniqueViolation,
+            Box::new("duplicate key value violates unique constraint".to_string()),
+        );
+        assert_eq!(AppError::from(unique).status_code(), StatusCode::CONFLICT);
+        assert_eq!(
+            AppError::from(DieselError::RollbackTransaction).status_code(),
+            StatusCode::INTERNAL_SERVER_ERROR
+        );
+        assert_eq!(
+            AppError::not_found(constants::MESSAGE_USER_NOT_FOUND)(DieselError::NotFound)
+                .to_string(),
+            constants::MESSAGE_USER_NOT_FOUND
+        );
+    }
+
+    #[actix_web::test]
+    async fn test_problem_json_response() {
+        let (status, content_type, problem) = problem_of(&AppError::validation(vec![FieldError {
+            field: "email".to_string(),
+            message: "invalid email".to_string(),
+        }]))
+        .await;
+        assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+        assert_eq!(content_type, PROBLEM_JSON);
+        assert_eq!(problem.problem_type, "about:blank");
+        assert_eq!(problem.title, "Unprocessable Entity");
+        assert_eq!(problem.status, 422);
+        assert_eq!(problem.errors[0].field, "email");
+        assert!(problem.correlation_id.is_none());
+    }
+
+    #[actix_web::test]
+    async fn test_internal_error_hides_details() {
+        let (status, _, problem) = problem_of(&AppError::InternalServerError {
+            error_message: "connection refused".to_string(),
+        })
+        .await;
+        assert_eq!(status, StatusCode::INTERNAL_SERVER_ERROR);
+        assert_eq!(problem.detail, constants::MESSAGE_INTERNAL_SERVER_ERROR);
+        assert!(problem.correlation_id.is_some());
+    }
+
+    #[actix_web::test]
+    async fn test_page_error_renders_html() {
+        let response = PageError(AppError::NotFound {
+            error_message: constants::MESSAGE_PAGE_NOT_FOUND.to_string(),
+        })
+        .error_response();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+        assert_eq!(
+            response.headers().get(header::CONTENT_TYPE).unwrap(),
+            "text/html"
+        );
+        let body = to_bytes(response.into_body()).await.unwrap();
+        assert!(String::from_utf8_lossy(&body).contains(constants::MESSAGE_PAGE_NOT_FOUND));
+    }
+}
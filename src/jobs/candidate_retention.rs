@@ -0,0 +1,299 @@
+use std::{sync::Arc, time::Duration};
+
+use chrono::Utc;
+use diesel::Connection as _;
+use serde::Serialize;
+use tracing::{error, info, warn};
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use crate::{
+    config::{
+        db::{self, Connection, Pool},
+        settings::GdprSettings,
+    },
+    error::AppError,
+    jobs::{self, JobHandle},
+    models::{candidate::Candidate, data_export::DataExportRequest, duplicate::CandidateMerge},
+    storage::{CvError, CvStorage},
+};
+
+#[derive(Debug, PartialEq, Serialize, ToSchema)]
+pub struct ErasureReport {
+    pub candidate_id: Uuid,
+    // CV files of the application and of the applications merged into it
+    pub files_deleted: usize,
+    pub merges_deleted: usize,
+    // Download links sent to the address of the application
+    pub export_requests_deleted: usize,
+}
+
+#[derive(Debug, Default, PartialEq, Serialize)]
+pub struct RetentionReport {
+    pub anonymised: usize,
+    // Left as they are, tried again on the next run
+    pub failed: usize,
+    pub export_requests_deleted: usize,
+}
+
+// Erase the personal data of a candidate under the lock of its row, taken by merges too: the CV
+// files of the candidate and of the applications merged into it are deleted first, then the
+// merged applications, the data export links of its address and the personal fields. A file that
+// cannot be deleted rolls the erasure back, to be tried again whole. Erasing an anonymised
+// candidate again deletes nothing
+pub fn erase(
+    storage: &CvStorage,
+    i_candidate: Uuid,
+    conn: &mut Connection,
+) -> Result<ErasureReport, AppError> {
+    conn.transaction(|conn| {
+        let candidate = Candidate::lock(i_candidate, conn)?;
+        let merges = CandidateMerge::find_by_survivor(i_candidate, conn)?;
+        let mut files_deleted = 0;
+        for file_name in merges
+            .iter()
+            .filter_map(CandidateMerge::file_name)
+            .chain([candidate.file_name])
+        {
+            match storage.delete(&file_name) {
+                Ok(()) => files_deleted += 1,
+                // Blank once anonymised, nothing was stored under it
+                Err(CvError::InvalidName) => {}
+                Err(e) => return Err(e.into()),
+            }
+        }
+        let merges_deleted = CandidateMerge::delete_by_survivor(i_candidate, conn)?;
+        let export_requests_deleted = DataExportRequest::delete_by_email(&candidate.email, conn)?;
+        Candidate::anonymise(i_candidate, conn)?;
+        Ok(ErasureReport {
+            candidate_id: i_candidate,
+            files_deleted,
+            merges_deleted,
+            export_requests_deleted,
+        })
+    })
+}
+
+// Erase one batch of the applications older than the retention of their company, and forget the
+// expired data export links
+pub fn anonymise_expired(
+    storage: &CvStorage,
+    settings: &GdprSettings,
+    conn: &mut Connection,
+) -> Result<RetentionReport, AppError> {
+    let mut report = RetentionReport::default();
+    let expired = Candidate::find_expired(
+        settings.default_retention_days,
+        settings.retention_batch_size,
+        conn,
+    )?;
+    for candidate in expired {
+        match erase(storage, candidate.id, conn) {
+            Ok(_) => report.anonymised += 1,
+            Err(e) => {
+                warn!("Anonymisation of candidate {} failed: {}", candidate.id, e);
+                report.failed += 1;
+            }
+        }
+    }
+    report.export_requests_deleted =
+        DataExportRequest::delete_expired(Utc::now().naive_utc(), conn)?;
+    Ok(report)
+}
+
+// Anonymise the expired applications every `gdpr.retention_interval_seconds`
+pub fn spawn(pool: Pool, storage: Arc<CvStorage>, settings: GdprSettings) -> JobHandle {
+    let interval = Duration::from_secs(settings.retention_interval_seconds);
+    jobs::spawn_periodic("Candidate retention", interval, move || {
+        let pool = pool.clone();
+        let storage = storage.clone();
+        let settings = settings.clone();
+        async move {
+            match db::run(&pool, move |conn| {
+                anonymise_expired(&storage, &settings, conn)
+            })
+            .await
+            {
+                Ok(report) if report == RetentionReport::default() => {}
+                Ok(report) => info!("Candidate retention: {:?}", report),
+                Err(e) => error!("Candidate retention failed: {}", e),
+            }
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        admin::create_company,
+        models::{
+            candidate::{CandidateDTO, ScanStatus},
+            company::Company,
+        },
+        storage::{scanner::NoopScanner, validation::SizeLimits},
+    };
+    use diesel::RunQueryDsl;
+    use testcontainers::{clients, images::postgres::Postgres};
+
+    const PDF: &[u8] = b"%PDF-1.4\n%\xe2\xe3\xcf\xd3\n";
+
+    fn settings() -> GdprSettings {
+        GdprSettings {
+            consent_version: "1".to_string(),
+            default_retention_days: 730,
+            retention_interval_seconds: 3600,
+            retention_batch_size: 10,
+            export_link: "http://localhost/{token}".to_string(),
+            export_link_ttl_seconds: 3600,
+        }
+    }
+
+    // Application with a released CV, received `days` ago
+    fn apply(
+        storage: &CvStorage,
+        i_company: Uuid,
+        name: &str,
+        days: i32,
+        conn: &mut Connection,
+    ) -> Candidate {
+        let stored = storage.store(i_company, PDF).unwrap();
+        storage.scan(&stored.file_name, PDF, &NoopScanner).unwrap();
+        let candidate = Candidate::insert(
+            CandidateDTO {
+                company_id: i_company,
+                lastname: name.to_string(),
+                firstname: "Jane".to_string(),
+                file_name: stored.file_name,
+                phone: "+33612345678".to_string(),
+                email: format!("{}@example.com", name.to_lowercase()),
+                motivation: "Motivated".to_string(),
+            },
+            conn,
+        )
+        .unwrap();
+        Candidate::record_consent(candidate.id, "1", Some("192.0.2.1".to_string()), conn).unwrap();
+        diesel::sql_query(format!(
+            "UPDATE candidate SET applied_at = NOW() - INTERVAL '{} days' WHERE id = '{}'",
+            days, candidate.id
+        ))
+        .execute(conn)
+        .unwrap();
+        Candidate::find_by_id(candidate.id, conn).unwrap()
+    }
+
+    #[actix_web::test]
+    async fn test_anonymise_expired() {
+        let docker = clients::Cli::default();
+        let postgres = docker.run(Postgres::default());
+        let pool = db::test_pool(postgres.get_host_port_ipv4(5432));
+        let conn = &mut pool.get().unwrap();
+        db::run_migration(conn);
+        let acme = create_company("Acme", conn).unwrap().id();
+        let globex = create_company("Globex", conn).unwrap().id();
+        Company::set_retention(globex, Some(30), conn).unwrap();
+        let root = std::env::temp_dir().join(format!("platform-cv-retention-{}", Uuid::new_v4()));
+        let storage = CvStorage::local(
+            &root,
+            SizeLimits {
+                document: 1024 * 1024,
+                image: 1024 * 1024,
+            },
+        );
+
+        let recent = apply(&storage, acme, "Recent", 100, conn);
+        let expired = apply(&storage, acme, "Expired", 800, conn);
+        // Within the default retention, past the one of its company
+        let short = apply(&storage, globex, "Short", 100, conn);
+
+        let report = anonymise_expired(&storage, &settings(), conn).unwrap();
+        assert_eq!(
+            report,
+            RetentionReport {
+                anonymised: 2,
+                ..RetentionReport::default()
+            }
+        );
+        for candidate in [&expired, &short] {
+            let anonymised = Candidate::find_by_id(candidate.id, conn).unwrap();
+            assert!(anonymised.anonymised_at.is_some());
+            assert_eq!(
+                (anonymised.lastname.as_str(), anonymised.email.as_str()),
+                ("", "")
+            );
+            assert_eq!(anonymised.consent_version.as_deref(), Some("1"));
+            assert_eq!(anonymised.consent_ip, None);
+            assert!(storage.read(&candidate.file_name).is_err());
+        }
+        let kept = Candidate::find_by_id(recent.id, conn).unwrap();
+        assert!(kept.anonymised_at.is_none());
+        assert!(storage.read(&kept.file_name).is_ok());
+        // Anonymised once
+        assert_eq!(
+            anonymise_expired(&storage, &settings(), conn).unwrap(),
+            RetentionReport::default()
+        );
+
+        let _ = std::fs::remove_dir_all(root);
+    }
+
+    #[actix_web::test]
+    async fn test_erase_merged_applications() {
+        let docker = clients::Cli::default();
+        let postgres = docker.run(Postgres::default());
+        let pool = db::test_pool(postgres.get_host_port_ipv4(5432));
+        let conn = &mut pool.get().unwrap();
+        db::run_migration(conn);
+        let acme = create_company("Acme", conn).unwrap().id();
+        let root = std::env::temp_dir().join(format!("platform-cv-erasure-{}", Uuid::new_v4()));
+        let storage = CvStorage::local(
+            &root,
+            SizeLimits {
+                document: 1024 * 1024,
+                image: 1024 * 1024,
+            },
+        );
+
+        let survivor = apply(&storage, acme, "Doe", 10, conn);
+        let duplicate = apply(&storage, acme, "Doe", 20, conn);
+        Candidate::record_scan(duplicate.id, ScanStatus::Clean, None, conn).unwrap();
+        Candidate::record_text(duplicate.id, "Rust developer".to_string(), Some(1), conn).unwrap();
+        CandidateMerge::merge(survivor.id, duplicate.id, None, conn).unwrap();
+        let now = Utc::now().naive_utc();
+        DataExportRequest::create(
+            &survivor.email,
+            now - chrono::Duration::minutes(5),
+            now + chrono::Duration::hours(1),
+            conn,
+        )
+        .unwrap();
+
+        let report = erase(&storage, survivor.id, conn).unwrap();
+        assert_eq!(
+            report,
+            ErasureReport {
+                candidate_id: survivor.id,
+                files_deleted: 2,
+                merges_deleted: 1,
+                export_requests_deleted: 1
+            }
+        );
+        assert!(storage.read(&survivor.file_name).is_err());
+        assert!(storage.read(&duplicate.file_name).is_err());
+        assert!(CandidateMerge::find_by_survivor(survivor.id, conn)
+            .unwrap()
+            .is_empty());
+        // Nothing left to delete
+        assert_eq!(
+            erase(&storage, survivor.id, conn).unwrap(),
+            ErasureReport {
+                candidate_id: survivor.id,
+                files_deleted: 0,
+                merges_deleted: 0,
+                export_requests_deleted: 0
+            }
+        );
+
+        let _ = std::fs::remove_dir_all(root);
+    }
+}
@@ -0,0 +1,487 @@
+use std::{sync::Arc, time::Duration};
+
+use actix_web::web;
+use chrono::{NaiveDateTime, Utc};
+use diesel::{Connection as _, QueryResult};
+use serde::Serialize;
+use tracing::{error, info, warn};
+
+use crate::{
+    config::{
+        db::{self, Connection, Pool},
+        settings::ExtractionSettings,
+    },
+    constants,
+    error::AppError,
+    jobs::{self, JobHandle},
+    models::candidate::{Candidate, TextStatus},
+    parsing::skills::SkillDictionary,
+    storage::{
+        extraction::{self, ExtractedText, ExtractionError},
+        validation::CvFormat,
+        CvStorage,
+    },
+};
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize)]
+pub struct ExtractionReport {
+    pub extracted: usize,
+    pub unsupported: usize,
+    // Failed, tried again after a delay
+    pub retried: usize,
+    // Failed `max_attempts` times, given up
+    pub failed: usize,
+    // Erased while their CV was extracted
+    pub skipped: usize,
+    // Outcome not stored, claimed again once the claim expires
+    pub unrecorded: usize,
+}
+
+// Next attempt after `attempts` failures, `None` once `max_attempts` is reached
+fn retry_at(
+    attempts: i32,
+    settings: &ExtractionSettings,
+    now: NaiveDateTime,
+) -> Option<NaiveDateTime> {
+    if attempts >= settings.max_attempts {
+        return None;
+    }
+    let factor = 1u64 << (attempts - 1).clamp(0, 16);
+    let delay = settings.retry_delay_seconds.saturating_mul(factor);
+    Some(now + chrono::Duration::seconds(delay.min(i64::MAX as u64) as i64))
+}
+
+// Store the outcome of the extraction of one CV then parse it, under the lock of its row. A
+// candidate erased while its CV was read is left as it is, its text and contact details are not
+// written back. The text of the CVs merged meanwhile is parsed along
+fn record(
+    candidate: &Candidate,
+    outcome: Result<ExtractedText, String>,
+    skills: &SkillDictionary,
+    settings: &ExtractionSettings,
+    now: NaiveDateTime,
+    report: &mut ExtractionReport,
+    conn: &mut Connection,
+) -> QueryResult<()> {
+    conn.transaction(|conn| {
+        if Candidate::lock(candidate.id, conn)?.anonymised_at.is_some() {
+            report.skipped += 1;
+            return Ok(());
+        }
+        match outcome {
+            Ok(extracted) => {
+                Candidate::record_text(candidate.id, extracted.text, extracted.page_count, conn)?;
+                Candidate::parse_cv(candidate.id, skills, conn)?;
+                report.extracted += 1;
+            }
+            Err(e) => {
+                let retry_at = retry_at(candidate.cv_text_attempts + 1, settings, now);
+                let status = match retry_at {
+                    Some(_) => {
+                        report.retried += 1;
+                        TextStatus::Pending
+                    }
+                    None => {
+                        report.failed += 1;
+                        TextStatus::Failed
+                    }
+                };
+                warn!(
+                    "Text extraction of the CV of candidate {} failed ({}): {}",
+                    candidate.id, status, e
+                );
+                Candidate::record_text_failure(candidate.id, status, e, retry_at, conn)?;
+            }
+        }
+        Ok(())
+    })
+}
+
+// Extract then parse the text of one batch of clean CVs. The batch is claimed in a short
+// transaction, the CVs are read and extracted on the blocking thread pool without holding a
+// connection. A storage or extraction failure is retried with a growing delay, the candidate
+// stays searchable by its names and motivation meanwhile. An outcome that cannot be stored is
+// logged and the rest of the batch goes on
+pub async fn extract_pending(
+    pool: &Pool,
+    storage: Arc<CvStorage>,
+    skills: Arc<SkillDictionary>,
+    settings: &ExtractionSettings,
+) -> Result<ExtractionReport, AppError> {
+    let now = Utc::now().naive_utc();
+    let claimed_until = now + chrono::Duration::seconds(constants::CV_TEXT_CLAIM_SECONDS);
+    let batch_size = settings.batch_size;
+    let claimed = db::run(pool, move |conn| {
+        Candidate::claim_pending_text(now, claimed_until, batch_size, conn)
+    })
+    .await?;
+
+    let mut report = ExtractionReport::default();
+    for candidate in claimed {
+        let format = CvFormat::from_file_name(&candidate.file_name);
+        let outcome = match format {
+            Some(format) if extraction::supports(format) => {
+                let storage = storage.clone();
+                let file_name = candidate.file_name.clone();
+                web::block(move || {
+                    let content = storage.read(&file_name).map_err(|e| e.to_string())?;
+                    extraction::extract(format, &content).map_err(|e| e.to_string())
+                })
+                .await?
+            }
+            _ => {
+                let error = match format {
+                    Some(format) => ExtractionError::Unsupported(format).to_string(),
+                    None => "unknown CV extension".to_string(),
+                };
+                match db::run(pool, move |conn| {
+                    Candidate::record_text_failure(
+                        candidate.id,
+                        TextStatus::Unsupported,
+                        error,
+                        None,
+                        conn,
+                    )
+                })
+                .await
+                {
+                    Ok(_) => report.unsupported += 1,
+                    Err(e) => {
+                        error!("CV text of candidate {} not recorded: {}", candidate.id, e);
+                        report.unrecorded += 1;
+                    }
+                }
+                continue;
+            }
+        };
+        let i_candidate = candidate.id;
+        let skills = skills.clone();
+        let settings = settings.clone();
+        // Rolled back, the counts of this CV are dropped with it
+        let before = report.clone();
+        report = match db::run(pool, move |conn| {
+            record(
+                &candidate,
+                outcome,
+                &skills,
+                &settings,
+                now,
+                &mut report,
+                conn,
+            )
+            .map(|()| report)
+        })
+        .await
+        {
+            Ok(report) => report,
+            Err(e) => {
+                error!("CV text of candidate {} not recorded: {}", i_candidate, e);
+                ExtractionReport {
+                    unrecorded: before.unrecorded + 1,
+                    ..before
+                }
+            }
+        };
+    }
+    Ok(report)
+}
+
+// Extract and parse the CV texts every `extraction.interval_seconds`
+pub fn spawn(
+    pool: Pool,
+    storage: Arc<CvStorage>,
+    skills: Arc<SkillDictionary>,
+    settings: ExtractionSettings,
+) -> JobHandle {
+    let interval = Duration::from_secs(settings.interval_seconds);
+    jobs::spawn_periodic("CV text extraction", interval, move || {
+        let pool = pool.clone();
+        let storage = storage.clone();
+        let skills = skills.clone();
+        let settings = settings.clone();
+        async move {
+            match extract_pending(&pool, storage, skills, &settings).await {
+                Ok(report) if report == ExtractionReport::default() => {}
+                Ok(report) => info!("CV text extraction: {:?}", report),
+                Err(e) => error!("CV text extraction failed: {}", e),
+            }
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        admin::create_company,
+        jobs::candidate_retention,
+        models::{candidate::CandidateDTO, pagination::PaginationParams, skill::Skill},
+        storage::{
+            extraction::tests::{docx, pdf},
+            scanner::NoopScanner,
+            validation::SizeLimits,
+        },
+    };
+    use testcontainers::{clients, images::postgres::Postgres};
+    use uuid::Uuid;
+
+    fn settings() -> ExtractionSettings {
+        ExtractionSettings {
+            interval_seconds: 30,
+            batch_size: 10,
+            max_attempts: 2,
+            retry_delay_seconds: 0,
+            skills_file: None,
+        }
+    }
+
+    #[test]
+    fn test_retry_delay_doubles() {
+        let settings = ExtractionSettings {
+            max_attempts: 4,
+            retry_delay_seconds: 60,
+            ..settings()
+        };
+        let now = Utc::now().naive_utc();
+        assert_eq!(
+            retry_at(1, &settings, now),
+            Some(now + chrono::Duration::seconds(60))
+        );
+        assert_eq!(
+            retry_at(3, &settings, now),
+            Some(now + chrono::Duration::seconds(240))
+        );
+        assert_eq!(retry_at(4, &settings, now), None);
+    }
+
+    #[actix_web::test]
+    async fn test_extract_pending_then_search() {
+        let docker = clients::Cli::default();
+        let postgres = docker.run(Postgres::default());
+        let pool = db::test_pool(postgres.get_host_port_ipv4(5432));
+        let conn = &mut pool.get().unwrap();
+        db::run_migration(conn);
+        let acme = create_company("Acme", conn).unwrap().id();
+        let globex = create_company("Globex", conn).unwrap().id();
+        let root = std::env::temp_dir().join(format!("platform-cv-text-{}", Uuid::new_v4()));
+        let storage = Arc::new(CvStorage::local(
+            &root,
+            SizeLimits {
+                document: 1024 * 1024,
+                image: 1024,
+            },
+        ));
+        let apply = |company: Uuid, lastname: &str, content: &[u8], conn: &mut Connection| {
+            let stored = storage.store(company, content).unwrap();
+            let outcome = storage
+                .scan(&stored.file_name, content, &NoopScanner)
+                .unwrap();
+            let candidate = Candidate::insert(
+                CandidateDTO {
+                    company_id: company,
+                    lastname: lastname.to_string(),
+                    firstname: "Jane".to_string(),
+                    file_name: stored.file_name,
+                    phone: "0612345678".to_string(),
+                    email: format!("{}@doe.test", Uuid::new_v4()),
+                    motivation: "Motivated".to_string(),
+                },
+                conn,
+            )
+            .unwrap();
+            Candidate::record_scan(candidate.id, outcome.status, outcome.signature, conn).unwrap()
+        };
+        let developer = apply(
+            acme,
+            "Doe",
+            &pdf(&["Jane Doe", "Rust developer at Initech"]),
+            conn,
+        );
+        // Left empty by the candidate, prefilled from the CV
+        let manager = apply(
+            globex,
+            "",
+            &docx("Camille ROE\nChef de projet\nRust, PostgreSQL", Some(1)),
+            conn,
+        );
+        apply(acme, "Poe", b"\x89PNG\r\n\x1a\n", conn);
+        // Released then lost, e.g. a bucket emptied by mistake
+        let lost = apply(acme, "Moe", &pdf(&["Lost"]), conn);
+        storage.backend().delete(&lost.file_name).unwrap();
+
+        let skills = Arc::new(SkillDictionary::from_settings(&settings()).unwrap());
+        let report = extract_pending(&pool, storage.clone(), skills.clone(), &settings())
+            .await
+            .unwrap();
+        assert_eq!(
+            report,
+            ExtractionReport {
+                extracted: 2,
+                unsupported: 1,
+                retried: 1,
+                failed: 0,
+                skipped: 0,
+                unrecorded: 0,
+            }
+        );
+        let developer = Candidate::find_by_id(developer.id, conn).unwrap();
+        assert_eq!(developer.cv_text_status, TextStatus::Extracted);
+        assert_eq!(developer.cv_page_count, Some(2));
+        assert!(developer.cv_text.unwrap().contains("Rust developer"));
+        assert_eq!(developer.cv_parsed.unwrap().skills, vec!["Rust"]);
+        let manager = Candidate::find_by_id(manager.id, conn).unwrap();
+        assert_eq!(manager.lastname, "ROE");
+        // Only the empty fields are prefilled
+        assert_eq!(manager.firstname, "Jane");
+        assert_eq!(
+            manager.cv_parsed.unwrap().skills,
+            vec!["Project Management", "Rust", "PostgreSQL"]
+        );
+        let lost = Candidate::find_by_id(lost.id, conn).unwrap();
+        assert_eq!(lost.cv_text_status, TextStatus::Pending);
+        assert_eq!(lost.cv_text_attempts, 1);
+        assert!(lost.cv_text_error.is_some());
+        assert!(lost.cv_text_retry_at.is_some());
+
+        // Retried on the next run, then given up
+        let report = extract_pending(&pool, storage.clone(), skills.clone(), &settings())
+            .await
+            .unwrap();
+        assert_eq!(
+            report,
+            ExtractionReport {
+                failed: 1,
+                ..Default::default()
+            }
+        );
+        let lost = Candidate::find_by_id(lost.id, conn).unwrap();
+        assert_eq!(lost.cv_text_status, TextStatus::Failed);
+        assert_eq!(
+            extract_pending(&pool, storage.clone(), skills.clone(), &settings())
+                .await
+                .unwrap(),
+            ExtractionReport::default()
+        );
+        // Claimed by a single run
+        let late = apply(acme, "Loe", &pdf(&["Late"]), conn);
+        let now = Utc::now().naive_utc();
+        let until = now + chrono::Duration::minutes(10);
+        let claimed = Candidate::claim_pending_text(now, until, 10, conn).unwrap();
+        assert_eq!(claimed.len(), 1);
+        assert_eq!(claimed[0].id, late.id);
+        assert!(Candidate::claim_pending_text(now, until, 10, conn)
+            .unwrap()
+            .is_empty());
+
+        let params = PaginationParams {
+            page: None,
+            per_page: None,
+        };
+        let (_, total) = Candidate::search("rust", None, &params, conn).unwrap();
+        assert_eq!(total, 2);
+        let (matches, total) = Candidate::search("rust", Some(acme), &params, conn).unwrap();
+        assert_eq!(total, 1);
+        assert_eq!(matches[0].id, developer.id);
+        assert_eq!(matches[0].cv_page_count, Some(2));
+        assert!(
+            matches[0].headline.contains("**Rust**"),
+            "{}",
+            matches[0].headline
+        );
+        // French stemming and web search syntax
+        let (matches, _) =
+            Candidate::search("\"chef de projets\" -java", None, &params, conn).unwrap();
+        assert_eq!(matches.len(), 1);
+        assert_eq!(matches[0].id, manager.id);
+        let (matches, _) = Candidate::search("roe", None, &params, conn).unwrap();
+        assert_eq!(matches.len(), 1);
+        assert_eq!(
+            Candidate::search("cobol", None, &params, conn).unwrap().1,
+            0
+        );
+
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[actix_web::test]
+    async fn test_candidate_erased_during_extraction() {
+        let docker = clients::Cli::default();
+        let postgres = docker.run(Postgres::default());
+        let pool = db::test_pool(postgres.get_host_port_ipv4(5432));
+        let conn = &mut pool.get().unwrap();
+        db::run_migration(conn);
+        let acme = create_company("Acme", conn).unwrap().id();
+        let root = std::env::temp_dir().join(format!("platform-cv-erased-{}", Uuid::new_v4()));
+        let storage = CvStorage::local(
+            &root,
+            SizeLimits {
+                document: 1024 * 1024,
+                image: 1024,
+            },
+        );
+        let content = pdf(&["Jane Doe", "jane.doe@example.com", "Rust developer"]);
+        let stored = storage.store(acme, &content).unwrap();
+        let outcome = storage
+            .scan(&stored.file_name, &content, &NoopScanner)
+            .unwrap();
+        // Contact details left empty, they would be prefilled from the CV
+        let applied = Candidate::insert(
+            CandidateDTO {
+                company_id: acme,
+                lastname: String::new(),
+                firstname: String::new(),
+                file_name: stored.file_name,
+                phone: String::new(),
+                email: String::new(),
+                motivation: "Motivated".to_string(),
+            },
+            conn,
+        )
+        .unwrap();
+        Candidate::record_scan(applied.id, outcome.status, outcome.signature, conn).unwrap();
+
+        let now = Utc::now().naive_utc();
+        let claimed = Candidate::claim_pending_text(now, now, 10, conn)
+            .unwrap()
+            .remove(0);
+        let format = CvFormat::from_file_name(&claimed.file_name).unwrap();
+        let extracted =
+            extraction::extract(format, &storage.read(&claimed.file_name).unwrap()).unwrap();
+        let skills = SkillDictionary::from_settings(&settings()).unwrap();
+        // Erased once its CV is read, before the outcome is stored
+        candidate_retention::erase(&storage, claimed.id, conn).unwrap();
+
+        let mut report = ExtractionReport::default();
+        record(
+            &claimed,
+            Ok(extracted),
+            &skills,
+            &settings(),
+            now,
+            &mut report,
+            conn,
+        )
+        .unwrap();
+        assert_eq!(
+            report,
+            ExtractionReport {
+                skipped: 1,
+                ..Default::default()
+            }
+        );
+        let erased = Candidate::find_by_id(claimed.id, conn).unwrap();
+        assert!(erased.anonymised_at.is_some());
+        assert_eq!(erased.cv_text, None);
+        assert!(erased.cv_parsed.is_none());
+        assert_eq!(
+            (erased.firstname.as_str(), erased.lastname.as_str()),
+            ("", "")
+        );
+        assert_eq!((erased.email.as_str(), erased.phone.as_str()), ("", ""));
+        assert!(Skill::find_by_candidate(claimed.id, conn)
+            .unwrap()
+            .is_empty());
+
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+}
@@ -0,0 +1,31 @@
+use std::time::Duration;
+
+use chrono::Utc;
+use tracing::{error, info};
+
+use crate::{
+    config::db::{self, Pool},
+    jobs::{self, JobHandle},
+    models::login_history::LoginHistory,
+};
+
+// Delete the login history entries older than `retention_days`, every `interval`
+pub fn spawn(pool: Pool, retention_days: i64, interval: Duration) -> JobHandle {
+    jobs::spawn_periodic("Login history retention", interval, move || {
+        let pool = pool.clone();
+        async move {
+            let cutoff = Utc::now().naive_utc() - chrono::Duration::days(retention_days);
+            match db::run(&pool, move |conn| {
+                LoginHistory::delete_older_than(cutoff, conn)
+            })
+            .await
+            {
+                Ok(deleted) => info!(
+                    "Login history retention: {} entries older than {} days deleted",
+                    deleted, retention_days
+                ),
+                Err(e) => error!("Login history retention failed: {}", e),
+            }
+        }
+    })
+}
@@ -0,0 +1,86 @@
+use std::{future::Future, pin::pin, time::Duration};
+
+use actix_rt::task::JoinHandle;
+use futures::{
+    channel::oneshot,
+    future::{select, Either},
+};
+use tracing::{info, warn};
+
+pub mod candidate_retention;
+pub mod cv_text_extraction;
+pub mod login_history_retention;
+
+// Background job running every `interval`, stopped after the HTTP server on shutdown
+pub struct JobHandle {
+    name: &'static str,
+    stop: oneshot::Sender<()>,
+    task: JoinHandle<()>,
+}
+
+pub fn spawn_periodic<F, Fut>(name: &'static str, interval: Duration, mut run: F) -> JobHandle
+where
+    F: FnMut() -> Fut + 'static,
+    Fut: Future<Output = ()>,
+{
+    let (stop, mut stopped) = oneshot::channel::<()>();
+    let task = actix_rt::spawn(async move {
+        let mut ticker = actix_rt::time::interval(interval);
+        // A run in progress always completes, the stop request is checked first between two runs
+        while let Either::Right(_) = select(&mut stopped, pin!(ticker.tick())).await {
+            run().await;
+        }
+    });
+    JobHandle { name, stop, task }
+}
+
+impl JobHandle {
+    // Ask the job to stop and wait for its current run, `false` when it outlived `timeout`
+    pub async fn shutdown(self, timeout: Duration) -> bool {
+        let _ = self.stop.send(());
+        match actix_rt::time::timeout(timeout, self.task).await {
+            Ok(_) => {
+                info!("{} stopped", self.name);
+                true
+            }
+            Err(_) => {
+                warn!("{} still running after {:?}, abandoned", self.name, timeout);
+                false
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{cell::Cell, rc::Rc};
+
+    #[actix_web::test]
+    async fn test_shutdown_waits_for_the_current_run() {
+        let runs = Rc::new(Cell::new(0));
+        let counter = runs.clone();
+        let job = spawn_periodic("test job", Duration::from_millis(10), move || {
+            let counter = counter.clone();
+            async move {
+                actix_rt::time::sleep(Duration::from_millis(30)).await;
+                counter.set(counter.get() + 1);
+            }
+        });
+        actix_rt::time::sleep(Duration::from_millis(50)).await;
+        assert!(job.shutdown(Duration::from_secs(1)).await);
+        let completed = runs.get();
+        assert!(completed >= 1);
+        actix_rt::time::sleep(Duration::from_millis(50)).await;
+        assert_eq!(runs.get(), completed);
+    }
+
+    #[actix_web::test]
+    async fn test_shutdown_gives_up_after_the_timeout() {
+        let job = spawn_periodic("slow job", Duration::from_millis(10), || {
+            actix_rt::time::sleep(Duration::from_secs(5))
+        });
+        actix_rt::time::sleep(Duration::from_millis(20)).await;
+        assert!(!job.shutdown(Duration::from_millis(50)).await);
+    }
+}
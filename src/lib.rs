@@ -0,0 +1,13 @@
+pub mod admin;
+pub mod config;
+pub mod constants;
+pub mod controller;
+pub mod error;
+pub mod jobs;
+pub mod mail;
+pub mod models;
+pub mod parsing;
+pub mod schema;
+pub mod storage;
+pub mod templates;
+pub mod utils;
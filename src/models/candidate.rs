@@ -1,13 +1,29 @@
-use diesel::{prelude::*, Identifiable, Insertable, Queryable};
+use chrono::{NaiveDateTime, Utc};
+use diesel::{
+    deserialize::{self, FromSql},
+    dsl::sql,
+    pg::{Pg, PgValue},
+    prelude::*,
+    serialize::{self, IsNull, Output, ToSql},
+    sql_types::{Bool, Float4, Integer, Nullable, Text, Varchar},
+    AsExpression, Connection as _, FromSqlRow, Identifiable, Insertable, Queryable,
+};
 use serde::{Deserialize, Serialize};
+use std::{fmt, io::Write, str::FromStr};
+use utoipa::{IntoParams, ToSchema};
 use uuid::Uuid;
 
 use crate::{
     config::db::Connection,
-    schema::candidate::{self, dsl::*},
+    models::{duplicate::CandidateMerge, pagination::PaginationParams, skill::CandidateSkill},
+    parsing::{self, skills::SkillDictionary, ParsedCv},
+    schema::{
+        candidate::{self, dsl::*},
+        company,
+    },
 };
 
-#[derive(Identifiable, Queryable, Serialize, Selectable, Deserialize)]
+#[derive(Identifiable, Queryable, Insertable, Serialize, Selectable, Deserialize)]
 #[diesel(check_for_backend(diesel::pg::Pg))]
 #[diesel(table_name = candidate)]
 pub struct Candidate {
@@ -19,20 +35,98 @@ pub struct Candidate {
     pub phone: String,
     pub email: String,
     pub motivation: String,
+    // Defaults keep the exports written before the malware scan importable
+    #[serde(default)]
+    pub cv_scan_status: ScanStatus,
+    #[serde(default)]
+    pub cv_scan_signature: Option<String>,
+    #[serde(default)]
+    pub cv_scanned_at: Option<NaiveDateTime>,
+    #[serde(default)]
+    pub cv_text: Option<String>,
+    #[serde(default)]
+    pub cv_page_count: Option<i32>,
+    #[serde(default)]
+    pub cv_text_status: TextStatus,
+    #[serde(default)]
+    pub cv_text_attempts: i32,
+    #[serde(default)]
+    pub cv_text_error: Option<String>,
+    #[serde(default)]
+    pub cv_text_retry_at: Option<NaiveDateTime>,
+    #[serde(default)]
+    pub cv_parsed: Option<ParsedCv>,
+    #[serde(default = "now")]
+    pub applied_at: NaiveDateTime,
+    // Version of the privacy notice accepted with the application
+    #[serde(default)]
+    pub consent_version: Option<String>,
+    #[serde(default)]
+    pub consented_at: Option<NaiveDateTime>,
+    #[serde(default)]
+    pub consent_ip: Option<String>,
+    // Personal data erased, the row is kept for the statistics
+    #[serde(default)]
+    pub anonymised_at: Option<NaiveDateTime>,
+}
+
+fn now() -> NaiveDateTime {
+    Utc::now().naive_utc()
 }
 
-#[derive(Insertable, Queryable, Serialize, Deserialize, AsChangeset)]
+#[derive(Insertable, Queryable, Serialize, Deserialize, AsChangeset, ToSchema)]
 #[diesel(table_name = candidate)]
 pub struct CandidateDTO {
     pub company_id: Uuid,
     pub lastname: String,
     pub firstname: String,
+    // Name returned by `CvStorage::store`, never the name sent by the client
     pub file_name: String,
     pub phone: String,
     pub email: String,
     pub motivation: String,
 }
 
+// Text search configuration of `candidate.search_vector`, see its migration
+const SEARCH_CONFIG: &str = "french";
+// Up to three passages of the motivation or the CV, matched words between `**`
+const SEARCH_HEADLINE_OPTIONS: &str =
+    "StartSel=**, StopSel=**, MaxFragments=3, MaxWords=20, MinWords=8, FragmentDelimiter=\" … \"";
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct CandidateSearch {
+    // Web search syntax: `rust "chef de projet" -stage`
+    pub q: String,
+    // Super admins only, the other administrators always search their company
+    pub company_id: Option<Uuid>,
+    pub page: Option<i64>,
+    pub per_page: Option<i64>,
+}
+
+impl CandidateSearch {
+    pub fn pagination(&self) -> PaginationParams {
+        PaginationParams {
+            page: self.page,
+            per_page: self.per_page,
+        }
+    }
+}
+
+#[derive(Queryable, Serialize, ToSchema)]
+pub struct CandidateMatch {
+    pub id: Uuid,
+    pub company_id: Uuid,
+    pub lastname: String,
+    pub firstname: String,
+    pub email: String,
+    pub cv_scan_status: ScanStatus,
+    pub cv_text_status: TextStatus,
+    pub cv_page_count: Option<i32>,
+    pub rank: f32,
+    pub headline: String,
+}
+
 impl Candidate {
     pub fn find_all(conn: &mut Connection) -> QueryResult<Vec<Candidate>> {
         candidate.load::<Candidate>(conn)
@@ -42,6 +136,11 @@ impl Candidate {
         candidate.find(i).get_result::<Candidate>(conn)
     }
 
+    // Locked until the end of the transaction, merges and erasures take the same lock
+    pub fn lock(i: Uuid, conn: &mut Connection) -> QueryResult<Candidate> {
+        candidate.find(i).for_update().get_result::<Candidate>(conn)
+    }
+
     pub fn find_by_company_id(
         i_company: Uuid,
         conn: &mut Connection,
@@ -51,9 +150,295 @@ impl Candidate {
             .load::<Candidate>(conn)
     }
 
-    pub fn insert(new_candidate: CandidateDTO, conn: &mut Connection) -> QueryResult<usize> {
+    // Applications sent with `address` to any company, the oldest first
+    pub fn find_by_email(address: &str, conn: &mut Connection) -> QueryResult<Vec<Candidate>> {
+        candidate
+            .filter(email.eq(address))
+            .filter(anonymised_at.is_null())
+            .order((applied_at, id))
+            .load::<Candidate>(conn)
+    }
+
+    // Candidates whose CV is still in quarantine, the oldest applications first
+    pub fn find_pending_scan(conn: &mut Connection) -> QueryResult<Vec<Candidate>> {
+        candidate
+            .filter(cv_scan_status.eq(ScanStatus::Pending))
+            .filter(anonymised_at.is_null())
+            .order(id)
+            .load::<Candidate>(conn)
+    }
+
+    // Clean CVs whose text is still to extract and due, the oldest applications first. They are
+    // claimed until `claimed_until`: rows locked by another run are skipped and the ones returned
+    // are not due again before then, the caller extracts them outside of the transaction
+    pub fn claim_pending_text(
+        now: NaiveDateTime,
+        claimed_until: NaiveDateTime,
+        limit: i64,
+        conn: &mut Connection,
+    ) -> QueryResult<Vec<Candidate>> {
+        conn.transaction(|conn| {
+            let ids = candidate
+                .filter(cv_scan_status.eq(ScanStatus::Clean))
+                .filter(cv_text_status.eq(TextStatus::Pending))
+                .filter(cv_text_retry_at.is_null().or(cv_text_retry_at.le(now)))
+                .filter(anonymised_at.is_null())
+                .order(id)
+                .limit(limit)
+                .select(id)
+                .for_update()
+                .skip_locked()
+                .load::<Uuid>(conn)?;
+            diesel::update(candidate.filter(id.eq_any(&ids)))
+                .set(cv_text_retry_at.eq(claimed_until))
+                .get_results::<Candidate>(conn)
+                .map(|mut claimed| {
+                    claimed.sort_by_key(|claimed| claimed.id);
+                    claimed
+                })
+        })
+    }
+
+    // Candidates whose CV text is extracted, the oldest applications first
+    pub fn find_extracted(conn: &mut Connection) -> QueryResult<Vec<Candidate>> {
+        candidate
+            .filter(cv_text_status.eq(TextStatus::Extracted))
+            .filter(anonymised_at.is_null())
+            .order(id)
+            .load::<Candidate>(conn)
+    }
+
+    // Ranked by relevance, the names weigh more than the motivation, which weighs more than the CV
+    pub fn search(
+        query: &str,
+        i_company: Option<Uuid>,
+        params: &PaginationParams,
+        conn: &mut Connection,
+    ) -> QueryResult<(Vec<CandidateMatch>, i64)> {
+        let tsquery = format!("websearch_to_tsquery('{}', ", SEARCH_CONFIG);
+        let matches = || {
+            let mut matches = candidate
+                .filter(
+                    sql::<Bool>(&format!("search_vector @@ {}", tsquery))
+                        .bind::<Text, _>(query)
+                        .sql(")"),
+                )
+                .into_boxed();
+            if let Some(i_company) = i_company {
+                matches = matches.filter(company_id.eq(i_company));
+            }
+            matches
+        };
+        let rank = || {
+            sql::<Float4>(&format!("ts_rank_cd(search_vector, {}", tsquery))
+                .bind::<Text, _>(query)
+                .sql("))")
+        };
+        let headline = sql::<Text>(&format!(
+            "ts_headline('{}', motivation || E'\\n' || coalesce(cv_text, ''), {}",
+            SEARCH_CONFIG, tsquery
+        ))
+        .bind::<Text, _>(query)
+        .sql(&format!("), '{}')", SEARCH_HEADLINE_OPTIONS));
+
+        let total = matches().count().get_result(conn)?;
+        let items = matches()
+            .select((
+                id,
+                company_id,
+                lastname,
+                firstname,
+                email,
+                cv_scan_status,
+                cv_text_status,
+                cv_page_count,
+                rank(),
+                headline,
+            ))
+            .order((rank().desc(), id))
+            .limit(params.per_page())
+            .offset(params.offset())
+            .load::<CandidateMatch>(conn)?;
+        Ok((items, total))
+    }
+
+    pub fn insert(new_candidate: CandidateDTO, conn: &mut Connection) -> QueryResult<Candidate> {
         diesel::insert_into(candidate)
             .values(&new_candidate)
+            .get_result::<Candidate>(conn)
+    }
+
+    pub fn record_consent(
+        i: Uuid,
+        version: &str,
+        ip: Option<String>,
+        conn: &mut Connection,
+    ) -> QueryResult<usize> {
+        diesel::update(candidate.find(i))
+            .set((
+                consent_version.eq(version),
+                consented_at.eq(Utc::now().naive_utc()),
+                consent_ip.eq(ip),
+            ))
+            .execute(conn)
+    }
+
+    // Applications kept longer than the retention of their company, `default_days` when it has none,
+    // the oldest first
+    pub fn find_expired(
+        default_days: i32,
+        limit: i64,
+        conn: &mut Connection,
+    ) -> QueryResult<Vec<Candidate>> {
+        candidate
+            .inner_join(company::table)
+            .filter(anonymised_at.is_null())
+            .filter(
+                sql::<Bool>(
+                    "candidate.applied_at < NOW() - make_interval(days => coalesce(company.retention_days, ",
+                )
+                .bind::<Integer, _>(default_days)
+                .sql("))"),
+            )
+            .order((applied_at, id))
+            .limit(limit)
+            .select(Candidate::as_select())
+            .load::<Candidate>(conn)
+    }
+
+    // Clear every personal field, the CV text and the parsed CV. The company, the dates, the statuses,
+    // the consent version and the skills stay for the statistics; the CV files are deleted by the caller
+    pub fn anonymise(i: Uuid, conn: &mut Connection) -> QueryResult<Candidate> {
+        diesel::update(candidate.find(i))
+            .set((
+                lastname.eq(""),
+                firstname.eq(""),
+                email.eq(""),
+                phone.eq(""),
+                motivation.eq(""),
+                file_name.eq(""),
+                cv_text.eq(None::<String>),
+                cv_text_error.eq(None::<String>),
+                cv_parsed.eq(None::<ParsedCv>),
+                consent_ip.eq(None::<String>),
+                anonymised_at.eq(Utc::now().naive_utc()),
+            ))
+            .get_result::<Candidate>(conn)
+    }
+
+    pub fn record_scan(
+        i: Uuid,
+        status: ScanStatus,
+        signature: Option<String>,
+        conn: &mut Connection,
+    ) -> QueryResult<Candidate> {
+        let scanned_at = match status {
+            ScanStatus::Pending => None,
+            _ => Some(Utc::now().naive_utc()),
+        };
+        diesel::update(candidate.find(i))
+            .set((
+                cv_scan_status.eq(status),
+                cv_scan_signature.eq(signature),
+                cv_scanned_at.eq(scanned_at),
+            ))
+            .get_result::<Candidate>(conn)
+    }
+
+    // The text of the CVs merged into the candidate before the extraction stays after it
+    pub fn record_text(
+        i: Uuid,
+        text: String,
+        page_count: Option<i32>,
+        conn: &mut Connection,
+    ) -> QueryResult<usize> {
+        diesel::update(candidate.find(i))
+            .set((
+                cv_text_status.eq(TextStatus::Extracted),
+                cv_text.eq(sql::<Nullable<Text>>("")
+                    .bind::<Text, _>(text)
+                    .sql(" || coalesce(E'\\n\\n' || cv_text, '')")),
+                cv_page_count.eq(page_count),
+                cv_text_attempts.eq(cv_text_attempts + 1),
+                cv_text_error.eq(None::<String>),
+                cv_text_retry_at.eq(None::<NaiveDateTime>),
+            ))
+            .execute(conn)
+    }
+
+    // Store the parsed CV and fill the names, email and phone the candidate left empty
+    pub fn record_parsed(
+        i: Uuid,
+        parsed: &ParsedCv,
+        conn: &mut Connection,
+    ) -> QueryResult<Candidate> {
+        conn.transaction(|conn| {
+            let current = candidate
+                .find(i)
+                .for_update()
+                .get_result::<Candidate>(conn)?;
+            let prefill = |value: String, found: &Option<String>| match found {
+                Some(found) if value.trim().is_empty() => found.clone(),
+                _ => value,
+            };
+            let contact = &parsed.contact;
+            diesel::update(candidate.find(i))
+                .set((
+                    cv_parsed.eq(Some(parsed)),
+                    firstname.eq(prefill(current.firstname, &contact.firstname)),
+                    lastname.eq(prefill(current.lastname, &contact.lastname)),
+                    email.eq(prefill(current.email, &contact.email)),
+                    phone.eq(prefill(current.phone, &contact.phone)),
+                ))
+                .get_result::<Candidate>(conn)
+        })
+    }
+
+    // Parse the stored text of the CV, the one of the CVs merged into the candidate included. The
+    // skills of a candidate with merges are added to, the ones of the merged CVs are kept
+    pub fn parse_cv(
+        i: Uuid,
+        dictionary: &SkillDictionary,
+        conn: &mut Connection,
+    ) -> QueryResult<Candidate> {
+        conn.transaction(|conn| {
+            let current = candidate.find(i).get_result::<Candidate>(conn)?;
+            let parsed = parsing::parse(current.cv_text.as_deref().unwrap_or_default(), dictionary);
+            let parsed_candidate = Self::record_parsed(i, &parsed, conn)?;
+            if CandidateMerge::find_by_survivor(i, conn)?.is_empty() {
+                CandidateSkill::replace(i, &parsed.skills, dictionary, conn)?;
+            } else {
+                CandidateSkill::add(i, &parsed.skills, dictionary, conn)?;
+            }
+            Ok(parsed_candidate)
+        })
+    }
+
+    // `Pending` with a `retry_at` for a failure that will be retried, `Failed` or `Unsupported` otherwise
+    pub fn record_text_failure(
+        i: Uuid,
+        status: TextStatus,
+        error: String,
+        retry_at: Option<NaiveDateTime>,
+        conn: &mut Connection,
+    ) -> QueryResult<usize> {
+        diesel::update(candidate.find(i))
+            .set((
+                cv_text_status.eq(status),
+                cv_text_attempts.eq(cv_text_attempts + 1),
+                cv_text_error.eq(error),
+                cv_text_retry_at.eq(retry_at),
+            ))
+            .execute(conn)
+    }
+
+    pub fn update(
+        i: Uuid,
+        updated_candidate: CandidateDTO,
+        conn: &mut Connection,
+    ) -> QueryResult<usize> {
+        diesel::update(candidate.find(i))
+            .set(&updated_candidate)
             .execute(conn)
     }
 
@@ -61,3 +446,141 @@ impl Candidate {
         diesel::delete(candidate.find(i)).execute(conn)
     }
 }
+
+// Result of the malware scan of the CV, only `clean` files can be downloaded
+#[derive(
+    Serialize,
+    Deserialize,
+    Debug,
+    Clone,
+    Copy,
+    Default,
+    PartialEq,
+    AsExpression,
+    FromSqlRow,
+    ToSchema,
+)]
+#[serde(rename_all = "lowercase")]
+#[diesel(sql_type = Varchar)]
+pub enum ScanStatus {
+    // In quarantine, not scanned yet or the scanner was unavailable
+    #[default]
+    Pending,
+    Clean,
+    // The file was deleted, the signature is kept on the candidate
+    Infected,
+}
+
+impl FromStr for ScanStatus {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<ScanStatus, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "pending" => Ok(ScanStatus::Pending),
+            "clean" => Ok(ScanStatus::Clean),
+            "infected" => Ok(ScanStatus::Infected),
+            _ => Err(format!("'{}' is not a valid scan status", s)),
+        }
+    }
+}
+
+impl fmt::Display for ScanStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}",
+            match self {
+                ScanStatus::Pending => "pending",
+                ScanStatus::Clean => "clean",
+                ScanStatus::Infected => "infected",
+            }
+        )
+    }
+}
+
+impl ToSql<Varchar, Pg> for ScanStatus {
+    fn to_sql<'b>(&'b self, out: &mut Output<'b, '_, Pg>) -> serialize::Result {
+        match *self {
+            ScanStatus::Pending => out.write_all(b"pending")?,
+            ScanStatus::Clean => out.write_all(b"clean")?,
+            ScanStatus::Infected => out.write_all(b"infected")?,
+        }
+        Ok(IsNull::No)
+    }
+}
+
+impl FromSql<Varchar, Pg> for ScanStatus {
+    fn from_sql(bytes: PgValue) -> deserialize::Result<Self> {
+        match bytes.as_bytes() {
+            b"pending" => Ok(ScanStatus::Pending),
+            b"clean" => Ok(ScanStatus::Clean),
+            b"infected" => Ok(ScanStatus::Infected),
+            _ => Err("Unrecognized enum variant".into()),
+        }
+    }
+}
+
+// Progress of the text extraction of the CV, searched once `extracted`
+#[derive(
+    Serialize,
+    Deserialize,
+    Debug,
+    Clone,
+    Copy,
+    Default,
+    PartialEq,
+    AsExpression,
+    FromSqlRow,
+    ToSchema,
+)]
+#[serde(rename_all = "lowercase")]
+#[diesel(sql_type = Varchar)]
+pub enum TextStatus {
+    // Waiting for a clean scan, the next run of the extraction job or a retry
+    #[default]
+    Pending,
+    Extracted,
+    // Given up after `extraction.max_attempts` failures
+    Failed,
+    // Images and ODT files, nothing to extract
+    Unsupported,
+}
+
+impl fmt::Display for TextStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}",
+            match self {
+                TextStatus::Pending => "pending",
+                TextStatus::Extracted => "extracted",
+                TextStatus::Failed => "failed",
+                TextStatus::Unsupported => "unsupported",
+            }
+        )
+    }
+}
+
+impl ToSql<Varchar, Pg> for TextStatus {
+    fn to_sql<'b>(&'b self, out: &mut Output<'b, '_, Pg>) -> serialize::Result {
+        match *self {
+            TextStatus::Pending => out.write_all(b"pending")?,
+            TextStatus::Extracted => out.write_all(b"extracted")?,
+            TextStatus::Failed => out.write_all(b"failed")?,
+            TextStatus::Unsupported => out.write_all(b"unsupported")?,
+        }
+        Ok(IsNull::No)
+    }
+}
+
+impl FromSql<Varchar, Pg> for TextStatus {
+    fn from_sql(bytes: PgValue) -> deserialize::Result<Self> {
+        match bytes.as_bytes() {
+            b"pending" => Ok(TextStatus::Pending),
+            b"extracted" => Ok(TextStatus::Extracted),
+            b"failed" => Ok(TextStatus::Failed),
+            b"unsupported" => Ok(TextStatus::Unsupported),
+            _ => Err("Unrecognized enum variant".into()),
+        }
+    }
+}
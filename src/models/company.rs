@@ -1,5 +1,6 @@
 use diesel::{prelude::*, Identifiable, Insertable, Queryable};
 use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
 use uuid::Uuid;
 
 use crate::{
@@ -7,15 +8,18 @@ use crate::{
     schema::company::{self, dsl::*},
 };
 
-#[derive(Identifiable, Queryable, Serialize, Selectable, Deserialize)]
+#[derive(Identifiable, Queryable, Insertable, Serialize, Selectable, Deserialize)]
 #[diesel(table_name = company)]
 #[diesel(check_for_backend(diesel::pg::Pg))]
 pub struct Company {
     pub id: Uuid,
     pub name: String,
+    // Days the applications are kept, `gdpr.default_retention_days` when `None`
+    #[serde(default)]
+    pub retention_days: Option<i32>,
 }
 
-#[derive(Insertable, Queryable, Serialize, Deserialize, AsChangeset)]
+#[derive(Insertable, Queryable, Serialize, Deserialize, AsChangeset, ToSchema)]
 #[diesel(table_name = company)]
 pub struct CompanyDTO {
     pub name: String,
@@ -50,6 +54,16 @@ impl Company {
             .execute(conn)
     }
 
+    pub fn set_retention(
+        i: Uuid,
+        days: Option<i32>,
+        conn: &mut Connection,
+    ) -> QueryResult<Company> {
+        diesel::update(company.find(i))
+            .set(retention_days.eq(days))
+            .get_result::<Company>(conn)
+    }
+
     pub fn delete(i: Uuid, conn: &mut Connection) -> QueryResult<usize> {
         diesel::delete(company.find(i)).execute(conn)
     }
@@ -0,0 +1,168 @@
+// Subject access requests: the candidates ask for a copy of their data with the address they applied
+// with, and download it from the link sent to that address
+use chrono::{NaiveDateTime, Utc};
+use data_encoding::{BASE64URL_NOPAD, HEXLOWER};
+use diesel::{prelude::*, sql_types::Text, Connection as _, Insertable, Queryable, Selectable};
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use utoipa::{IntoParams, ToSchema};
+use uuid::Uuid;
+
+use crate::{
+    config::db::Connection,
+    models::{
+        candidate::Candidate,
+        company::Company,
+        duplicate::{CandidateMerge, MergeRecord},
+        skill::Skill,
+    },
+    schema::data_export_requests::{self, dsl::*},
+};
+
+const TOKEN_LENGTH: usize = 32;
+
+#[derive(Queryable, Selectable, Debug)]
+#[diesel(table_name = data_export_requests)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct DataExportRequest {
+    pub id: Uuid,
+    pub email: String,
+    pub token_hash: String,
+    pub created_at: NaiveDateTime,
+    pub expires_at: NaiveDateTime,
+    pub downloaded_at: Option<NaiveDateTime>,
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = data_export_requests)]
+struct DataExportRequestInsertableDTO {
+    email: String,
+    token_hash: String,
+    expires_at: NaiveDateTime,
+}
+
+#[derive(Deserialize, ToSchema)]
+pub struct DataExportDTO {
+    // Address given with the applications
+    pub email: String,
+}
+
+#[derive(Deserialize, IntoParams)]
+pub struct DataExportDownload {
+    // Token of the link emailed to the candidate
+    pub token: String,
+}
+
+// Tokens are random and high entropy, a plain SHA-256 digest is enough to store them
+fn hash_token(token: &str) -> String {
+    HEXLOWER.encode(&Sha256::digest(token.trim().as_bytes()))
+}
+
+impl DataExportRequest {
+    // New download link for `address`, none when one was created after `since`. The requests
+    // for an address are serialized by a lock held until the end of the transaction, the plain
+    // token is only returned here
+    pub fn create(
+        address: &str,
+        since: NaiveDateTime,
+        expiry: NaiveDateTime,
+        conn: &mut Connection,
+    ) -> QueryResult<Option<String>> {
+        conn.transaction(|conn| {
+            diesel::sql_query("SELECT pg_advisory_xact_lock(hashtext($1))")
+                .bind::<Text, _>(address)
+                .execute(conn)?;
+            let recent = data_export_requests
+                .filter(email.eq(address))
+                .filter(created_at.gt(since));
+            if diesel::select(diesel::dsl::exists(recent)).get_result(conn)? {
+                return Ok(None);
+            }
+            let mut token = [0u8; TOKEN_LENGTH];
+            rand::thread_rng().fill_bytes(&mut token);
+            let token = BASE64URL_NOPAD.encode(&token);
+            diesel::insert_into(data_export_requests)
+                .values(DataExportRequestInsertableDTO {
+                    email: address.to_string(),
+                    token_hash: hash_token(&token),
+                    expires_at: expiry,
+                })
+                .execute(conn)?;
+            Ok(Some(token))
+        })
+    }
+
+    // Address of an unexpired link, the download is recorded. The link stays valid until it expires
+    pub fn redeem(token: &str, conn: &mut Connection) -> QueryResult<Option<String>> {
+        let now = Utc::now().naive_utc();
+        diesel::update(
+            data_export_requests
+                .filter(token_hash.eq(hash_token(token)))
+                .filter(expires_at.gt(now)),
+        )
+        .set(downloaded_at.eq(now))
+        .returning(email)
+        .get_result(conn)
+        .optional()
+    }
+
+    pub fn delete_by_email(address: &str, conn: &mut Connection) -> QueryResult<usize> {
+        diesel::delete(data_export_requests.filter(email.eq(address))).execute(conn)
+    }
+
+    pub fn delete_expired(now: NaiveDateTime, conn: &mut Connection) -> QueryResult<usize> {
+        diesel::delete(data_export_requests.filter(expires_at.le(now))).execute(conn)
+    }
+}
+
+// `data.json` of the export archive
+#[derive(Serialize)]
+pub struct SubjectAccessExport {
+    pub exported_at: NaiveDateTime,
+    pub applications: Vec<ExportedApplication>,
+}
+
+// An application with every column of `candidate`, the skills read from its CV and the
+// applications merged into it
+#[derive(Serialize)]
+pub struct ExportedApplication {
+    pub company: String,
+    #[serde(flatten)]
+    pub candidate: Candidate,
+    pub skills: Vec<String>,
+    pub merged_applications: Vec<MergeRecord>,
+    // (merged candidate, stored name) of the CV files of the merged applications
+    #[serde(skip)]
+    pub merged_files: Vec<(Uuid, String)>,
+}
+
+impl SubjectAccessExport {
+    pub fn collect(
+        candidates: Vec<Candidate>,
+        conn: &mut Connection,
+    ) -> QueryResult<SubjectAccessExport> {
+        let mut applications = Vec::with_capacity(candidates.len());
+        for application in candidates {
+            let merges = CandidateMerge::find_by_survivor(application.id, conn)?;
+            let merged_files = merges
+                .iter()
+                .filter_map(|merge| Some((merge.merged_id, merge.file_name()?)))
+                .collect();
+            applications.push(ExportedApplication {
+                company: Company::find_by_id(application.company_id, conn)?.name,
+                skills: Skill::find_by_candidate(application.id, conn)?
+                    .into_iter()
+                    .map(|skill| skill.name)
+                    .collect(),
+                merged_applications: merges.into_iter().map(MergeRecord::from).collect(),
+                merged_files,
+                candidate: application,
+            });
+        }
+        Ok(SubjectAccessExport {
+            exported_at: Utc::now().naive_utc(),
+            applications,
+        })
+    }
+}
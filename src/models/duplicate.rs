@@ -0,0 +1,596 @@
+// Candidates who applied several times, and their merge into one record
+use std::collections::{BTreeSet, HashMap};
+
+use chrono::{NaiveDateTime, Utc};
+use diesel::{prelude::*, Connection as _, Insertable, Queryable};
+use serde::{Deserialize, Serialize};
+use utoipa::{IntoParams, ToSchema};
+use uuid::Uuid;
+
+use crate::{
+    config::db::Connection,
+    error::MergeError,
+    models::{
+        candidate::{Candidate, ScanStatus, TextStatus},
+        pagination::PaginationParams,
+        skill::CandidateSkill,
+    },
+    schema::{candidate, candidate_merges, candidate_skills},
+    utils::contact::{name_key, normalize_email, normalize_phone},
+};
+
+// Jaro-Winkler similarity of the folded names from which two candidates are reported
+const NAME_SIMILARITY_THRESHOLD: f64 = 0.92;
+// Points of the score, a same email is almost certainly the same person
+const EMAIL_POINTS: u32 = 50;
+const PHONE_POINTS: u32 = 30;
+const NAME_POINTS: u32 = 20;
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct DuplicateSearch {
+    // Required for super admins, the other administrators always see their company
+    pub company_id: Option<Uuid>,
+    pub page: Option<i64>,
+    pub per_page: Option<i64>,
+}
+
+impl DuplicateSearch {
+    pub fn pagination(&self) -> PaginationParams {
+        PaginationParams {
+            page: self.page,
+            per_page: self.per_page,
+        }
+    }
+}
+
+#[derive(Queryable, Selectable, Serialize, ToSchema, Debug, Clone)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+#[diesel(table_name = candidate)]
+pub struct DuplicateCandidate {
+    pub id: Uuid,
+    pub lastname: String,
+    pub firstname: String,
+    pub email: String,
+    pub phone: String,
+    pub cv_scan_status: ScanStatus,
+}
+
+#[derive(Serialize, ToSchema, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum DuplicateReason {
+    // Same address once trimmed and lowercased
+    Email,
+    // Same E.164 number
+    Phone,
+    // Similar names, in any order and with or without accents
+    Name,
+}
+
+// Two applications likely sent by the same person
+#[derive(Serialize, ToSchema, Debug)]
+pub struct DuplicatePair {
+    // From 0 to 100: 50 for the same email, 30 for the same phone and up to 20 for the names
+    pub score: u32,
+    pub reasons: Vec<DuplicateReason>,
+    // Jaro-Winkler similarity of the folded names, from 0 to 1
+    pub name_similarity: f64,
+    pub first: DuplicateCandidate,
+    pub second: DuplicateCandidate,
+}
+
+struct Keys {
+    email: Option<String>,
+    phone: Option<String>,
+    name: String,
+}
+
+// Only the candidates sharing an email, a phone or the initial of a name word are compared
+pub fn find_pairs(candidates: &[DuplicateCandidate]) -> Vec<DuplicatePair> {
+    let keys: Vec<Keys> = candidates
+        .iter()
+        .map(|candidate| Keys {
+            email: normalize_email(&candidate.email),
+            phone: normalize_phone(&candidate.phone),
+            name: name_key(&candidate.firstname, &candidate.lastname),
+        })
+        .collect();
+    let mut blocks: HashMap<String, Vec<usize>> = HashMap::new();
+    for (index, keys) in keys.iter().enumerate() {
+        let initials = keys
+            .name
+            .split(' ')
+            .filter_map(|word| word.chars().next())
+            .map(|initial| format!("name:{}", initial));
+        let mut block_keys: Vec<String> = initials
+            .chain(keys.email.iter().map(|email| format!("email:{}", email)))
+            .chain(keys.phone.iter().map(|phone| format!("phone:{}", phone)))
+            .collect();
+        block_keys.dedup();
+        for key in block_keys {
+            blocks.entry(key).or_default().push(index);
+        }
+    }
+    let mut compared = BTreeSet::new();
+    for members in blocks.values() {
+        for (position, &a) in members.iter().enumerate() {
+            for &b in &members[position + 1..] {
+                compared.insert((a.min(b), a.max(b)));
+            }
+        }
+    }
+
+    let mut pairs: Vec<DuplicatePair> = compared
+        .into_iter()
+        .filter_map(|(a, b)| {
+            let (first, second) = (&keys[a], &keys[b]);
+            let mut reasons = Vec::new();
+            let mut score = 0;
+            if first.email.is_some() && first.email == second.email {
+                reasons.push(DuplicateReason::Email);
+                score += EMAIL_POINTS;
+            }
+            if first.phone.is_some() && first.phone == second.phone {
+                reasons.push(DuplicateReason::Phone);
+                score += PHONE_POINTS;
+            }
+            let name_similarity = if first.name.is_empty() || second.name.is_empty() {
+                0.0
+            } else {
+                strsim::jaro_winkler(&first.name, &second.name)
+            };
+            if name_similarity >= NAME_SIMILARITY_THRESHOLD {
+                reasons.push(DuplicateReason::Name);
+                score += (name_similarity * NAME_POINTS as f64).round() as u32;
+            }
+            (!reasons.is_empty()).then(|| DuplicatePair {
+                score,
+                reasons,
+                name_similarity: (name_similarity * 100.0).round() / 100.0,
+                first: candidates[a].clone(),
+                second: candidates[b].clone(),
+            })
+        })
+        .collect();
+    pairs.sort_by(|a, b| {
+        b.score
+            .cmp(&a.score)
+            .then_with(|| a.first.id.cmp(&b.first.id))
+            .then_with(|| a.second.id.cmp(&b.second.id))
+    });
+    pairs
+}
+
+impl DuplicatePair {
+    // Likely duplicates of a company, the most likely first
+    pub fn find_by_company(
+        i_company: Uuid,
+        params: &PaginationParams,
+        conn: &mut Connection,
+    ) -> QueryResult<(Vec<DuplicatePair>, i64)> {
+        let candidates = candidate::table
+            .filter(candidate::company_id.eq(i_company))
+            .filter(candidate::anonymised_at.is_null())
+            .order(candidate::id)
+            .select(DuplicateCandidate::as_select())
+            .load(conn)?;
+        let mut pairs = find_pairs(&candidates);
+        let total = pairs.len() as i64;
+        let offset = (params.offset() as usize).min(pairs.len());
+        pairs.drain(..offset);
+        pairs.truncate(params.per_page() as usize);
+        Ok((pairs, total))
+    }
+}
+
+// Audit trail of a merge, the merged candidate as it was before being deleted
+#[derive(Queryable, Selectable, Insertable, Serialize, Deserialize, Debug)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+#[diesel(table_name = candidate_merges)]
+pub struct CandidateMerge {
+    pub id: Uuid,
+    pub survivor_id: Uuid,
+    pub merged_id: Uuid,
+    pub merged_by: Option<Uuid>,
+    pub merged_at: NaiveDateTime,
+    pub filled_fields: Vec<String>,
+    // Every column of the merged candidate, see `Candidate`
+    pub snapshot: serde_json::Value,
+}
+
+#[derive(Deserialize, ToSchema)]
+pub struct MergeRequest {
+    // Candidate merged into the one of the path, then deleted
+    pub duplicate_id: Uuid,
+}
+
+// Application of the merged candidate, as returned by the API
+#[derive(Serialize, Deserialize, ToSchema, Debug, Default)]
+#[serde(default)]
+pub struct MergedApplication {
+    pub lastname: String,
+    pub firstname: String,
+    pub email: String,
+    pub phone: String,
+    pub motivation: String,
+    // Its CV is downloaded from `/api/admin/candidates/{id}/merges/{merge_id}/cv` once clean
+    pub cv_scan_status: ScanStatus,
+}
+
+#[derive(Serialize, ToSchema, Debug)]
+pub struct MergeRecord {
+    pub id: Uuid,
+    pub survivor_id: Uuid,
+    pub merged_id: Uuid,
+    // `None` once the administrator is deleted
+    pub merged_by: Option<Uuid>,
+    pub merged_at: NaiveDateTime,
+    // Fields of the survivor that were empty, filled from the merged candidate
+    pub filled_fields: Vec<String>,
+    pub application: MergedApplication,
+}
+
+impl From<CandidateMerge> for MergeRecord {
+    fn from(merge: CandidateMerge) -> MergeRecord {
+        MergeRecord {
+            id: merge.id,
+            survivor_id: merge.survivor_id,
+            merged_id: merge.merged_id,
+            merged_by: merge.merged_by,
+            merged_at: merge.merged_at,
+            filled_fields: merge.filled_fields,
+            application: serde_json::from_value(merge.snapshot).unwrap_or_default(),
+        }
+    }
+}
+
+// Text of the CV of the survivor followed by the one of the duplicate
+fn merge_text(survivor: Option<String>, duplicate: Option<&str>) -> Option<String> {
+    match (survivor, duplicate) {
+        (Some(survivor), Some(duplicate)) if !duplicate.trim().is_empty() => {
+            Some(format!("{}\n\n{}", survivor, duplicate))
+        }
+        (None, Some(duplicate)) => Some(duplicate.to_string()),
+        (survivor, _) => survivor,
+    }
+}
+
+impl CandidateMerge {
+    pub fn find_by_id(i: Uuid, conn: &mut Connection) -> QueryResult<CandidateMerge> {
+        candidate_merges::table
+            .find(i)
+            .select(CandidateMerge::as_select())
+            .get_result(conn)
+    }
+
+    // Most recent merge first
+    pub fn find_by_survivor(
+        i_survivor: Uuid,
+        conn: &mut Connection,
+    ) -> QueryResult<Vec<CandidateMerge>> {
+        candidate_merges::table
+            .filter(candidate_merges::survivor_id.eq(i_survivor))
+            .order((candidate_merges::merged_at.desc(), candidate_merges::id))
+            .select(CandidateMerge::as_select())
+            .load(conn)
+    }
+
+    // Merged applications are personal data, erased along with the survivor
+    pub fn delete_by_survivor(i_survivor: Uuid, conn: &mut Connection) -> QueryResult<usize> {
+        diesel::delete(candidate_merges::table.filter(candidate_merges::survivor_id.eq(i_survivor)))
+            .execute(conn)
+    }
+
+    // CV file of the merged application, kept in the storage under its original name
+    pub fn file_name(&self) -> Option<String> {
+        self.snapshot["file_name"].as_str().map(str::to_string)
+    }
+
+    // Move `i_duplicate` onto `i_survivor` and delete it: the empty fields of the survivor are
+    // filled, the text and the skills of both CVs are kept and the earlier merges of the duplicate
+    // follow it. The application is then counted once in the statistics.
+    // The two candidates must belong to the same company, checked by the caller. A duplicate
+    // whose CV is still to scan or extract is refused, it would be deleted before
+    pub fn merge(
+        i_survivor: Uuid,
+        i_duplicate: Uuid,
+        merged_by: Option<Uuid>,
+        conn: &mut Connection,
+    ) -> Result<(Candidate, CandidateMerge), MergeError> {
+        conn.transaction(|conn| {
+            // Locked in the order of their ids, two merges of the same pair cannot deadlock
+            let mut locked = candidate::table
+                .filter(candidate::id.eq_any([i_survivor, i_duplicate]))
+                .order(candidate::id)
+                .for_update()
+                .load::<Candidate>(conn)?;
+            let position = |i: Uuid, locked: &[Candidate]| {
+                locked
+                    .iter()
+                    .position(|candidate| candidate.id == i)
+                    .ok_or(diesel::result::Error::NotFound)
+            };
+            let duplicate = locked.remove(position(i_duplicate, &locked)?);
+            let survivor = locked.remove(position(i_survivor, &locked)?);
+            // Checked under the lock, an erasure in between would be undone by the merge
+            if survivor.anonymised_at.is_some() || duplicate.anonymised_at.is_some() {
+                return Err(MergeError::Anonymised);
+            }
+            // Infected CVs are never extracted, failed and unsupported ones are given up
+            let processing = match duplicate.cv_scan_status {
+                ScanStatus::Pending => true,
+                ScanStatus::Clean => duplicate.cv_text_status == TextStatus::Pending,
+                ScanStatus::Infected => false,
+            };
+            if processing {
+                return Err(MergeError::CvPending);
+            }
+
+            let mut filled_fields = Vec::new();
+            let mut fill = |field: &str, current: String, other: &str| {
+                if current.trim().is_empty() && !other.trim().is_empty() {
+                    filled_fields.push(field.to_string());
+                    other.to_string()
+                } else {
+                    current
+                }
+            };
+            let changes = (
+                candidate::firstname.eq(fill(
+                    "firstname",
+                    survivor.firstname,
+                    &duplicate.firstname,
+                )),
+                candidate::lastname.eq(fill("lastname", survivor.lastname, &duplicate.lastname)),
+                candidate::email.eq(fill("email", survivor.email, &duplicate.email)),
+                candidate::phone.eq(fill("phone", survivor.phone, &duplicate.phone)),
+                candidate::motivation.eq(fill(
+                    "motivation",
+                    survivor.motivation,
+                    &duplicate.motivation,
+                )),
+                // Searched with the candidate, the text of a CV still to extract is added after it
+                candidate::cv_text.eq(merge_text(survivor.cv_text, duplicate.cv_text.as_deref())),
+                candidate::cv_parsed.eq(survivor.cv_parsed.or(duplicate.cv_parsed.clone())),
+            );
+            let survivor = diesel::update(candidate::table.find(i_survivor))
+                .set(changes)
+                .get_result::<Candidate>(conn)?;
+
+            let skills: Vec<CandidateSkill> = candidate_skills::table
+                .filter(candidate_skills::candidate_id.eq(i_duplicate))
+                .select(candidate_skills::skill_id)
+                .load::<Uuid>(conn)?
+                .into_iter()
+                .map(|skill_id| CandidateSkill {
+                    candidate_id: i_survivor,
+                    skill_id,
+                })
+                .collect();
+            diesel::insert_into(candidate_skills::table)
+                .values(&skills)
+                .on_conflict_do_nothing()
+                .execute(conn)?;
+            diesel::update(
+                candidate_merges::table.filter(candidate_merges::survivor_id.eq(i_duplicate)),
+            )
+            .set(candidate_merges::survivor_id.eq(i_survivor))
+            .execute(conn)?;
+
+            let snapshot = serde_json::to_value(&duplicate)
+                .map_err(|e| diesel::result::Error::SerializationError(Box::new(e)))?;
+            let merge = diesel::insert_into(candidate_merges::table)
+                .values(CandidateMerge {
+                    id: Uuid::new_v4(),
+                    survivor_id: i_survivor,
+                    merged_id: i_duplicate,
+                    merged_by,
+                    merged_at: Utc::now().naive_utc(),
+                    filled_fields,
+                    snapshot,
+                })
+                .returning(CandidateMerge::as_returning())
+                .get_result(conn)?;
+            Candidate::delete(i_duplicate, conn)?;
+            Ok((survivor, merge))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        admin::create_company,
+        config::db,
+        models::{candidate::CandidateDTO, skill::Skill},
+        parsing::skills::SkillDictionary,
+    };
+    use testcontainers::{clients, images::postgres::Postgres};
+
+    fn candidate(firstname: &str, lastname: &str, email: &str, phone: &str) -> DuplicateCandidate {
+        DuplicateCandidate {
+            id: Uuid::new_v4(),
+            lastname: lastname.to_string(),
+            firstname: firstname.to_string(),
+            email: email.to_string(),
+            phone: phone.to_string(),
+            cv_scan_status: ScanStatus::Clean,
+        }
+    }
+
+    #[test]
+    fn test_find_pairs() {
+        let jane = candidate("Jane", "Doe", "jane.doe@acme.test", "06 12 34 56 78");
+        let again = candidate("JANE", "DOE", " Jane.Doe@ACME.test", "+33 6 12 34 56 78");
+        let moved = candidate("Jane", "Doe", "jane@elsewhere.test", "+33612345678");
+        let accents = candidate("Hélène", "Dupont", "helene@mail.test", "0700000000");
+        let swapped = candidate("Dupond", "Helene", "h.dupond@mail.test", "");
+        let stranger = candidate("John", "Smith", "john@smith.test", "0699999999");
+        let pairs = find_pairs(&[
+            jane.clone(),
+            again.clone(),
+            moved.clone(),
+            accents.clone(),
+            swapped.clone(),
+            stranger,
+        ]);
+        let found: Vec<(Uuid, Uuid, u32, Vec<DuplicateReason>)> = pairs
+            .iter()
+            .map(|pair| {
+                (
+                    pair.first.id.min(pair.second.id),
+                    pair.first.id.max(pair.second.id),
+                    pair.score,
+                    pair.reasons.clone(),
+                )
+            })
+            .collect();
+        let key = |a: &DuplicateCandidate, b: &DuplicateCandidate| (a.id.min(b.id), a.id.max(b.id));
+        let all = vec![
+            DuplicateReason::Email,
+            DuplicateReason::Phone,
+            DuplicateReason::Name,
+        ];
+        let phone_name = vec![DuplicateReason::Phone, DuplicateReason::Name];
+        assert_eq!(found.len(), 4, "{:?}", pairs);
+        assert_eq!((found[0].0, found[0].1), key(&jane, &again));
+        assert_eq!((found[0].2, &found[0].3), (100, &all));
+        for (a, b) in [(&jane, &moved), (&again, &moved)] {
+            let pair = found
+                .iter()
+                .find(|pair| (pair.0, pair.1) == key(a, b))
+                .unwrap();
+            assert_eq!((pair.2, &pair.3), (50, &phone_name));
+        }
+        let pair = found
+            .iter()
+            .find(|pair| (pair.0, pair.1) == key(&accents, &swapped))
+            .unwrap();
+        assert_eq!(pair.3, vec![DuplicateReason::Name]);
+        assert!(pair.2 < 20);
+    }
+
+    #[actix_web::test]
+    async fn test_merge() {
+        let docker = clients::Cli::default();
+        let postgres = docker.run(Postgres::default());
+        let pool = db::test_pool(postgres.get_host_port_ipv4(5432));
+        let conn = &mut pool.get().unwrap();
+        db::run_migration(conn);
+        let dictionary = SkillDictionary::parse(include_str!("../../config/skills.yaml")).unwrap();
+        let acme = create_company("Acme", conn).unwrap().id();
+
+        let apply = |firstname: &str, phone: &str, motivation: &str, conn: &mut Connection| {
+            Candidate::insert(
+                CandidateDTO {
+                    company_id: acme,
+                    lastname: "Doe".to_string(),
+                    firstname: firstname.to_string(),
+                    file_name: format!("{}/{}.pdf", acme, Uuid::new_v4()),
+                    phone: phone.to_string(),
+                    email: "jane@doe.test".to_string(),
+                    motivation: motivation.to_string(),
+                },
+                conn,
+            )
+            .unwrap()
+        };
+        let survivor = apply("Jane", "", "First application", conn);
+        let duplicate = apply("Jane", "0612345678", "Second application", conn);
+        let older = apply("", "", "", conn);
+        CandidateSkill::replace(survivor.id, &["Rust".to_string()], &dictionary, conn).unwrap();
+        CandidateSkill::replace(
+            duplicate.id,
+            &["Rust".to_string(), "SQL".to_string()],
+            &dictionary,
+            conn,
+        )
+        .unwrap();
+        Candidate::record_scan(duplicate.id, ScanStatus::Clean, None, conn).unwrap();
+        Candidate::record_text(duplicate.id, "PostgreSQL expert".to_string(), Some(1), conn)
+            .unwrap();
+
+        let params = PaginationParams {
+            page: None,
+            per_page: None,
+        };
+        let (pairs, total) = DuplicatePair::find_by_company(acme, &params, conn).unwrap();
+        assert_eq!((pairs.len(), total), (3, 3));
+
+        // Deleted by the merge, a CV still to scan or extract would never be searched
+        assert!(matches!(
+            CandidateMerge::merge(duplicate.id, older.id, None, conn),
+            Err(MergeError::CvPending)
+        ));
+        Candidate::record_scan(older.id, ScanStatus::Clean, None, conn).unwrap();
+        assert!(matches!(
+            CandidateMerge::merge(duplicate.id, older.id, None, conn),
+            Err(MergeError::CvPending)
+        ));
+        Candidate::record_text_failure(
+            older.id,
+            TextStatus::Unsupported,
+            "unsupported".to_string(),
+            None,
+            conn,
+        )
+        .unwrap();
+
+        // The earlier merge into the duplicate follows it onto the survivor
+        let (_, first) = CandidateMerge::merge(duplicate.id, older.id, None, conn).unwrap();
+        assert!(first.filled_fields.is_empty());
+        let (merged, merge) = CandidateMerge::merge(survivor.id, duplicate.id, None, conn).unwrap();
+        assert_eq!(merged.phone, "0612345678");
+        assert_eq!(merged.motivation, "First application");
+        assert_eq!(merge.filled_fields, vec!["phone".to_string()]);
+        assert_eq!(merge.file_name(), Some(duplicate.file_name.clone()));
+        assert!(Candidate::find_by_id(duplicate.id, conn).is_err());
+        // Searched with the survivor, whose own CV is extracted afterwards
+        assert_eq!(merged.cv_text.as_deref(), Some("PostgreSQL expert"));
+        Candidate::record_text(survivor.id, "Rust developer".to_string(), Some(1), conn).unwrap();
+        let parsed = Candidate::parse_cv(survivor.id, &dictionary, conn).unwrap();
+        assert_eq!(
+            parsed.cv_text.as_deref(),
+            Some("Rust developer\n\nPostgreSQL expert")
+        );
+        // Both texts are parsed, the skills of the duplicate are kept
+        assert_eq!(
+            parsed.cv_parsed.unwrap().skills,
+            vec!["Rust".to_string(), "PostgreSQL".to_string()]
+        );
+
+        let names: Vec<String> = Skill::find_by_candidate(survivor.id, conn)
+            .unwrap()
+            .into_iter()
+            .map(|skill| skill.name)
+            .collect();
+        assert_eq!(names.len(), 3);
+        assert!(names.contains(&"SQL".to_string()));
+        let trail: Vec<MergeRecord> = CandidateMerge::find_by_survivor(survivor.id, conn)
+            .unwrap()
+            .into_iter()
+            .map(MergeRecord::from)
+            .collect();
+        assert_eq!(trail.len(), 2);
+        let second = trail
+            .iter()
+            .find(|record| record.merged_id == duplicate.id)
+            .unwrap();
+        assert_eq!(second.application.motivation, "Second application");
+        assert!(trail.iter().any(|record| record.merged_id == older.id));
+
+        assert!(matches!(
+            CandidateMerge::merge(survivor.id, duplicate.id, None, conn),
+            Err(MergeError::CandidateNotFound)
+        ));
+        let erased = apply("Jane", "", "Third application", conn);
+        Candidate::anonymise(erased.id, conn).unwrap();
+        assert!(matches!(
+            CandidateMerge::merge(survivor.id, erased.id, None, conn),
+            Err(MergeError::Anonymised)
+        ));
+        let (pairs, _) = DuplicatePair::find_by_company(acme, &params, conn).unwrap();
+        assert!(pairs.is_empty());
+    }
+}
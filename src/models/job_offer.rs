@@ -1,6 +1,7 @@
 use chrono::{NaiveDateTime, Utc};
-use diesel::{prelude::*, Identifiable, Queryable, Selectable};
+use diesel::{prelude::*, Identifiable, Insertable, Queryable, Selectable};
 use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
 use uuid::Uuid;
 
 use crate::{
@@ -8,7 +9,7 @@ use crate::{
     schema::job_offers::{self, dsl::*},
 };
 
-#[derive(Identifiable, Queryable, Serialize, Selectable, Deserialize)]
+#[derive(Identifiable, Queryable, Insertable, Serialize, Selectable, Deserialize)]
 #[diesel(check_for_backend(diesel::pg::Pg))]
 pub struct JobOffer {
     pub id: Uuid,
@@ -19,13 +20,13 @@ pub struct JobOffer {
     pub location: String,
     pub remote: Option<String>,
     pub employment_type: String,
-    pub salary: f32,
+    pub salary: f64,
     pub created_at: NaiveDateTime,
     #[diesel(sql_type = Nullable<Date>)]
     pub updated_at: Option<NaiveDateTime>,
 }
 
-#[derive(Insertable, Queryable, Serialize, Deserialize, AsChangeset)]
+#[derive(Insertable, Queryable, Serialize, Deserialize, AsChangeset, ToSchema)]
 #[diesel(check_for_backend(diesel::pg::Pg))]
 #[diesel(table_name = job_offers)]
 pub struct JobOfferDTO {
@@ -36,7 +37,7 @@ pub struct JobOfferDTO {
     pub location: String,
     pub remote: Option<String>,
     pub employment_type: String,
-    pub salary: f32,
+    pub salary: f64,
     pub created_at: NaiveDateTime,
     #[diesel(sql_type = Nullable<Date>)]
     pub updated_at: Option<NaiveDateTime>,
@@ -104,3 +105,39 @@ impl JobOffer {
         diesel::delete(job_offers.find(i)).execute(conn)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{admin::create_company, config::db};
+    use testcontainers::{clients, images::postgres::Postgres};
+
+    // `salary FLOAT` is a double precision column, read back to the cent
+    #[actix_web::test]
+    async fn test_salary_round_trip() {
+        let docker = clients::Cli::default();
+        let postgres = docker.run(Postgres::default());
+        let pool = db::test_pool(postgres.get_host_port_ipv4(5432));
+        let conn = &mut pool.get().unwrap();
+        db::run_migration(conn);
+        let acme = create_company("Acme", conn).unwrap().id();
+        JobOffer::insert(
+            JobOfferDTO {
+                company_id: acme,
+                title: "Rust developer".to_string(),
+                description: "Backend".to_string(),
+                requirements: None,
+                location: "Lyon".to_string(),
+                remote: None,
+                employment_type: "CDI".to_string(),
+                salary: 123456.78,
+                created_at: Utc::now().naive_utc(),
+                updated_at: None,
+            },
+            conn,
+        )
+        .unwrap();
+        let offer = JobOffer::find_by_company_id(acme, conn).unwrap().remove(0);
+        assert_eq!(offer.salary, 123456.78);
+    }
+}
@@ -1,39 +1,112 @@
 use chrono::{NaiveDateTime, Utc};
 use diesel::{prelude::*, Associations, Identifiable, Insertable, Queryable};
+use serde::{Deserialize, Serialize};
+use utoipa::{IntoParams, ToSchema};
 use uuid::Uuid;
 
 use crate::{
     config::db::Connection,
-    models::user::User,
-    schema::login_history::{self, dsl::*},
+    models::{pagination::PaginationParams, user::User, user_session::ClientInfo},
+    schema::{
+        login_history::{self, dsl::*},
+        users,
+    },
 };
 
-#[derive(Identifiable, Associations, Queryable)]
+#[derive(Identifiable, Associations, Queryable, Selectable, Serialize, Deserialize, ToSchema)]
 #[diesel(belongs_to(User))]
 #[diesel(table_name = login_history)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
 pub struct LoginHistory {
     pub id: Uuid,
-    pub user_id: Uuid,
+    pub user_id: Option<Uuid>,
     pub login_timestamp: NaiveDateTime,
+    pub success: bool,
+    pub failure_reason: Option<String>,
+    pub ip_address: Option<String>,
+    pub user_agent: Option<String>,
+    pub auth_method: String,
 }
 
 #[derive(Insertable)]
 #[diesel(table_name = login_history)]
 pub struct LoginHistoryInsertableDTO {
-    pub user_id: Uuid,
+    pub user_id: Option<Uuid>,
     pub login_timestamp: NaiveDateTime,
+    pub success: bool,
+    pub failure_reason: Option<String>,
+    pub ip_address: Option<String>,
+    pub user_agent: Option<String>,
+    pub auth_method: String,
+}
+
+// How the user proved its identity
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum AuthMethod {
+    Password,
+    PasswordTotp,
+    PasswordRecoveryCode,
+}
+
+impl AuthMethod {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AuthMethod::Password => "password",
+            AuthMethod::PasswordTotp => "password_totp",
+            AuthMethod::PasswordRecoveryCode => "password_recovery_code",
+        }
+    }
+}
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct LoginHistoryFilter {
+    pub user_id: Option<Uuid>,
+    pub page: Option<i64>,
+    pub per_page: Option<i64>,
+}
+
+impl LoginHistoryFilter {
+    pub fn pagination(&self) -> PaginationParams {
+        PaginationParams {
+            page: self.page,
+            per_page: self.per_page,
+        }
+    }
 }
 
 impl LoginHistory {
-    pub fn create(un: &str, conn: &mut Connection) -> Option<LoginHistoryInsertableDTO> {
-        if let Ok(user) = User::find_user_by_username(un, conn) {
-            let now = Utc::now();
-            Some(LoginHistoryInsertableDTO {
-                user_id: user.id,
-                login_timestamp: now.naive_utc(),
-            })
-        } else {
-            None
+    pub fn success(
+        i_user: Uuid,
+        method: AuthMethod,
+        client: &ClientInfo,
+    ) -> LoginHistoryInsertableDTO {
+        LoginHistoryInsertableDTO {
+            user_id: Some(i_user),
+            login_timestamp: Utc::now().naive_utc(),
+            success: true,
+            failure_reason: None,
+            ip_address: client.ip_address.clone(),
+            user_agent: client.user_agent.clone(),
+            auth_method: method.as_str().to_string(),
+        }
+    }
+
+    // Failed attempt, `i_user` is None when the username or email is unknown
+    pub fn failure(
+        i_user: Option<Uuid>,
+        reason: &str,
+        method: AuthMethod,
+        client: &ClientInfo,
+    ) -> LoginHistoryInsertableDTO {
+        LoginHistoryInsertableDTO {
+            user_id: i_user,
+            login_timestamp: Utc::now().naive_utc(),
+            success: false,
+            failure_reason: Some(reason.to_string()),
+            ip_address: client.ip_address.clone(),
+            user_agent: client.user_agent.clone(),
+            auth_method: method.as_str().to_string(),
         }
     }
 
@@ -45,4 +118,88 @@ impl LoginHistory {
             .values(&insert_record)
             .execute(conn)
     }
+
+    pub fn count_failures_by_ip_since(
+        ip: &str,
+        since: NaiveDateTime,
+        conn: &mut Connection,
+    ) -> QueryResult<i64> {
+        login_history
+            .filter(ip_address.eq(ip))
+            .filter(success.eq(false))
+            .filter(login_timestamp.gt(since))
+            .count()
+            .get_result(conn)
+    }
+
+    pub fn find_by_user_id(
+        i_user: Uuid,
+        params: &PaginationParams,
+        conn: &mut Connection,
+    ) -> QueryResult<(Vec<LoginHistory>, i64)> {
+        let total = login_history
+            .filter(user_id.eq(i_user))
+            .count()
+            .get_result(conn)?;
+        let items = login_history
+            .filter(user_id.eq(i_user))
+            .order(login_timestamp.desc())
+            .limit(params.per_page())
+            .offset(params.offset())
+            .load::<LoginHistory>(conn)?;
+        Ok((items, total))
+    }
+
+    // Entries of the users of a company, optionally narrowed to one user
+    pub fn find_by_company_id(
+        i_company: Uuid,
+        i_user: Option<Uuid>,
+        params: &PaginationParams,
+        conn: &mut Connection,
+    ) -> QueryResult<(Vec<LoginHistory>, i64)> {
+        let query = || {
+            let mut query = login_history
+                .inner_join(users::table)
+                .filter(users::company_id.eq(i_company))
+                .into_boxed();
+            if let Some(i_user) = i_user {
+                query = query.filter(user_id.eq(i_user));
+            }
+            query
+        };
+        let total = query().count().get_result(conn)?;
+        let items = query()
+            .select(LoginHistory::as_select())
+            .order(login_timestamp.desc())
+            .limit(params.per_page())
+            .offset(params.offset())
+            .load::<LoginHistory>(conn)?;
+        Ok((items, total))
+    }
+
+    // Every entry, including failures of unknown users, optionally narrowed to one user
+    pub fn find_all(
+        i_user: Option<Uuid>,
+        params: &PaginationParams,
+        conn: &mut Connection,
+    ) -> QueryResult<(Vec<LoginHistory>, i64)> {
+        let query = || {
+            let mut query = login_history.into_boxed();
+            if let Some(i_user) = i_user {
+                query = query.filter(user_id.eq(i_user));
+            }
+            query
+        };
+        let total = query().count().get_result(conn)?;
+        let items = query()
+            .order(login_timestamp.desc())
+            .limit(params.per_page())
+            .offset(params.offset())
+            .load::<LoginHistory>(conn)?;
+        Ok((items, total))
+    }
+
+    pub fn delete_older_than(cutoff: NaiveDateTime, conn: &mut Connection) -> QueryResult<usize> {
+        diesel::delete(login_history.filter(login_timestamp.lt(cutoff))).execute(conn)
+    }
 }
@@ -1,6 +1,13 @@
 pub mod candidate;
 pub mod company;
+pub mod data_export;
+pub mod duplicate;
 pub mod job_offer;
 pub mod login_history;
+pub mod pagination;
+pub mod recovery_code;
+pub mod response;
+pub mod skill;
 pub mod user;
+pub mod user_session;
 pub mod user_token;
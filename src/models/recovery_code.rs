@@ -0,0 +1,78 @@
+use chrono::{NaiveDateTime, Utc};
+use diesel::{
+    connection::Connection as _, prelude::*, Associations, Identifiable, Insertable, Queryable,
+    Selectable,
+};
+use uuid::Uuid;
+
+use crate::{
+    config::db::Connection,
+    models::user::User,
+    schema::user_recovery_codes::{self, dsl::*},
+    utils::totp,
+};
+
+#[derive(Identifiable, Associations, Queryable, Selectable)]
+#[diesel(belongs_to(User))]
+#[diesel(table_name = user_recovery_codes)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct RecoveryCode {
+    pub id: Uuid,
+    pub user_id: Uuid,
+    pub code_hash: String,
+    pub created_at: NaiveDateTime,
+    pub used_at: Option<NaiveDateTime>,
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = user_recovery_codes)]
+pub struct RecoveryCodeInsertableDTO {
+    pub user_id: Uuid,
+    pub code_hash: String,
+}
+
+impl RecoveryCode {
+    // Replace every recovery code of the user, the plain codes are only returned here
+    pub fn regenerate(i_user: Uuid, conn: &mut Connection) -> QueryResult<Vec<String>> {
+        let codes = totp::generate_recovery_codes();
+        conn.transaction(|conn| {
+            diesel::delete(user_recovery_codes.filter(user_id.eq(i_user))).execute(conn)?;
+            let records: Vec<RecoveryCodeInsertableDTO> = codes
+                .iter()
+                .map(|code| RecoveryCodeInsertableDTO {
+                    user_id: i_user,
+                    code_hash: totp::hash_recovery_code(code),
+                })
+                .collect();
+            diesel::insert_into(user_recovery_codes)
+                .values(&records)
+                .execute(conn)
+        })?;
+        Ok(codes)
+    }
+
+    // Mark the matching unused code as used, returns false when no code matched
+    pub fn consume(i_user: Uuid, code: &str, conn: &mut Connection) -> QueryResult<bool> {
+        diesel::update(
+            user_recovery_codes
+                .filter(user_id.eq(i_user))
+                .filter(code_hash.eq(totp::hash_recovery_code(code)))
+                .filter(used_at.is_null()),
+        )
+        .set(used_at.eq(Utc::now().naive_utc()))
+        .execute(conn)
+        .map(|updated| updated > 0)
+    }
+
+    pub fn count_remaining(i_user: Uuid, conn: &mut Connection) -> QueryResult<i64> {
+        user_recovery_codes
+            .filter(user_id.eq(i_user))
+            .filter(used_at.is_null())
+            .count()
+            .get_result(conn)
+    }
+
+    pub fn delete_all_for_user(i_user: Uuid, conn: &mut Connection) -> QueryResult<usize> {
+        diesel::delete(user_recovery_codes.filter(user_id.eq(i_user))).execute(conn)
+    }
+}
@@ -0,0 +1,17 @@
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct ResponseBody<T> {
+    pub message: String,
+    pub data: T,
+}
+
+impl<T> ResponseBody<T> {
+    pub fn new(message: &str, data: T) -> ResponseBody<T> {
+        ResponseBody {
+            message: message.to_string(),
+            data,
+        }
+    }
+}
@@ -0,0 +1,556 @@
+use std::collections::{HashMap, HashSet};
+
+use diesel::{prelude::*, upsert::excluded, Connection as _, Identifiable, Insertable, Queryable};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use crate::{
+    config::db::Connection,
+    models::{candidate::Candidate, job_offer::JobOffer, pagination::PaginationParams},
+    parsing::skills::{self as dictionary, SkillDictionary},
+    schema::{candidate, candidate_skills, job_offer_skills, job_offers, skills},
+};
+
+// A required skill weighs this many nice-to-have ones in the score
+const REQUIRED_WEIGHT: u32 = 2;
+
+#[derive(
+    Identifiable,
+    Queryable,
+    Selectable,
+    Insertable,
+    Serialize,
+    Deserialize,
+    ToSchema,
+    Debug,
+    Clone,
+    PartialEq,
+)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+#[diesel(table_name = skills)]
+pub struct Skill {
+    pub id: Uuid,
+    // Name of the skills dictionary
+    pub name: String,
+    pub category: Option<String>,
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = skills)]
+struct NewSkill<'a> {
+    name: &'a str,
+    category: Option<&'a str>,
+}
+
+#[derive(Queryable, Selectable, Insertable, Serialize, Deserialize)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+#[diesel(table_name = job_offer_skills)]
+pub struct JobOfferSkill {
+    pub job_offer_id: Uuid,
+    pub skill_id: Uuid,
+    pub required: bool,
+}
+
+#[derive(Queryable, Selectable, Insertable, Serialize, Deserialize)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+#[diesel(table_name = candidate_skills)]
+pub struct CandidateSkill {
+    pub candidate_id: Uuid,
+    pub skill_id: Uuid,
+}
+
+// Skill of a job offer, as sent and returned by the API
+#[derive(Serialize, Deserialize, ToSchema, Debug, Clone, PartialEq)]
+pub struct OfferSkill {
+    // Name or alias of the skills dictionary when sent, `k8s` is returned as `Kubernetes`
+    pub name: String,
+    // Nice to have otherwise
+    pub required: bool,
+}
+
+#[derive(Deserialize, ToSchema)]
+pub struct OfferSkills {
+    pub skills: Vec<OfferSkill>,
+}
+
+// How well a candidate holds the skills of an offer
+#[derive(Serialize, ToSchema, Debug, Default, PartialEq)]
+pub struct SkillMatch {
+    // Share of the offer skills held, from 0 to 100, a required skill weighing twice a nice-to-have one
+    pub score: u32,
+    pub matched_required: Vec<String>,
+    pub missing_required: Vec<String>,
+    pub matched_nice_to_have: Vec<String>,
+    pub missing_nice_to_have: Vec<String>,
+}
+
+impl SkillMatch {
+    fn matched(&self) -> bool {
+        !self.matched_required.is_empty() || !self.matched_nice_to_have.is_empty()
+    }
+}
+
+// `offer` holds the skills of the offer with their `required` flag
+pub fn score(offer: &[(Skill, bool)], held: &HashSet<Uuid>) -> SkillMatch {
+    let mut result = SkillMatch::default();
+    let (mut total, mut matched) = (0, 0);
+    for (skill, required) in offer {
+        let weight = if *required { REQUIRED_WEIGHT } else { 1 };
+        let holds = held.contains(&skill.id);
+        total += weight;
+        if holds {
+            matched += weight;
+        }
+        match (required, holds) {
+            (true, true) => &mut result.matched_required,
+            (true, false) => &mut result.missing_required,
+            (false, true) => &mut result.matched_nice_to_have,
+            (false, false) => &mut result.missing_nice_to_have,
+        }
+        .push(skill.name.clone());
+    }
+    result.score = (matched * 100 + total / 2)
+        .checked_div(total)
+        .unwrap_or_default();
+    result
+}
+
+// Best score first, then the most required skills held
+fn rank<T>(ranked: &mut Vec<(Uuid, T, SkillMatch)>, params: &PaginationParams) -> i64 {
+    ranked.sort_by(|(a_id, _, a), (b_id, _, b)| {
+        b.score
+            .cmp(&a.score)
+            .then_with(|| b.matched_required.len().cmp(&a.matched_required.len()))
+            .then_with(|| a_id.cmp(b_id))
+    });
+    let total = ranked.len() as i64;
+    let offset = (params.offset() as usize).min(ranked.len());
+    ranked.drain(..offset);
+    ranked.truncate(params.per_page() as usize);
+    total
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct RankedCandidate {
+    pub id: Uuid,
+    pub lastname: String,
+    pub firstname: String,
+    pub email: String,
+    pub matching: SkillMatch,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct RankedJobOffer {
+    pub id: Uuid,
+    pub title: String,
+    pub location: String,
+    pub employment_type: String,
+    pub matching: SkillMatch,
+}
+
+impl Skill {
+    // Skills of the dictionary, created or given the category of the dictionary
+    pub fn upsert(found: &[&dictionary::Skill], conn: &mut Connection) -> QueryResult<Vec<Skill>> {
+        if found.is_empty() {
+            return Ok(Vec::new());
+        }
+        let rows: Vec<NewSkill> = found
+            .iter()
+            .map(|skill| NewSkill {
+                name: &skill.name,
+                category: skill.category.as_deref(),
+            })
+            .collect();
+        diesel::insert_into(skills::table)
+            .values(&rows)
+            .on_conflict(skills::name)
+            .do_update()
+            .set(skills::category.eq(excluded(skills::category)))
+            .get_results::<Skill>(conn)
+    }
+
+    // Required skills first, then by name
+    pub fn find_by_job_offer(
+        i_offer: Uuid,
+        conn: &mut Connection,
+    ) -> QueryResult<Vec<(Skill, bool)>> {
+        job_offer_skills::table
+            .inner_join(skills::table)
+            .filter(job_offer_skills::job_offer_id.eq(i_offer))
+            .order((job_offer_skills::required.desc(), skills::name))
+            .select((Skill::as_select(), job_offer_skills::required))
+            .load(conn)
+    }
+
+    pub fn find_by_candidate(i_candidate: Uuid, conn: &mut Connection) -> QueryResult<Vec<Skill>> {
+        candidate_skills::table
+            .inner_join(skills::table)
+            .filter(candidate_skills::candidate_id.eq(i_candidate))
+            .order(skills::name)
+            .select(Skill::as_select())
+            .load(conn)
+    }
+}
+
+impl JobOfferSkill {
+    pub fn replace(
+        i_offer: Uuid,
+        offer_skills: &[(Skill, bool)],
+        conn: &mut Connection,
+    ) -> QueryResult<usize> {
+        let rows: Vec<JobOfferSkill> = offer_skills
+            .iter()
+            .map(|(skill, required)| JobOfferSkill {
+                job_offer_id: i_offer,
+                skill_id: skill.id,
+                required: *required,
+            })
+            .collect();
+        conn.transaction(|conn| {
+            diesel::delete(
+                job_offer_skills::table.filter(job_offer_skills::job_offer_id.eq(i_offer)),
+            )
+            .execute(conn)?;
+            diesel::insert_into(job_offer_skills::table)
+                .values(&rows)
+                .execute(conn)
+        })
+    }
+}
+
+impl CandidateSkill {
+    // Replace the skills of a candidate by the ones parsed from the CV, `names` of the dictionary
+    pub fn replace(
+        i_candidate: Uuid,
+        names: &[String],
+        dictionary: &SkillDictionary,
+        conn: &mut Connection,
+    ) -> QueryResult<Vec<Skill>> {
+        conn.transaction(|conn| {
+            diesel::delete(
+                candidate_skills::table.filter(candidate_skills::candidate_id.eq(i_candidate)),
+            )
+            .execute(conn)?;
+            Self::add(i_candidate, names, dictionary, conn)
+        })
+    }
+
+    // Add the skills parsed from a CV to the ones the candidate already holds
+    pub fn add(
+        i_candidate: Uuid,
+        names: &[String],
+        dictionary: &SkillDictionary,
+        conn: &mut Connection,
+    ) -> QueryResult<Vec<Skill>> {
+        let found: Vec<&dictionary::Skill> = names
+            .iter()
+            .filter_map(|name| dictionary.get(name))
+            .collect();
+        conn.transaction(|conn| {
+            let held = Skill::upsert(&found, conn)?;
+            let rows: Vec<CandidateSkill> = held
+                .iter()
+                .map(|skill| CandidateSkill {
+                    candidate_id: i_candidate,
+                    skill_id: skill.id,
+                })
+                .collect();
+            diesel::insert_into(candidate_skills::table)
+                .values(&rows)
+                .on_conflict_do_nothing()
+                .execute(conn)?;
+            Ok(held)
+        })
+    }
+}
+
+impl RankedCandidate {
+    // Candidates of the company of the offer holding at least one of its skills, best match first
+    pub fn for_job_offer(
+        offer: &JobOffer,
+        params: &PaginationParams,
+        conn: &mut Connection,
+    ) -> QueryResult<(Vec<RankedCandidate>, i64)> {
+        let offer_skills = Skill::find_by_job_offer(offer.id, conn)?;
+        let skill_ids: Vec<Uuid> = offer_skills.iter().map(|(skill, _)| skill.id).collect();
+        let mut held: HashMap<Uuid, HashSet<Uuid>> = HashMap::new();
+        for (i_candidate, i_skill) in candidate_skills::table
+            .inner_join(candidate::table)
+            .filter(candidate::company_id.eq(offer.company_id))
+            // Anonymised candidates keep their skills for the statistics only
+            .filter(candidate::anonymised_at.is_null())
+            .filter(candidate_skills::skill_id.eq_any(&skill_ids))
+            .select((candidate_skills::candidate_id, candidate_skills::skill_id))
+            .load::<(Uuid, Uuid)>(conn)?
+        {
+            held.entry(i_candidate).or_default().insert(i_skill);
+        }
+        let mut ranked: Vec<(Uuid, (), SkillMatch)> = held
+            .iter()
+            .map(|(i_candidate, held)| (*i_candidate, (), score(&offer_skills, held)))
+            .collect();
+        let total = rank(&mut ranked, params);
+
+        let ids: Vec<Uuid> = ranked.iter().map(|(i, _, _)| *i).collect();
+        let mut candidates: HashMap<Uuid, Candidate> = candidate::table
+            .filter(candidate::id.eq_any(&ids))
+            .load::<Candidate>(conn)?
+            .into_iter()
+            .map(|candidate| (candidate.id, candidate))
+            .collect();
+        let items = ranked
+            .into_iter()
+            .filter_map(|(i_candidate, _, matching)| {
+                let candidate = candidates.remove(&i_candidate)?;
+                Some(RankedCandidate {
+                    id: candidate.id,
+                    lastname: candidate.lastname,
+                    firstname: candidate.firstname,
+                    email: candidate.email,
+                    matching,
+                })
+            })
+            .collect();
+        Ok((items, total))
+    }
+}
+
+impl RankedJobOffer {
+    // Offers of the company of the candidate asking for at least one of its skills, best match first
+    pub fn for_candidate(
+        candidate: &Candidate,
+        params: &PaginationParams,
+        conn: &mut Connection,
+    ) -> QueryResult<(Vec<RankedJobOffer>, i64)> {
+        let held: HashSet<Uuid> = Skill::find_by_candidate(candidate.id, conn)?
+            .into_iter()
+            .map(|skill| skill.id)
+            .collect();
+        let mut offers: HashMap<Uuid, (JobOffer, Vec<(Skill, bool)>)> = HashMap::new();
+        for (offer, skill, required) in job_offer_skills::table
+            .inner_join(skills::table)
+            .inner_join(job_offers::table)
+            .filter(job_offers::company_id.eq(candidate.company_id))
+            .order((job_offer_skills::required.desc(), skills::name))
+            .select((
+                JobOffer::as_select(),
+                Skill::as_select(),
+                job_offer_skills::required,
+            ))
+            .load::<(JobOffer, Skill, bool)>(conn)?
+        {
+            offers
+                .entry(offer.id)
+                .or_insert_with(|| (offer, Vec::new()))
+                .1
+                .push((skill, required));
+        }
+        let mut ranked: Vec<(Uuid, JobOffer, SkillMatch)> = offers
+            .into_iter()
+            .map(|(i_offer, (offer, offer_skills))| (i_offer, offer, score(&offer_skills, &held)))
+            .filter(|(_, _, matching)| matching.matched())
+            .collect();
+        let total = rank(&mut ranked, params);
+        let items = ranked
+            .into_iter()
+            .map(|(_, offer, matching)| RankedJobOffer {
+                id: offer.id,
+                title: offer.title,
+                location: offer.location,
+                employment_type: offer.employment_type,
+                matching,
+            })
+            .collect();
+        Ok((items, total))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        admin::create_company,
+        config::db,
+        models::{candidate::CandidateDTO, job_offer::JobOfferDTO},
+    };
+    use chrono::Utc;
+    use testcontainers::{clients, images::postgres::Postgres};
+
+    fn skill(name: &str) -> Skill {
+        Skill {
+            id: Uuid::new_v4(),
+            name: name.to_string(),
+            category: None,
+        }
+    }
+
+    #[test]
+    fn test_score() {
+        let (rust, sql, docker, scrum) =
+            (skill("Rust"), skill("SQL"), skill("Docker"), skill("Scrum"));
+        let offer = vec![
+            (rust.clone(), true),
+            (sql.clone(), true),
+            (docker.clone(), false),
+            (scrum.clone(), false),
+        ];
+        let matching = score(&offer, &HashSet::from([rust.id, docker.id]));
+        assert_eq!(
+            matching,
+            SkillMatch {
+                // (2 + 1) / (2 + 2 + 1 + 1)
+                score: 50,
+                matched_required: vec!["Rust".to_string()],
+                missing_required: vec!["SQL".to_string()],
+                matched_nice_to_have: vec!["Docker".to_string()],
+                missing_nice_to_have: vec!["Scrum".to_string()],
+            }
+        );
+        // Two required skills beat three nice-to-have ones
+        let required = score(&offer, &HashSet::from([rust.id, sql.id]));
+        let nice = score(&offer, &HashSet::from([docker.id, scrum.id]));
+        assert_eq!((required.score, nice.score), (67, 33));
+        assert_eq!(score(&offer, &HashSet::new()).score, 0);
+        assert_eq!(score(&[], &HashSet::from([rust.id])), SkillMatch::default());
+    }
+
+    #[actix_web::test]
+    async fn test_rankings() {
+        let docker = clients::Cli::default();
+        let postgres = docker.run(Postgres::default());
+        let pool = db::test_pool(postgres.get_host_port_ipv4(5432));
+        let conn = &mut pool.get().unwrap();
+        db::run_migration(conn);
+        let dictionary = SkillDictionary::parse(include_str!("../../config/skills.yaml")).unwrap();
+        let acme = create_company("Acme", conn).unwrap().id();
+        let globex = create_company("Globex", conn).unwrap().id();
+
+        let apply = |company: Uuid, lastname: &str, names: &[&str], conn: &mut Connection| {
+            let candidate = Candidate::insert(
+                CandidateDTO {
+                    company_id: company,
+                    lastname: lastname.to_string(),
+                    firstname: "Jane".to_string(),
+                    file_name: format!("{}.pdf", Uuid::new_v4()),
+                    phone: "0612345678".to_string(),
+                    email: format!("{}@doe.test", lastname.to_lowercase()),
+                    motivation: "Motivated".to_string(),
+                },
+                conn,
+            )
+            .unwrap();
+            let names: Vec<String> = names.iter().map(|name| name.to_string()).collect();
+            CandidateSkill::replace(candidate.id, &names, &dictionary, conn).unwrap();
+            candidate
+        };
+        let publish =
+            |company: Uuid, title: &str, wanted: &[(&str, bool)], conn: &mut Connection| {
+                JobOffer::insert(
+                    JobOfferDTO {
+                        company_id: company,
+                        title: title.to_string(),
+                        description: "Description".to_string(),
+                        requirements: None,
+                        location: "Lyon".to_string(),
+                        remote: None,
+                        employment_type: "CDI".to_string(),
+                        salary: 45000.0,
+                        created_at: Utc::now().naive_utc(),
+                        updated_at: None,
+                    },
+                    conn,
+                )
+                .unwrap();
+                let offer = JobOffer::find_by_company_id(company, conn)
+                    .unwrap()
+                    .into_iter()
+                    .find(|offer| offer.title == title)
+                    .unwrap();
+                let found: Vec<&dictionary::Skill> = wanted
+                    .iter()
+                    .map(|(name, _)| dictionary.get(name).unwrap())
+                    .collect();
+                let upserted = Skill::upsert(&found, conn).unwrap();
+                let offer_skills: Vec<(Skill, bool)> = wanted
+                    .iter()
+                    .map(|(name, required)| {
+                        let name = &dictionary.get(name).unwrap().name;
+                        let skill = upserted.iter().find(|skill| &skill.name == name).unwrap();
+                        (skill.clone(), *required)
+                    })
+                    .collect();
+                JobOfferSkill::replace(offer.id, &offer_skills, conn).unwrap();
+                offer
+            };
+
+        let backend = publish(
+            acme,
+            "Backend developer",
+            &[("rust", true), ("postgres", true), ("k8s", false)],
+            conn,
+        );
+        let designer = publish(acme, "Designer", &[("Figma", true)], conn);
+        publish(globex, "Rust developer", &[("Rust", true)], conn);
+
+        let expert = apply(acme, "Expert", &["Rust", "PostgreSQL", "Kubernetes"], conn);
+        let junior = apply(acme, "Junior", &["Rust", "Kubernetes", "unknown"], conn);
+        apply(acme, "Ops", &["Kubernetes"], conn);
+        apply(acme, "Stranger", &["Figma"], conn);
+        // Another company
+        apply(globex, "Rival", &["Rust", "PostgreSQL"], conn);
+        assert_eq!(
+            Skill::find_by_candidate(junior.id, conn)
+                .unwrap()
+                .iter()
+                .map(|skill| (skill.name.as_str(), skill.category.as_deref()))
+                .collect::<Vec<_>>(),
+            vec![
+                ("Kubernetes", Some("devops")),
+                ("Rust", Some("programming"))
+            ]
+        );
+
+        let params = PaginationParams {
+            page: None,
+            per_page: None,
+        };
+        let (ranked, total) = RankedCandidate::for_job_offer(&backend, &params, conn).unwrap();
+        assert_eq!(total, 3);
+        assert_eq!(
+            ranked
+                .iter()
+                .map(|candidate| (candidate.lastname.as_str(), candidate.matching.score))
+                .collect::<Vec<_>>(),
+            vec![("Expert", 100), ("Junior", 60), ("Ops", 20)]
+        );
+        assert_eq!(ranked[1].matching.missing_required, vec!["PostgreSQL"]);
+        let (second, total) = RankedCandidate::for_job_offer(
+            &backend,
+            &PaginationParams {
+                page: Some(2),
+                per_page: Some(1),
+            },
+            conn,
+        )
+        .unwrap();
+        assert_eq!((second.len(), total), (1, 3));
+        assert_eq!(second[0].id, junior.id);
+
+        let (offers, total) = RankedJobOffer::for_candidate(&expert, &params, conn).unwrap();
+        assert_eq!(total, 1);
+        assert_eq!(offers[0].id, backend.id);
+        assert_eq!(
+            offers[0].matching.matched_required,
+            vec!["PostgreSQL", "Rust"]
+        );
+        assert_eq!(offers[0].matching.matched_nice_to_have, vec!["Kubernetes"]);
+
+        // Offer skills replaced, the links of the candidates stay
+        JobOfferSkill::replace(designer.id, &[], conn).unwrap();
+        assert!(Skill::find_by_job_offer(designer.id, conn)
+            .unwrap()
+            .is_empty());
+        assert_eq!(Skill::find_by_candidate(expert.id, conn).unwrap().len(), 3);
+    }
+}
@@ -1,4 +1,5 @@
 use bcrypt::{hash, verify, DEFAULT_COST};
+use chrono::{Duration, NaiveDateTime, Utc};
 use diesel::{
     deserialize::{self, FromSql},
     pg::{Pg, PgValue},
@@ -6,17 +7,25 @@ use diesel::{
     result::Error as DieselError,
     serialize::{self, IsNull, Output, ToSql},
     sql_types::Varchar,
-    AsExpression, FromSqlRow, Identifiable, Insertable, Queryable,
+    AsExpression, Connection as _, FromSqlRow, Identifiable, Insertable, Queryable,
 };
 use serde::{Deserialize, Serialize};
 use std::{fmt, io::Write, str::FromStr};
+use utoipa::ToSchema;
 use uuid::Uuid;
 
 use crate::{
     config::db::Connection,
     constants,
-    models::{login_history::LoginHistory, user_token::UserToken},
+    error::{AppError, AuthError, FieldError},
+    models::{
+        login_history::{AuthMethod, LoginHistory},
+        recovery_code::RecoveryCode,
+        user_session::{ClientInfo, UserSession},
+        user_token::UserToken,
+    },
     schema::users::{self, dsl::*},
+    utils::{metrics::METRICS, totp, validation::Validate},
 };
 
 #[derive(Identifiable, Queryable, Serialize, Selectable, Deserialize)]
@@ -28,10 +37,16 @@ pub struct User {
     pub email: String,
     pub password: Option<String>,
     pub role: RoleType,
-    pub login_session: Option<String>,
+    #[serde(skip_serializing)]
+    pub totp_secret: Option<String>,
+    pub totp_enabled: bool,
+    #[serde(skip_serializing)]
+    pub totp_last_step: Option<i64>,
+    pub failed_login_attempts: i32,
+    pub locked_until: Option<NaiveDateTime>,
 }
 
-#[derive(Insertable, Queryable, Serialize, Deserialize, AsChangeset)]
+#[derive(Insertable, Queryable, Serialize, Deserialize, AsChangeset, ToSchema)]
 #[diesel(table_name = users)]
 pub struct UserDTO {
     pub username: String,
@@ -39,138 +54,347 @@ pub struct UserDTO {
     pub email: String,
     pub password: Option<String>,
     pub role: RoleType,
-    pub login_session: Option<String>,
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, ToSchema)]
 pub struct LoginDTO {
     pub username_or_email: String,
     pub password: String,
+    // One-time code of the authenticator app or a recovery code, second login step
+    #[serde(default)]
+    pub totp_code: Option<String>,
 }
 
-#[derive(Insertable, Serialize, Deserialize)]
-#[diesel(table_name = users)]
+#[derive(Serialize, Deserialize)]
 pub struct LoginInfoDTO {
     pub username: String,
     pub login_session: String,
 }
 
 impl User {
-    pub fn signup(new_user: UserDTO, conn: &mut Connection) -> Result<String, String> {
-        match Self::find_user_by_username(&new_user.username, conn) {
-            Ok(_) => Err(format!(
-                "User '{}' is already registered",
-                &new_user.username
-            )),
-            Err(_) => {
-                if let Some(password_clone) = new_user.password.clone() {
-                    match hash(password_clone, DEFAULT_COST) {
-                        Ok(password_hash) => {
-                            let new_user = UserDTO {
-                                password: Some(password_hash),
-                                ..new_user
-                            };
-                            match Self::insert(new_user, conn) {
-                                Ok(_) => Ok(constants::MESSAGE_SIGNUP_SUCCESS.to_string()),
-                                Err(e) => Err(format!("Failed to insert user: {}", e)),
-                            }
-                        }
-                        Err(e) => Err(format!("Failed to hash password: {}", e)),
-                    }
-                } else {
-                    Err("Password is required".to_string())
-                }
-            }
+    pub fn signup(new_user: UserDTO, conn: &mut Connection) -> Result<String, AppError> {
+        let new_user = new_user.validate().map_err(AppError::validation)?;
+        if Self::find_user_by_username(&new_user.username, conn)
+            .optional()?
+            .is_some()
+        {
+            return Err(AppError::Conflict {
+                error_message: constants::MESSAGE_USER_ALREADY_REGISTERED.to_string(),
+            });
         }
+        let password_clone = new_user.password.clone().ok_or_else(|| {
+            AppError::validation(vec![FieldError {
+                field: "password".to_string(),
+                message: constants::MESSAGE_PASSWORD_REQUIRED.to_string(),
+            }])
+        })?;
+        let password_hash =
+            Self::hash_password(&password_clone).map_err(|e| AppError::InternalServerError {
+                error_message: format!("Failed to hash password: {}", e),
+            })?;
+        Self::insert(
+            UserDTO {
+                password: Some(password_hash),
+                ..new_user
+            },
+            conn,
+        )?;
+        Ok(constants::MESSAGE_SIGNUP_SUCCESS.to_string())
     }
 
-    pub fn login(login: LoginDTO, conn: &mut Connection) -> Option<LoginInfoDTO> {
-        if let Ok(user_to_verify) = users
+    pub fn hash_password(plain: &str) -> Result<String, bcrypt::BcryptError> {
+        hash(plain, DEFAULT_COST)
+    }
+
+    // `session_max_age` is the lifetime of the token issued for the session, in seconds
+    pub fn login(
+        login: LoginDTO,
+        client: &ClientInfo,
+        session_max_age: i64,
+        conn: &mut Connection,
+    ) -> Result<LoginInfoDTO, AuthError> {
+        Self::check_login_allowed(&login.username_or_email, client, conn)?;
+
+        // Emails are stored lowercased, usernames as typed
+        let user_to_verify = match users
             .filter(username.eq(&login.username_or_email))
-            .or_filter(email.eq(&login.username_or_email))
+            .or_filter(email.eq(login.username_or_email.trim().to_lowercase()))
             .get_result::<User>(conn)
         {
-            if !user_to_verify.password.clone()?.is_empty()
-                && verify(&login.password, &user_to_verify.password?.to_string()).unwrap()
-            {
-                if let Some(login_history) = LoginHistory::create(&user_to_verify.username, conn) {
-                    if LoginHistory::save_login_history(login_history, conn).is_err() {
-                        return None;
-                    }
-                    let login_session_str = User::generate_login_session();
-                    if User::update_login_session_to_db(
-                        &user_to_verify.username,
-                        &login_session_str,
+            Ok(user) => user,
+            Err(DieselError::NotFound) => {
+                let _ = LoginHistory::save_login_history(
+                    LoginHistory::failure(
+                        None,
+                        constants::LOGIN_FAILURE_UNKNOWN_USER,
+                        AuthMethod::Password,
+                        client,
+                    ),
+                    conn,
+                );
+                METRICS.record_login(None, false);
+                return Err(AuthError::UnknownUser);
+            }
+            Err(e) => return Err(e.into()),
+        };
+
+        let password_hash = match user_to_verify.password.as_deref() {
+            Some(password_hash) if !password_hash.is_empty() => password_hash,
+            _ => {
+                User::register_failed_login(
+                    &user_to_verify,
+                    constants::LOGIN_FAILURE_PASSWORD_NOT_SET,
+                    AuthMethod::Password,
+                    client,
+                    conn,
+                );
+                return Err(AuthError::PasswordNotSet);
+            }
+        };
+        // A malformed stored hash never authenticates
+        if !verify(&login.password, password_hash).unwrap_or(false) {
+            User::register_failed_login(
+                &user_to_verify,
+                constants::LOGIN_FAILURE_INVALID_PASSWORD,
+                AuthMethod::Password,
+                client,
+                conn,
+            );
+            return Err(AuthError::InvalidPassword);
+        }
+
+        let mut method = AuthMethod::Password;
+        if user_to_verify.totp_enabled {
+            let code = login
+                .totp_code
+                .as_deref()
+                .map(str::trim)
+                .filter(|code| !code.is_empty())
+                .ok_or(AuthError::TwoFactorRequired)?;
+            method = match User::verify_second_factor(
+                &user_to_verify,
+                code,
+                Utc::now().timestamp(),
+                conn,
+            ) {
+                Some(second_factor) => second_factor,
+                None => {
+                    User::register_failed_login(
+                        &user_to_verify,
+                        constants::LOGIN_FAILURE_INVALID_TWO_FACTOR_CODE,
+                        AuthMethod::PasswordTotp,
+                        client,
                         conn,
-                    ) {
-                        return Some(LoginInfoDTO {
-                            username: user_to_verify.username,
-                            login_session: login_session_str,
-                        });
-                    }
+                    );
+                    return Err(AuthError::InvalidTwoFactorCode);
                 }
-            } else {
-                return Some(LoginInfoDTO {
-                    username: user_to_verify.username,
-                    login_session: String::new(),
-                });
-            }
+            };
         }
 
-        None
+        LoginHistory::save_login_history(
+            LoginHistory::success(user_to_verify.id, method, client),
+            conn,
+        )?;
+        METRICS.record_login(user_to_verify.company_id, true);
+        if user_to_verify.failed_login_attempts > 0 {
+            User::unlock(user_to_verify.id, conn)?;
+        }
+        let login_session_str = User::generate_login_session();
+        UserSession::create(
+            user_to_verify.id,
+            &login_session_str,
+            client,
+            session_max_age,
+            conn,
+        )?;
+        Ok(LoginInfoDTO {
+            username: user_to_verify.username,
+            login_session: login_session_str,
+        })
     }
 
-    pub fn logout(user_id: Uuid, conn: &mut Connection) {
-        if let Ok(user) = users.find(user_id).get_result::<User>(conn) {
-            Self::update_login_session_to_db(&user.username, "", conn);
+    // Refuse the attempt while the account is locked or the client IP sent too many failures
+    pub fn check_login_allowed(
+        username_or_email: &str,
+        client: &ClientInfo,
+        conn: &mut Connection,
+    ) -> Result<(), AuthError> {
+        let now = Utc::now().naive_utc();
+        if let Some(ip) = client.ip_address.as_deref() {
+            let since = now - Duration::seconds(constants::LOGIN_IP_WINDOW_SECONDS);
+            let failures = LoginHistory::count_failures_by_ip_since(ip, since, conn)?;
+            if failures >= constants::LOGIN_MAX_FAILED_ATTEMPTS_PER_IP {
+                return Err(AuthError::TooManyAttempts);
+            }
         }
+        if let Some(Some(until)) = users
+            .filter(username.eq(username_or_email))
+            .or_filter(email.eq(username_or_email.trim().to_lowercase()))
+            .select(locked_until)
+            .first::<Option<NaiveDateTime>>(conn)
+            .optional()?
+        {
+            if until > now {
+                return Err(AuthError::AccountLocked { until });
+            }
+        }
+        Ok(())
     }
 
-    pub fn update_login_session_to_db(
-        un: &str,
-        login_session_str: &str,
+    // Count the failure, lock the account once the threshold is reached and log the attempt
+    pub fn register_failed_login(
+        user: &User,
+        reason: &str,
+        method: AuthMethod,
+        client: &ClientInfo,
         conn: &mut Connection,
-    ) -> bool {
-        if let Ok(user) = User::find_user_by_username(un, conn) {
-            diesel::update(users.find(user.id))
-                .set(login_session.eq(login_session_str))
-                .execute(conn)
-                .is_ok()
-        } else {
-            false
+    ) {
+        if let Ok(attempts) = diesel::update(users.find(user.id))
+            .set(failed_login_attempts.eq(failed_login_attempts + 1))
+            .returning(failed_login_attempts)
+            .get_result::<i32>(conn)
+        {
+            if let Some(duration) = Self::lockout_duration(attempts) {
+                let _ = diesel::update(users.find(user.id))
+                    .set(locked_until.eq(Utc::now().naive_utc() + duration))
+                    .execute(conn);
+            }
+        }
+        let _ = LoginHistory::save_login_history(
+            LoginHistory::failure(Some(user.id), reason, method, client),
+            conn,
+        );
+        METRICS.record_login(user.company_id, false);
+    }
+
+    // Exponential backoff: base duration doubled for each failure past the threshold, capped
+    pub fn lockout_duration(attempts: i32) -> Option<Duration> {
+        if attempts < constants::LOGIN_MAX_FAILED_ATTEMPTS {
+            return None;
         }
+        let exponent = (attempts - constants::LOGIN_MAX_FAILED_ATTEMPTS).min(16) as u32;
+        let seconds = constants::LOGIN_LOCKOUT_BASE_SECONDS
+            .saturating_mul(2i64.pow(exponent))
+            .min(constants::LOGIN_LOCKOUT_MAX_SECONDS);
+        Some(Duration::seconds(seconds))
+    }
+
+    pub fn unlock(i: Uuid, conn: &mut Connection) -> QueryResult<usize> {
+        diesel::update(users.find(i))
+            .set((
+                failed_login_attempts.eq(0),
+                locked_until.eq(None::<NaiveDateTime>),
+            ))
+            .execute(conn)
+    }
+
+    pub fn logout(user_id: Uuid, login_session_str: &str, conn: &mut Connection) {
+        let _ = UserSession::revoke_by_token(user_id, login_session_str, conn);
+    }
+
+    pub fn find_session_by_token(
+        user_token: &UserToken,
+        conn: &mut Connection,
+    ) -> QueryResult<(User, UserSession)> {
+        let user = User::find_user_by_username(&user_token.user, conn)?;
+        let session = UserSession::find_active_by_token(user.id, &user_token.login_session, conn)?;
+        Ok((user, session))
     }
 
     pub fn is_valid_login_session(user_token: &UserToken, conn: &mut Connection) -> bool {
-        users
-            .filter(username.eq(&user_token.user))
-            .filter(login_session.eq(&user_token.login_session))
-            .get_result::<User>(conn)
-            .is_ok()
+        match Self::find_session_by_token(user_token, conn) {
+            Ok((_, session)) => UserSession::touch(session.id, conn).is_ok(),
+            Err(_) => false,
+        }
     }
 
     pub fn find_login_info_by_token(
         user_token: &UserToken,
         conn: &mut Connection,
-    ) -> Result<LoginInfoDTO, String> {
-        let user_result = users
-            .filter(username.eq(&user_token.user))
-            .filter(login_session.eq(&user_token.login_session))
-            .first::<User>(conn);
-
-        match user_result {
-            Ok(user) => {
-                let login_session_data = user.login_session.ok_or("Login session is missing")?;
-                Ok(LoginInfoDTO {
-                    username: user.username,
-                    login_session: login_session_data,
-                })
+    ) -> Result<LoginInfoDTO, AppError> {
+        let (user, session) = Self::find_session_by_token(user_token, conn)
+            .map_err(AppError::not_found(constants::MESSAGE_USER_NOT_FOUND))?;
+        Ok(LoginInfoDTO {
+            username: user.username,
+            login_session: session.session_token,
+        })
+    }
+
+    // Store a new pending TOTP secret, 2FA is only enabled once a code is confirmed
+    pub fn begin_totp_enrolment(i: Uuid, conn: &mut Connection) -> QueryResult<String> {
+        let secret = totp::generate_secret();
+        diesel::update(users.find(i))
+            .set((
+                totp_secret.eq(&secret),
+                totp_enabled.eq(false),
+                totp_last_step.eq(None::<i64>),
+            ))
+            .execute(conn)?;
+        Ok(secret)
+    }
+
+    // Enable 2FA when `code` matches the pending secret and return fresh recovery codes
+    pub fn confirm_totp_enrolment(
+        user: &User,
+        code: &str,
+        now: i64,
+        conn: &mut Connection,
+    ) -> QueryResult<Option<Vec<String>>> {
+        let Some(secret) = user.totp_secret.as_deref() else {
+            return Ok(None);
+        };
+        let Some(step) = totp::verify_code(secret, code, now, user.totp_last_step) else {
+            return Ok(None);
+        };
+        conn.transaction(|conn| {
+            diesel::update(users.find(user.id))
+                .set((totp_enabled.eq(true), totp_last_step.eq(step)))
+                .execute(conn)?;
+            RecoveryCode::regenerate(user.id, conn).map(Some)
+        })
+    }
+
+    // Check a TOTP code, falling back to a one-time recovery code, and return the method used
+    pub fn verify_second_factor(
+        user: &User,
+        code: &str,
+        now: i64,
+        conn: &mut Connection,
+    ) -> Option<AuthMethod> {
+        if let Some(secret) = user.totp_secret.as_deref() {
+            if let Some(step) = totp::verify_code(secret, code, now, user.totp_last_step) {
+                // A concurrent login with the same code has recorded its step first
+                return diesel::update(
+                    users
+                        .find(user.id)
+                        .filter(totp_last_step.is_null().or(totp_last_step.lt(step))),
+                )
+                .set(totp_last_step.eq(step))
+                .execute(conn)
+                .ok()
+                .filter(|updated| *updated == 1)
+                .map(|_| AuthMethod::PasswordTotp);
             }
-            Err(DieselError::NotFound) => Err("User not found".to_string()),
-            Err(e) => Err(format!("Database error: {}", e)),
+        }
+        match RecoveryCode::consume(user.id, code, conn) {
+            Ok(true) => Some(AuthMethod::PasswordRecoveryCode),
+            _ => None,
         }
     }
 
+    pub fn disable_totp(i: Uuid, conn: &mut Connection) -> QueryResult<usize> {
+        conn.transaction(|conn| {
+            RecoveryCode::delete_all_for_user(i, conn)?;
+            diesel::update(users.find(i))
+                .set((
+                    totp_secret.eq(None::<String>),
+                    totp_enabled.eq(false),
+                    totp_last_step.eq(None::<i64>),
+                ))
+                .execute(conn)
+        })
+    }
+
     pub fn generate_login_session() -> String {
         Uuid::new_v4().to_string()
     }
@@ -187,12 +411,13 @@ impl User {
         users.filter(username.eq(un)).get_result::<User>(conn)
     }
 
-    pub fn get_superadmin_user(conn: &mut Connection) -> bool {
+    pub fn get_superadmin_user(conn: &mut Connection) -> QueryResult<bool> {
         users
             .filter(username.eq(RoleType::SuperAdmin))
             .select(username)
             .first::<String>(conn)
-            .is_ok()
+            .optional()
+            .map(|superadmin| superadmin.is_some())
     }
 
     pub fn insert(new_user: UserDTO, conn: &mut Connection) -> QueryResult<usize> {
@@ -218,7 +443,7 @@ impl User {
     }
 }
 
-#[derive(Serialize, Deserialize, Debug, AsExpression, FromSqlRow)]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, AsExpression, FromSqlRow, ToSchema)]
 #[serde(rename_all = "lowercase")]
 #[diesel(sql_type = Varchar)]
 pub enum RoleType {
@@ -227,6 +452,13 @@ pub enum RoleType {
     User,
 }
 
+impl RoleType {
+    // Admins see every CV of their company, they can not rely on a password only
+    pub fn requires_two_factor(&self) -> bool {
+        matches!(self, RoleType::SuperAdmin | RoleType::Admin)
+    }
+}
+
 impl FromStr for RoleType {
     type Err = String;
 
@@ -275,3 +507,218 @@ impl FromSql<Varchar, Pg> for RoleType {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::db::{self, Pool};
+    use testcontainers::{clients, images::postgres::Postgres, Container};
+
+    const PASSWORD: &str = "correct horse battery staple";
+    const SESSION_MAX_AGE: i64 = 60 * 60;
+
+    fn init_pool(docker: &clients::Cli) -> (Container<'_, Postgres>, Pool) {
+        //CODE OK FOR testcontainers 0.14.0 not 0.20
+        let postgres = docker.run(Postgres::default());
+        let pool = db::test_pool(postgres.get_host_port_ipv4(5432));
+        db::run_migration(&mut pool.get().unwrap());
+        (postgres, pool)
+    }
+
+    fn insert_user(name: &str, password_data: Option<&str>, conn: &mut Connection) -> User {
+        User::insert(
+            UserDTO {
+                username: name.to_string(),
+                company_id: None,
+                email: format!("{}@platform-cv.test", name),
+                password: password_data.map(|p| hash(p, 4).unwrap()),
+                role: RoleType::User,
+            },
+            conn,
+        )
+        .unwrap();
+        User::find_user_by_username(name, conn).unwrap()
+    }
+
+    fn login_dto(username_or_email: &str, password_data: &str) -> LoginDTO {
+        LoginDTO {
+            username_or_email: username_or_email.to_string(),
+            password: password_data.to_string(),
+            totp_code: None,
+        }
+    }
+
+    fn user_token(login_info: &LoginInfoDTO) -> UserToken {
+        let now = Utc::now().timestamp();
+        UserToken {
+            iat: now,
+            exp: now + 60,
+            user: login_info.username.clone(),
+            login_session: login_info.login_session.clone(),
+        }
+    }
+
+    #[test]
+    fn test_login_ok_opens_a_session() {
+        let docker = clients::Cli::default();
+        let (_postgres, pool) = init_pool(&docker);
+        let conn = &mut pool.get().unwrap();
+        insert_user("jdoe", Some(PASSWORD), conn);
+
+        let login_info = User::login(
+            login_dto("jdoe@platform-cv.test", PASSWORD),
+            &ClientInfo::default(),
+            SESSION_MAX_AGE,
+            conn,
+        )
+        .unwrap();
+
+        assert_eq!(login_info.username, "jdoe");
+        assert!(!login_info.login_session.is_empty());
+        assert!(User::is_valid_login_session(&user_token(&login_info), conn));
+    }
+
+    #[test]
+    fn test_login_wrong_password() {
+        let docker = clients::Cli::default();
+        let (_postgres, pool) = init_pool(&docker);
+        let conn = &mut pool.get().unwrap();
+        let user = insert_user("jdoe", Some(PASSWORD), conn);
+
+        let result = User::login(
+            login_dto("jdoe", "wrong password"),
+            &ClientInfo::default(),
+            SESSION_MAX_AGE,
+            conn,
+        );
+
+        assert!(matches!(result, Err(AuthError::InvalidPassword)));
+        assert!(UserSession::find_active_by_user_id(user.id, conn)
+            .unwrap()
+            .is_empty());
+        assert_eq!(
+            User::find_by_id(user.id, conn)
+                .unwrap()
+                .failed_login_attempts,
+            1
+        );
+        // The session of a refused login can never be forged from an empty value
+        let forged = UserToken {
+            iat: 0,
+            exp: i64::MAX,
+            user: "jdoe".to_string(),
+            login_session: String::new(),
+        };
+        assert!(!User::is_valid_login_session(&forged, conn));
+    }
+
+    #[test]
+    fn test_login_unknown_user() {
+        let docker = clients::Cli::default();
+        let (_postgres, pool) = init_pool(&docker);
+        let conn = &mut pool.get().unwrap();
+
+        let result = User::login(
+            login_dto("nobody", PASSWORD),
+            &ClientInfo::default(),
+            SESSION_MAX_AGE,
+            conn,
+        );
+
+        assert!(matches!(result, Err(AuthError::UnknownUser)));
+    }
+
+    #[test]
+    fn test_login_null_password() {
+        let docker = clients::Cli::default();
+        let (_postgres, pool) = init_pool(&docker);
+        let conn = &mut pool.get().unwrap();
+        let user = insert_user("nopassword", None, conn);
+
+        for attempt in ["", PASSWORD] {
+            let result = User::login(
+                login_dto("nopassword", attempt),
+                &ClientInfo::default(),
+                SESSION_MAX_AGE,
+                conn,
+            );
+            assert!(matches!(result, Err(AuthError::PasswordNotSet)));
+        }
+        assert!(UserSession::find_active_by_user_id(user.id, conn)
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn test_logout_then_reuse_session() {
+        let docker = clients::Cli::default();
+        let (_postgres, pool) = init_pool(&docker);
+        let conn = &mut pool.get().unwrap();
+        let user = insert_user("jdoe", Some(PASSWORD), conn);
+
+        let login_info = User::login(
+            login_dto("jdoe", PASSWORD),
+            &ClientInfo::default(),
+            SESSION_MAX_AGE,
+            conn,
+        )
+        .unwrap();
+        let token = user_token(&login_info);
+        assert!(User::is_valid_login_session(&token, conn));
+
+        User::logout(user.id, &login_info.login_session, conn);
+
+        assert!(!User::is_valid_login_session(&token, conn));
+        assert!(User::find_login_info_by_token(&token, conn).is_err());
+        let emptied = UserToken {
+            login_session: String::new(),
+            ..token
+        };
+        assert!(!User::is_valid_login_session(&emptied, conn));
+    }
+
+    #[test]
+    fn test_lockout_duration_backoff() {
+        let threshold = constants::LOGIN_MAX_FAILED_ATTEMPTS;
+        assert!(User::lockout_duration(threshold - 1).is_none());
+        assert_eq!(
+            User::lockout_duration(threshold),
+            Some(Duration::seconds(constants::LOGIN_LOCKOUT_BASE_SECONDS))
+        );
+        assert_eq!(
+            User::lockout_duration(threshold + 2),
+            Some(Duration::seconds(constants::LOGIN_LOCKOUT_BASE_SECONDS * 4))
+        );
+        assert_eq!(
+            User::lockout_duration(threshold + 100),
+            Some(Duration::seconds(constants::LOGIN_LOCKOUT_MAX_SECONDS))
+        );
+    }
+
+    #[test]
+    fn test_totp_code_accepted_once() {
+        let docker = clients::Cli::default();
+        let (_postgres, pool) = init_pool(&docker);
+        let conn = &mut pool.get().unwrap();
+        let user = insert_user("jdoe", Some(PASSWORD), conn);
+        let secret = User::begin_totp_enrolment(user.id, conn).unwrap();
+        let now = Utc::now().timestamp();
+        let key = data_encoding::BASE32_NOPAD
+            .decode(secret.as_bytes())
+            .unwrap();
+        let code = |step: i64| totp::hotp(&key, step as u64, totp::TOTP_DIGITS);
+
+        let user = User::find_by_id(user.id, conn).unwrap();
+        User::confirm_totp_enrolment(&user, &code(totp::time_step(now) - 1), now, conn)
+            .unwrap()
+            .unwrap();
+        // Both logins read the account before either recorded the step of the code
+        let user = User::find_by_id(user.id, conn).unwrap();
+        let current = code(totp::time_step(now));
+        assert_eq!(
+            User::verify_second_factor(&user, &current, now, conn),
+            Some(AuthMethod::PasswordTotp)
+        );
+        assert_eq!(User::verify_second_factor(&user, &current, now, conn), None);
+    }
+}
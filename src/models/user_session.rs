@@ -0,0 +1,172 @@
+use chrono::{Duration, NaiveDateTime, Utc};
+use diesel::{prelude::*, Associations, Identifiable, Insertable, Queryable, Selectable};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use crate::{
+    config::db::Connection,
+    models::user::User,
+    schema::user_sessions::{self, dsl::*},
+};
+
+#[derive(Identifiable, Associations, Queryable, Selectable, Serialize, Deserialize)]
+#[diesel(belongs_to(User))]
+#[diesel(table_name = user_sessions)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct UserSession {
+    pub id: Uuid,
+    pub user_id: Uuid,
+    #[serde(skip_serializing)]
+    pub session_token: String,
+    pub user_agent: Option<String>,
+    pub ip_address: Option<String>,
+    pub created_at: NaiveDateTime,
+    pub last_seen_at: NaiveDateTime,
+    pub expires_at: NaiveDateTime,
+    pub revoked_at: Option<NaiveDateTime>,
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = user_sessions)]
+pub struct UserSessionInsertableDTO {
+    pub user_id: Uuid,
+    pub session_token: String,
+    pub user_agent: Option<String>,
+    pub ip_address: Option<String>,
+    pub created_at: NaiveDateTime,
+    pub last_seen_at: NaiveDateTime,
+    pub expires_at: NaiveDateTime,
+}
+
+// Device information of the client opening a session
+#[derive(Clone, Default)]
+pub struct ClientInfo {
+    pub user_agent: Option<String>,
+    pub ip_address: Option<String>,
+}
+
+// Session as listed to its owner, `current` flags the session of the calling token
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct UserSessionDTO {
+    pub id: Uuid,
+    pub user_agent: Option<String>,
+    pub ip_address: Option<String>,
+    pub created_at: NaiveDateTime,
+    pub last_seen_at: NaiveDateTime,
+    pub expires_at: NaiveDateTime,
+    pub current: bool,
+}
+
+impl UserSession {
+    pub fn create(
+        i_user: Uuid,
+        token: &str,
+        client: &ClientInfo,
+        max_age: i64,
+        conn: &mut Connection,
+    ) -> QueryResult<UserSession> {
+        let now = Utc::now().naive_utc();
+        let new_session = UserSessionInsertableDTO {
+            user_id: i_user,
+            session_token: token.to_string(),
+            user_agent: client.user_agent.clone(),
+            ip_address: client.ip_address.clone(),
+            created_at: now,
+            last_seen_at: now,
+            expires_at: now + Duration::seconds(max_age),
+        };
+        diesel::insert_into(user_sessions)
+            .values(&new_session)
+            .get_result::<UserSession>(conn)
+    }
+
+    pub fn find_active_by_token(
+        i_user: Uuid,
+        token: &str,
+        conn: &mut Connection,
+    ) -> QueryResult<UserSession> {
+        // A logged out or failed login never carries a usable empty session
+        if token.is_empty() {
+            return Err(diesel::result::Error::NotFound);
+        }
+        user_sessions
+            .filter(user_id.eq(i_user))
+            .filter(session_token.eq(token))
+            .filter(revoked_at.is_null())
+            .filter(expires_at.gt(Utc::now().naive_utc()))
+            .get_result::<UserSession>(conn)
+    }
+
+    pub fn find_active_by_user_id(
+        i_user: Uuid,
+        conn: &mut Connection,
+    ) -> QueryResult<Vec<UserSession>> {
+        user_sessions
+            .filter(user_id.eq(i_user))
+            .filter(revoked_at.is_null())
+            .filter(expires_at.gt(Utc::now().naive_utc()))
+            .order(last_seen_at.desc())
+            .load::<UserSession>(conn)
+    }
+
+    pub fn touch(i: Uuid, conn: &mut Connection) -> QueryResult<usize> {
+        diesel::update(user_sessions.find(i))
+            .set(last_seen_at.eq(Utc::now().naive_utc()))
+            .execute(conn)
+    }
+
+    pub fn revoke(i: Uuid, i_user: Uuid, conn: &mut Connection) -> QueryResult<usize> {
+        diesel::update(
+            user_sessions
+                .find(i)
+                .filter(user_id.eq(i_user))
+                .filter(revoked_at.is_null()),
+        )
+        .set(revoked_at.eq(Utc::now().naive_utc()))
+        .execute(conn)
+    }
+
+    pub fn revoke_by_token(i_user: Uuid, token: &str, conn: &mut Connection) -> QueryResult<usize> {
+        diesel::update(
+            user_sessions
+                .filter(user_id.eq(i_user))
+                .filter(session_token.eq(token))
+                .filter(revoked_at.is_null()),
+        )
+        .set(revoked_at.eq(Utc::now().naive_utc()))
+        .execute(conn)
+    }
+
+    pub fn revoke_all_for_user(i_user: Uuid, conn: &mut Connection) -> QueryResult<usize> {
+        diesel::update(
+            user_sessions
+                .filter(user_id.eq(i_user))
+                .filter(revoked_at.is_null()),
+        )
+        .set(revoked_at.eq(Utc::now().naive_utc()))
+        .execute(conn)
+    }
+
+    // Expired and revoked sessions can never authenticate again
+    pub fn purge_expired(now: NaiveDateTime, conn: &mut Connection) -> QueryResult<usize> {
+        diesel::delete(
+            user_sessions
+                .filter(expires_at.le(now))
+                .or_filter(revoked_at.is_not_null()),
+        )
+        .execute(conn)
+    }
+
+    pub fn to_dto(&self, current_token: &str) -> UserSessionDTO {
+        UserSessionDTO {
+            id: self.id,
+            user_agent: self.user_agent.clone(),
+            ip_address: self.ip_address.clone(),
+            created_at: self.created_at,
+            last_seen_at: self.last_seen_at,
+            expires_at: self.expires_at,
+            current: self.session_token == current_token,
+        }
+    }
+}
@@ -1,15 +1,9 @@
-#[warn(deprecated)]
-use std::env;
-
 use chrono::Utc;
-use jsonwebtoken::{EncodingKey, Header};
-use log::debug;
 use serde::{Deserialize, Serialize};
+use tracing::debug;
+use utoipa::ToSchema;
 
-use crate::models::user::LoginInfoDTO;
-
-pub static KEY: [u8; 16] = *include_bytes!("../secret.key");
-static ONE_WEEK: i64 = 60 * 60 * 24 * 7; // in seconds
+use crate::{config::signing_keys::SigningKeys, models::user::LoginInfoDTO};
 
 #[derive(Serialize, Deserialize)]
 pub struct UserToken {
@@ -22,20 +16,14 @@ pub struct UserToken {
     pub login_session: String,
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, ToSchema)]
 pub struct TokenBodyResponse {
     pub token: String,
     pub token_type: String,
 }
 
 impl UserToken {
-    pub fn generate_token(login: &LoginInfoDTO) -> String {
-        dotenv::dotenv().expect("Failed to read .env file");
-        let max_age: i64 = match env::var("MAX_AGE") {
-            Ok(val) => val.parse::<i64>().unwrap_or(ONE_WEEK),
-            Err(_) => ONE_WEEK,
-        };
-
+    pub fn generate_token(login: &LoginInfoDTO, max_age: i64, keys: &SigningKeys) -> String {
         debug!("Token Max Age: {}", max_age);
 
         let now = Utc::now()
@@ -49,11 +37,6 @@ impl UserToken {
             login_session: login.login_session.clone(),
         };
 
-        jsonwebtoken::encode(
-            &Header::default(),
-            &payload,
-            &EncodingKey::from_secret(&KEY),
-        )
-        .unwrap()
+        keys.encode(&payload).unwrap()
     }
 }
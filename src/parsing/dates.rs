@@ -0,0 +1,178 @@
+// Dates of the experience and education entries, in French or English: "2019 - 2022",
+// "janv. 2020 – aujourd'hui", "03/2018 à 06/2020", "depuis 2021", "Sept 2015 to Present"
+use std::sync::LazyLock;
+
+use regex::{Captures, Regex};
+
+const MONTHS: &str = r"janvier|janv|january|jan|f[ée]vrier|f[ée]v|february|feb|mars|march|mar|avril|avr|april|apr|mai|may|juin|june|jun|juillet|juil|july|jul|ao[uû]t|august|aug|septembre|sept|september|sep|octobre|october|oct|novembre|november|nov|d[ée]cembre|d[ée]c|december|dec";
+const ONGOING: &str =
+    r"pr[ée]sent|aujourd['’]hui|ce jour|today|now|en cours|current|actuel(?:lement)?";
+
+static DATE: LazyLock<String> = LazyLock::new(|| {
+    format!(
+        r"(?:(?:{})\.?\s+|(?:0?[1-9]|1[0-2])\s*[/.]\s*)?(?:19|20)\d{{2}}",
+        MONTHS
+    )
+});
+static RANGE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(&format!(
+        r"(?i)(?:\b(?:de|du|from)\s+)?\b(?P<start>{date})\s*(?:-|–|—|\bà\b|\bau\b|\bto\b|\buntil\b|\bjusqu['’](?:à|en)\b)\s*(?:(?P<end>{date})|(?P<ongoing>{ongoing}))\b",
+        date = *DATE,
+        ongoing = ONGOING
+    ))
+    .unwrap()
+});
+static SINCE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(&format!(
+        r"(?i)\b(?:depuis|since)\s+(?:(?:le|la|l['’])\s*)?(?P<start>{})\b",
+        *DATE
+    ))
+    .unwrap()
+});
+static YEAR: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(&format!(r"(?i)\b(?P<end>{})\b", *DATE)).unwrap());
+static PARTS: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(&format!(
+        r"(?i)^(?:(?P<month>{})\.?\s+|(?P<number>\d{{1,2}})\s*[/.]\s*)?(?P<year>\d{{4}})$",
+        MONTHS
+    ))
+    .unwrap()
+});
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Period {
+    // `YYYY-MM`, or `YYYY` when the month is not given
+    pub start: Option<String>,
+    pub end: Option<String>,
+    pub current: bool,
+}
+
+fn month_number(name: &str) -> Option<u32> {
+    let name = name.to_lowercase();
+    let prefixes = [
+        ("jan", 1),
+        ("f", 2),
+        ("mar", 3),
+        ("av", 4),
+        ("ap", 4),
+        ("mai", 5),
+        ("may", 5),
+        ("juin", 6),
+        ("jun", 6),
+        ("juil", 7),
+        ("jul", 7),
+        ("ao", 8),
+        ("au", 8),
+        ("sep", 9),
+        ("oct", 10),
+        ("nov", 11),
+        ("d", 12),
+    ];
+    prefixes
+        .iter()
+        .find(|(prefix, _)| name.starts_with(prefix))
+        .map(|&(_, number)| number)
+}
+
+// "Sept. 2015" => "2015-09", "03/2018" => "2018-03", "2019" => "2019"
+pub fn normalize_date(date: &str) -> Option<String> {
+    let parts = PARTS.captures(date.trim())?;
+    let year = parts.name("year")?.as_str();
+    let month = match (parts.name("month"), parts.name("number")) {
+        (Some(name), _) => month_number(name.as_str()),
+        (None, Some(number)) => number
+            .as_str()
+            .parse()
+            .ok()
+            .filter(|n| (1..=12).contains(n)),
+        (None, None) => None,
+    };
+    Some(match month {
+        Some(month) => format!("{}-{:02}", year, month),
+        None => year.to_string(),
+    })
+}
+
+fn period(captures: &Captures) -> Period {
+    let date = |name: &str| captures.name(name).and_then(|m| normalize_date(m.as_str()));
+    Period {
+        start: date("start"),
+        end: date("end"),
+        current: captures.name("ongoing").is_some() || captures.name("end").is_none(),
+    }
+}
+
+// First period of `line`, with the text before and after it. A lone date is an end date:
+// "Master, 2012"
+pub fn find_period(line: &str) -> Option<(Period, &str, &str)> {
+    let (captures, single) = match RANGE.captures(line).or_else(|| SINCE.captures(line)) {
+        Some(captures) => (captures, false),
+        None => (YEAR.captures(line)?, true),
+    };
+    let found = captures.get(0)?;
+    let mut period = period(&captures);
+    if single {
+        period.current = false;
+    }
+    Some((period, &line[..found.start()], &line[found.end()..]))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn dates(line: &str) -> Period {
+        find_period(line).unwrap().0
+    }
+
+    fn expected(start: Option<&str>, end: Option<&str>, current: bool) -> Period {
+        Period {
+            start: start.map(str::to_string),
+            end: end.map(str::to_string),
+            current,
+        }
+    }
+
+    #[test]
+    fn test_find_period() {
+        assert_eq!(
+            dates("2019 - 2022 : Développeur"),
+            expected(Some("2019"), Some("2022"), false)
+        );
+        assert_eq!(
+            dates("Janv. 2020 – aujourd'hui"),
+            expected(Some("2020-01"), None, true)
+        );
+        assert_eq!(
+            dates("de 03/2018 à 06/2020"),
+            expected(Some("2018-03"), Some("2020-06"), false)
+        );
+        assert_eq!(
+            dates("Depuis septembre 2021"),
+            expected(Some("2021-09"), None, true)
+        );
+        assert_eq!(
+            dates("Sept 2015 to Present"),
+            expected(Some("2015-09"), None, true)
+        );
+        assert_eq!(
+            dates("Février 2010 - Déc. 2011"),
+            expected(Some("2010-02"), Some("2011-12"), false)
+        );
+        assert_eq!(dates("Master, 2012"), expected(None, Some("2012"), false));
+        assert!(find_period("Salaire 45000 euros, 12 ans").is_none());
+        assert!(find_period("Junior 2019").unwrap().0.start.is_none());
+
+        let (_, before, after) = find_period("Développeur Rust (2019-2022) Acme").unwrap();
+        assert_eq!((before, after), ("Développeur Rust (", ") Acme"));
+    }
+
+    #[test]
+    fn test_normalize_date() {
+        assert_eq!(normalize_date("Août 2019").as_deref(), Some("2019-08"));
+        assert_eq!(normalize_date("apr. 2019").as_deref(), Some("2019-04"));
+        assert_eq!(normalize_date("13/2019").as_deref(), Some("2019"));
+        assert_eq!(normalize_date("2019").as_deref(), Some("2019"));
+        assert_eq!(normalize_date("soon"), None);
+    }
+}
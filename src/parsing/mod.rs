@@ -0,0 +1,557 @@
+// Heuristic parsing of the CV texts: contact details, skills, experience and education
+pub mod dates;
+pub mod skills;
+
+use std::{io::Write, sync::LazyLock};
+
+use diesel::{
+    deserialize::{self, FromSql},
+    pg::{Pg, PgValue},
+    serialize::{self, IsNull, Output, ToSql},
+    sql_types::Jsonb,
+    AsExpression, FromSqlRow,
+};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+use self::{dates::find_period, skills::SkillDictionary};
+use crate::utils::contact::{fold, normalize_phone};
+
+// Lines read for the name of the candidate before the first section
+const NAME_LINES: usize = 5;
+// Longer lines are descriptions, not titles, companies or degrees
+const MAX_ENTRY_LENGTH: usize = 100;
+
+static EMAIL: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"[A-Za-z0-9._%+-]+@[A-Za-z0-9.-]+\.[A-Za-z]{2,}").unwrap());
+// French numbers (`06 12 34 56 78`, `+33 (0)6 12 34 56 78`, `0033 6...`) then other international ones
+static FRENCH_PHONE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"(?:(?:\+|\b00)33[ .-]?(?:\(0\)[ .-]?)?|\b0)[1-9](?:[ .-]?\d{2}){4}\b").unwrap()
+});
+static INTERNATIONAL_PHONE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"\+[1-9][\d .()-]{6,18}\d\b").unwrap());
+static LINK: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(
+        r"(?i)\b(?:https?://[^\s<>]+|(?:www\.)?(?:linkedin\.com|github\.com|gitlab\.com)/[^\s<>]+)",
+    )
+    .unwrap()
+});
+static LABELLED_NAME: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"(?i)^(?P<label>pr[ée]nom|nom|first\s*name|last\s*name)\s*:\s*(?P<value>.+)$")
+        .unwrap()
+});
+
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Contact {
+    pub firstname: Option<String>,
+    pub lastname: Option<String>,
+    pub email: Option<String>,
+    // E.164, `+33612345678`
+    pub phone: Option<String>,
+    pub links: Vec<String>,
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Experience {
+    pub title: String,
+    pub company: Option<String>,
+    // `YYYY-MM`, or `YYYY` when the month is not given
+    pub start: Option<String>,
+    pub end: Option<String>,
+    pub current: bool,
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Education {
+    pub degree: String,
+    pub institution: Option<String>,
+    pub start: Option<String>,
+    pub end: Option<String>,
+}
+
+// Stored as JSON in `candidate.cv_parsed`
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize, AsExpression, FromSqlRow)]
+#[diesel(sql_type = Jsonb)]
+pub struct ParsedCv {
+    pub contact: Contact,
+    // Names of the dictionary, in the order of their first mention
+    pub skills: Vec<String>,
+    pub experience: Vec<Experience>,
+    pub education: Vec<Education>,
+}
+
+impl ToSql<Jsonb, Pg> for ParsedCv {
+    fn to_sql<'b>(&'b self, out: &mut Output<'b, '_, Pg>) -> serialize::Result {
+        // Version of the binary format of JSONB, followed by the JSON text
+        out.write_all(&[1])?;
+        serde_json::to_writer(out, self)?;
+        Ok(IsNull::No)
+    }
+}
+
+impl FromSql<Jsonb, Pg> for ParsedCv {
+    fn from_sql(bytes: PgValue) -> deserialize::Result<Self> {
+        let value = <serde_json::Value as FromSql<Jsonb, Pg>>::from_sql(bytes)?;
+        Ok(serde_json::from_value(value)?)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Section {
+    // Before the first heading: name, title and contact details
+    Header,
+    Experience,
+    Education,
+    Other,
+}
+
+// "EXPÉRIENCES PROFESSIONNELLES", "Formation :", "Technical skills"
+fn heading(line: &str) -> Option<Section> {
+    let folded = fold(line.trim_end_matches([':', ' ']));
+    if folded.split_whitespace().count() > 3 || folded.chars().any(|c| c.is_ascii_digit()) {
+        return None;
+    }
+    let starts = |prefixes: &[&str]| prefixes.iter().any(|prefix| folded.starts_with(prefix));
+    if starts(&[
+        "experience",
+        "parcours professionnel",
+        "emplois",
+        "work history",
+        "employment",
+        "professional experience",
+        "work experience",
+    ]) {
+        Some(Section::Experience)
+    } else if starts(&["formation", "education", "etudes", "diplome", "cursus"]) {
+        Some(Section::Education)
+    } else if starts(&[
+        "competence",
+        "skills",
+        "technical skills",
+        "langue",
+        "language",
+        "centres d'interet",
+        "interets",
+        "interests",
+        "loisirs",
+        "hobbies",
+        "profil",
+        "profile",
+        "summary",
+        "a propos",
+        "about",
+        "contact",
+        "coordonnees",
+        "certification",
+        "projets",
+        "projects",
+        "references",
+    ]) {
+        Some(Section::Other)
+    } else {
+        None
+    }
+}
+
+fn is_bullet(line: &str) -> bool {
+    line.starts_with(['•', '▪', '◦', '·', '*', '>'])
+        || ["- ", "– ", "— "]
+            .iter()
+            .any(|bullet| line.starts_with(bullet))
+}
+
+fn find_phone(text: &str) -> Option<String> {
+    FRENCH_PHONE
+        .find_iter(text)
+        .chain(INTERNATIONAL_PHONE.find_iter(text))
+        .find_map(|found| normalize_phone(found.as_str()))
+}
+
+fn find_links(text: &str) -> Vec<String> {
+    let mut links: Vec<String> = Vec::new();
+    for found in LINK.find_iter(text) {
+        let link = found.as_str().trim_end_matches(['.', ',', ';', ')', '/']);
+        let link = if link.to_lowercase().starts_with("http") {
+            link.to_string()
+        } else {
+            format!("https://{}", link)
+        };
+        if !links.contains(&link) {
+            links.push(link);
+        }
+    }
+    links
+}
+
+// Words of a name: letters, hyphens and apostrophes, "Jean-Pierre", "d'Artagnan"
+fn is_name_word(word: &str) -> bool {
+    word.chars().next().is_some_and(char::is_alphabetic)
+        && word
+            .chars()
+            .all(|c| c.is_alphabetic() || matches!(c, '-' | '\'' | '’'))
+}
+
+fn is_upper_word(word: &str) -> bool {
+    word.chars().filter(|c| c.is_alphabetic()).count() > 1 && !word.chars().any(char::is_lowercase)
+}
+
+// "Jane DOE", "Marie Claire DUPONT", "John Smith". The upper case words are the last name,
+// otherwise the first word is the first name
+fn split_name(line: &str) -> Option<(String, String)> {
+    let words: Vec<&str> = line.split_whitespace().collect();
+    if !(2..=4).contains(&words.len()) || !words.iter().all(|word| is_name_word(word)) {
+        return None;
+    }
+    let folded = fold(line);
+    let job_words = [
+        "curriculum",
+        "cv",
+        "resume",
+        "developpeur",
+        "developer",
+        "ingenieur",
+        "engineer",
+        "chef",
+        "manager",
+        "consultant",
+        "analyste",
+        "analyst",
+        "designer",
+        "architecte",
+        "architect",
+        "stagiaire",
+        "assistant",
+        "directeur",
+        "director",
+        "responsable",
+        "technicien",
+        "commercial",
+    ];
+    if folded
+        .split_whitespace()
+        .any(|word| job_words.contains(&word.trim_end_matches(['(', ')'])))
+    {
+        return None;
+    }
+    let upper: Vec<&str> = words.iter().copied().filter(|w| is_upper_word(w)).collect();
+    let (first, last): (Vec<&str>, Vec<&str>) = if !upper.is_empty() && upper.len() < words.len() {
+        words.iter().partition(|word| !is_upper_word(word))
+    } else {
+        (words[..1].to_vec(), words[1..].to_vec())
+    };
+    Some((first.join(" "), last.join(" ")))
+}
+
+fn find_name(header: &[&str], contact: &mut Contact) {
+    for line in header {
+        if let Some(labelled) = LABELLED_NAME.captures(line) {
+            let value = labelled["value"].trim().to_string();
+            if fold(&labelled["label"]).starts_with("prenom")
+                || fold(&labelled["label"]).starts_with("first")
+            {
+                contact.firstname = Some(value);
+            } else {
+                contact.lastname = Some(value);
+            }
+        }
+    }
+    if contact.firstname.is_some() || contact.lastname.is_some() {
+        return;
+    }
+    if let Some((firstname, lastname)) = header
+        .iter()
+        .take(NAME_LINES)
+        .filter(|line| !line.contains('@') && !line.chars().any(|c| c.is_ascii_digit()))
+        .find_map(|line| split_name(line))
+    {
+        contact.firstname = Some(firstname);
+        contact.lastname = Some(lastname);
+    }
+}
+
+// Text around a date, without the separators left by the date: "Développeur - Acme ( )"
+fn clean(text: &str) -> String {
+    let text = text.split_whitespace().collect::<Vec<_>>().join(" ");
+    let text = text.replace("( )", " ").replace("()", " ");
+    let separators: &[char] = &[
+        ' ', ':', '-', '–', '—', '|', ',', ';', '(', ')', '[', ']', '•', '*', '/',
+    ];
+    text.trim_matches(separators)
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+// "Développeur Rust chez Acme", "Data Analyst, Globex", "Master Informatique - Université de Lyon"
+fn split_entry(text: &str) -> (String, Option<String>) {
+    for separator in [" chez ", " at ", " @ ", " | ", " – ", " — ", " - ", ", "] {
+        if let Some((left, right)) = text.split_once(separator) {
+            let (left, right) = (clean(left), clean(right));
+            if !left.is_empty() && !right.is_empty() {
+                return (left, Some(right));
+            }
+        }
+    }
+    (clean(text), None)
+}
+
+fn is_degree(line: &str) -> bool {
+    let folded = fold(line);
+    [
+        "master",
+        "licence",
+        "bachelor",
+        "baccalaureat",
+        "bac",
+        "bts",
+        "dut",
+        "but",
+        "mba",
+        "diplome",
+        "doctorat",
+        "phd",
+        "msc",
+        "bsc",
+        "ingenieur",
+        "degree",
+        "certificat",
+    ]
+    .iter()
+    .any(|keyword| {
+        folded
+            .split(|c: char| !c.is_alphanumeric())
+            .any(|word| word == *keyword)
+    })
+}
+
+// Lines that may be a title or a degree, rather than a description
+fn is_entry_line(line: &str) -> bool {
+    !line.is_empty()
+        && line.len() <= MAX_ENTRY_LENGTH
+        && !is_bullet(line)
+        && !line.ends_with('.')
+        && heading(line).is_none()
+}
+
+fn parse_experience(lines: &[&str]) -> Vec<Experience> {
+    let mut entries = Vec::new();
+    let mut consumed = vec![false; lines.len()];
+    for (i, line) in lines.iter().enumerate() {
+        if consumed[i] || is_bullet(line) {
+            continue;
+        }
+        let Some((mut period, before, after)) = find_period(line) else {
+            continue;
+        };
+        let rest = clean(&format!("{} {}", before, after));
+        if period.start.is_none() {
+            // A lone year inside a sentence is not an entry: "migration vers Rust en 2021"
+            if !clean(before).is_empty() && !clean(after).is_empty() {
+                continue;
+            }
+            period.start = period.end.clone();
+        }
+        let previous = i.checked_sub(1).filter(|&p| {
+            !consumed[p] && is_entry_line(lines[p]) && find_period(lines[p]).is_none()
+        });
+        let (title, company) = if rest.is_empty() {
+            // Dates alone, the title follows, "Janv. 2020 – aujourd'hui\nData Analyst, Globex",
+            // or precedes when a description follows
+            match lines.get(i + 1).filter(|next| is_entry_line(next)) {
+                Some(next) => {
+                    consumed[i + 1] = true;
+                    split_entry(next)
+                }
+                None => match previous {
+                    Some(p) => split_entry(lines[p]),
+                    None => continue,
+                },
+            }
+        } else {
+            match (split_entry(&rest), previous) {
+                // The title precedes: "Software Engineer\nInitech | Sept 2015 to Present"
+                ((company, None), Some(p)) => (clean(lines[p]), Some(company)),
+                (entry, _) => entry,
+            }
+        };
+        if title.is_empty() {
+            continue;
+        }
+        consumed[i] = true;
+        entries.push(Experience {
+            title,
+            company,
+            start: period.start,
+            end: period.end,
+            current: period.current,
+        });
+    }
+    entries
+}
+
+fn parse_education(lines: &[&str]) -> Vec<Education> {
+    let mut entries: Vec<Education> = Vec::new();
+    // Line of the last entry, its dates or institution may follow
+    let mut last_line: Option<usize> = None;
+    let mut i = 0;
+    while i < lines.len() {
+        let line = lines[i];
+        let follows_last = last_line.is_some_and(|last| last + 1 == i);
+        let mut next = i + 1;
+        if is_bullet(line) {
+            i = next;
+            continue;
+        }
+        match find_period(line) {
+            Some((period, before, after)) => {
+                let rest = clean(&format!("{} {}", before, after));
+                let last = entries
+                    .last_mut()
+                    .filter(|last| follows_last && last.start.is_none() && last.end.is_none());
+                match (rest.is_empty(), last) {
+                    // "Master Informatique\n2010 - 2012" or "Master Informatique\nUniversité de Lyon, 2012"
+                    (_, Some(last)) if rest.is_empty() || last.institution.is_none() => {
+                        if !rest.is_empty() {
+                            last.institution = Some(rest);
+                        }
+                        last.start = period.start;
+                        last.end = period.end;
+                    }
+                    (true, _) => {
+                        if let Some(text) = lines.get(i + 1).filter(|text| is_entry_line(text)) {
+                            let (degree, institution) = split_entry(text);
+                            entries.push(Education {
+                                degree,
+                                institution,
+                                start: period.start,
+                                end: period.end,
+                            });
+                            next = i + 2;
+                        }
+                    }
+                    (false, _) => {
+                        let (degree, institution) = split_entry(&rest);
+                        entries.push(Education {
+                            degree,
+                            institution,
+                            start: period.start,
+                            end: period.end,
+                        });
+                    }
+                }
+                last_line = Some(next - 1);
+            }
+            None if is_entry_line(line) && is_degree(line) => {
+                let (degree, institution) = split_entry(line);
+                entries.push(Education {
+                    degree,
+                    institution,
+                    ..Default::default()
+                });
+                last_line = Some(i);
+            }
+            // The institution of a degree on its own line: "Master Informatique\nUniversité de Lyon"
+            None if follows_last && is_entry_line(line) => {
+                if let Some(last) = entries.last_mut().filter(|last| last.institution.is_none()) {
+                    last.institution = Some(clean(line));
+                    last_line = Some(i);
+                }
+            }
+            None => {}
+        }
+        i = next;
+    }
+    entries
+}
+
+pub fn parse(text: &str, dictionary: &SkillDictionary) -> ParsedCv {
+    let mut sections: Vec<(Section, Vec<&str>)> = vec![(Section::Header, Vec::new())];
+    for line in text.lines().map(str::trim).filter(|line| !line.is_empty()) {
+        match heading(line) {
+            Some(section) => sections.push((section, Vec::new())),
+            None => sections.last_mut().unwrap().1.push(line),
+        }
+    }
+    let lines = |wanted: Section| -> Vec<&str> {
+        sections
+            .iter()
+            .filter(|(section, _)| *section == wanted)
+            .flat_map(|(_, lines)| lines.iter().copied())
+            .collect()
+    };
+
+    let mut contact = Contact {
+        email: EMAIL.find(text).map(|email| email.as_str().to_lowercase()),
+        phone: find_phone(text),
+        links: find_links(text),
+        ..Default::default()
+    };
+    find_name(&lines(Section::Header), &mut contact);
+    ParsedCv {
+        contact,
+        // "github.com/jane" is not a mention of Git
+        skills: dictionary.find(&EMAIL.replace_all(&LINK.replace_all(text, " "), " ")),
+        experience: parse_experience(&lines(Section::Experience)),
+        education: parse_education(&lines(Section::Education)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{fs, path::Path};
+
+    fn dictionary() -> SkillDictionary {
+        SkillDictionary::parse(include_str!("../../config/skills.yaml")).unwrap()
+    }
+
+    // Every `<name>.txt` of the corpus is parsed into `<name>.json`
+    #[test]
+    fn test_fixture_corpus() {
+        let dictionary = dictionary();
+        let corpus = Path::new(env!("CARGO_MANIFEST_DIR")).join("src/parsing/fixtures");
+        let mut count = 0;
+        for entry in fs::read_dir(&corpus).unwrap() {
+            let path = entry.unwrap().path();
+            if path.extension().is_none_or(|extension| extension != "txt") {
+                continue;
+            }
+            let parsed = parse(&fs::read_to_string(&path).unwrap(), &dictionary);
+            let expected: ParsedCv =
+                serde_json::from_str(&fs::read_to_string(path.with_extension("json")).unwrap())
+                    .unwrap();
+            assert_eq!(
+                parsed,
+                expected,
+                "{}: {}",
+                path.display(),
+                serde_json::to_string_pretty(&parsed).unwrap()
+            );
+            count += 1;
+        }
+        assert!(count >= 5);
+    }
+
+    #[test]
+    fn test_split_name() {
+        let split = |line| split_name(line).map(|(first, last)| format!("{}|{}", first, last));
+        assert_eq!(split("Jane Doe").as_deref(), Some("Jane|Doe"));
+        assert_eq!(
+            split("Marie Claire DUPONT").as_deref(),
+            Some("Marie Claire|DUPONT")
+        );
+        assert_eq!(
+            split("DUPONT Jean-Pierre").as_deref(),
+            Some("Jean-Pierre|DUPONT")
+        );
+        assert_eq!(split("JANE DOE").as_deref(), Some("JANE|DOE"));
+        assert_eq!(split("Développeur Rust"), None);
+        assert_eq!(split("Curriculum Vitae"), None);
+        assert_eq!(split("Jane"), None);
+    }
+
+    #[test]
+    fn test_empty_text() {
+        assert_eq!(parse("", &dictionary()), ParsedCv::default());
+    }
+}
@@ -0,0 +1,153 @@
+// Skills dictionary, `config/skills.yaml` unless `extraction.skills_file` points to another file
+use std::{collections::HashSet, fs};
+
+use serde::Deserialize;
+
+use crate::config::settings::ExtractionSettings;
+
+const DEFAULT_DICTIONARY: &str = include_str!("../../config/skills.yaml");
+
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+pub struct Skill {
+    pub name: String,
+    #[serde(default)]
+    pub category: Option<String>,
+    #[serde(default)]
+    pub aliases: Vec<String>,
+}
+
+pub struct SkillDictionary {
+    skills: Vec<Skill>,
+    // Lowercased name and aliases with the index of their skill, longest first so that
+    // "spring boot" is tried before "spring"
+    terms: Vec<(String, usize)>,
+}
+
+// Letters, digits and the symbols ending "c++" or "c#" continue a word: "c" is not found in
+// "c++", "r" in "r&d", nor "c" in "c'est"
+fn is_word_char(c: char) -> bool {
+    c.is_alphanumeric() || matches!(c, '+' | '#' | '&' | '\'' | '’' | '_')
+}
+
+impl SkillDictionary {
+    pub fn new(skills: Vec<Skill>) -> Result<SkillDictionary, String> {
+        let mut seen = HashSet::new();
+        let mut terms = Vec::new();
+        for (index, skill) in skills.iter().enumerate() {
+            if skill.name.trim().is_empty() {
+                return Err("A skill of the dictionary has an empty name".to_string());
+            }
+            for term in std::iter::once(&skill.name).chain(&skill.aliases) {
+                let term = term.trim().to_lowercase();
+                if term.is_empty() {
+                    continue;
+                }
+                if !seen.insert(term.clone()) {
+                    return Err(format!("'{}' appears twice in the skills dictionary", term));
+                }
+                terms.push((term, index));
+            }
+        }
+        terms.sort_by(|(a, _), (b, _)| b.len().cmp(&a.len()).then_with(|| a.cmp(b)));
+        Ok(SkillDictionary { skills, terms })
+    }
+
+    pub fn parse(yaml: &str) -> Result<SkillDictionary, String> {
+        SkillDictionary::new(serde_yaml::from_str(yaml).map_err(|e| e.to_string())?)
+    }
+
+    pub fn from_settings(settings: &ExtractionSettings) -> Result<SkillDictionary, String> {
+        match &settings.skills_file {
+            Some(path) => SkillDictionary::parse(
+                &fs::read_to_string(path)
+                    .map_err(|e| format!("Failed to read the skills file {}: {}", path, e))?,
+            )
+            .map_err(|e| format!("Invalid skills file {}: {}", path, e)),
+            None => SkillDictionary::parse(DEFAULT_DICTIONARY),
+        }
+    }
+
+    pub fn skills(&self) -> &[Skill] {
+        &self.skills
+    }
+
+    // Skill named or aliased `term`, ignoring case: `k8s` gives `Kubernetes`
+    pub fn get(&self, term: &str) -> Option<&Skill> {
+        let term = term.trim().to_lowercase();
+        self.terms
+            .iter()
+            .find(|(candidate, _)| *candidate == term)
+            .map(|&(_, index)| &self.skills[index])
+    }
+
+    // Names of the skills found in `text`, in the order of their first mention
+    pub fn find(&self, text: &str) -> Vec<String> {
+        let text = text.to_lowercase();
+        let mut found: Vec<(usize, usize)> = Vec::new();
+        // Bytes already taken by a longer term
+        let mut taken: Vec<(usize, usize)> = Vec::new();
+        for (term, index) in &self.terms {
+            for (start, _) in text.match_indices(term.as_str()) {
+                let end = start + term.len();
+                let bounded = !text[..start].chars().next_back().is_some_and(is_word_char)
+                    && !text[end..].chars().next().is_some_and(is_word_char);
+                if !bounded || taken.iter().any(|&(s, e)| start < e && s < end) {
+                    continue;
+                }
+                taken.push((start, end));
+                match found.iter_mut().find(|(_, i)| i == index) {
+                    Some(first) => first.0 = first.0.min(start),
+                    None => found.push((start, *index)),
+                }
+            }
+        }
+        found.sort();
+        found
+            .into_iter()
+            .map(|(_, index)| self.skills[index].name.clone())
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_default_dictionary() {
+        let dictionary = SkillDictionary::parse(DEFAULT_DICTIONARY).unwrap();
+        assert!(dictionary.skills().len() > 50);
+        assert_eq!(
+            dictionary.find(
+                "Développeur C++ et C#, c'est du C. Spring Boot, Postgres et k8s ; R&D en Rust/Go lang."
+            ),
+            vec!["C++", "C#", "C", "Spring", "PostgreSQL", "Kubernetes", "Rust", "Golang"]
+        );
+        assert_eq!(dictionary.get(" K8S ").unwrap().name, "Kubernetes");
+        assert_eq!(
+            dictionary.get("postgresql").unwrap().category.as_deref(),
+            Some("database")
+        );
+        assert!(dictionary.get("cobol").is_none());
+        // Whole words only
+        assert!(dictionary
+            .find("Excellent javanais, scalable, cssr")
+            .is_empty());
+    }
+
+    #[test]
+    fn test_invalid_dictionaries() {
+        assert!(SkillDictionary::parse("- name: Rust\n- name: rust").is_err());
+        assert!(SkillDictionary::parse("- name: Rust\n  aliases: [Rust]").is_err());
+        assert!(SkillDictionary::parse("- name: ' '").is_err());
+        assert!(SkillDictionary::parse("name: Rust").is_err());
+        assert!(SkillDictionary::from_settings(&ExtractionSettings {
+            skills_file: Some("config/missing.yaml".to_string()),
+            interval_seconds: 30,
+            batch_size: 20,
+            max_attempts: 5,
+            retry_delay_seconds: 60,
+        })
+        .is_err());
+    }
+}
@@ -10,6 +10,21 @@ table! {
         phone -> Varchar,
         email -> Varchar,
         motivation -> Text,
+        cv_scan_status -> Varchar,
+        cv_scan_signature -> Nullable<Varchar>,
+        cv_scanned_at -> Nullable<Timestamp>,
+        cv_text -> Nullable<Text>,
+        cv_page_count -> Nullable<Int4>,
+        cv_text_status -> Varchar,
+        cv_text_attempts -> Int4,
+        cv_text_error -> Nullable<Varchar>,
+        cv_text_retry_at -> Nullable<Timestamp>,
+        cv_parsed -> Nullable<Jsonb>,
+        applied_at -> Timestamp,
+        consent_version -> Nullable<Varchar>,
+        consented_at -> Nullable<Timestamp>,
+        consent_ip -> Nullable<Varchar>,
+        anonymised_at -> Nullable<Timestamp>,
     }
 }
 
@@ -17,14 +32,20 @@ table! {
     company (id) {
         id -> Uuid,
         name -> Varchar,
+        retention_days -> Nullable<Int4>,
     }
 }
 
 table! {
     login_history (id) {
         id -> Uuid,
-        user_id -> Uuid,
+        user_id -> Nullable<Uuid>,
         login_timestamp -> Timestamp,
+        success -> Bool,
+        failure_reason -> Nullable<Varchar>,
+        ip_address -> Nullable<Varchar>,
+        user_agent -> Nullable<Varchar>,
+        auth_method -> Varchar,
     }
 }
 
@@ -36,7 +57,35 @@ table! {
         email -> Varchar,
         password -> Nullable<Varchar>,
         role -> Varchar,
-        login_session -> Nullable<Varchar>,
+        totp_secret -> Nullable<Varchar>,
+        totp_enabled -> Bool,
+        totp_last_step -> Nullable<Int8>,
+        failed_login_attempts -> Int4,
+        locked_until -> Nullable<Timestamp>,
+    }
+}
+
+table! {
+    user_recovery_codes (id) {
+        id -> Uuid,
+        user_id -> Uuid,
+        code_hash -> Varchar,
+        created_at -> Timestamp,
+        used_at -> Nullable<Timestamp>,
+    }
+}
+
+table! {
+    user_sessions (id) {
+        id -> Uuid,
+        user_id -> Uuid,
+        session_token -> Varchar,
+        user_agent -> Nullable<Varchar>,
+        ip_address -> Nullable<Varchar>,
+        created_at -> Timestamp,
+        last_seen_at -> Timestamp,
+        expires_at -> Timestamp,
+        revoked_at -> Nullable<Timestamp>,
     }
 }
 
@@ -50,15 +99,82 @@ table! {
         location -> Varchar,
         remote -> Nullable<Varchar>,
         employment_type -> Varchar,
-        salary -> Float,
+        salary -> Double,
         created_at -> Timestamp,
         updated_at -> Nullable<Timestamp>,
     }
 }
 
+table! {
+    skills (id) {
+        id -> Uuid,
+        name -> Varchar,
+        category -> Nullable<Varchar>,
+    }
+}
+
+table! {
+    job_offer_skills (job_offer_id, skill_id) {
+        job_offer_id -> Uuid,
+        skill_id -> Uuid,
+        required -> Bool,
+    }
+}
+
+table! {
+    candidate_skills (candidate_id, skill_id) {
+        candidate_id -> Uuid,
+        skill_id -> Uuid,
+    }
+}
+
+table! {
+    candidate_merges (id) {
+        id -> Uuid,
+        survivor_id -> Uuid,
+        merged_id -> Uuid,
+        merged_by -> Nullable<Uuid>,
+        merged_at -> Timestamp,
+        filled_fields -> Array<Varchar>,
+        snapshot -> Jsonb,
+    }
+}
+
+table! {
+    data_export_requests (id) {
+        id -> Uuid,
+        email -> Varchar,
+        token_hash -> Varchar,
+        created_at -> Timestamp,
+        expires_at -> Timestamp,
+        downloaded_at -> Nullable<Timestamp>,
+    }
+}
+
 joinable!(candidate -> company (company_id));
 joinable!(users -> company (company_id));
 joinable!(login_history -> users (user_id));
 joinable!(job_offers -> company (company_id));
+joinable!(user_sessions -> users (user_id));
+joinable!(user_recovery_codes -> users (user_id));
+joinable!(job_offer_skills -> job_offers (job_offer_id));
+joinable!(job_offer_skills -> skills (skill_id));
+joinable!(candidate_skills -> candidate (candidate_id));
+joinable!(candidate_skills -> skills (skill_id));
+joinable!(candidate_merges -> candidate (survivor_id));
+joinable!(candidate_merges -> users (merged_by));
 
-allow_tables_to_appear_in_same_query!(candidate, company, login_history, users, job_offers);
+allow_tables_to_appear_in_same_query!(
+    candidate,
+    company,
+    login_history,
+    users,
+    job_offers,
+    user_sessions,
+    user_recovery_codes,
+    skills,
+    job_offer_skills,
+    candidate_skills,
+    candidate_merges,
+    data_export_requests
+);
@@ -0,0 +1,181 @@
+// Object storage of the CV files, selected by `storage.backend`: a local directory for a single
+// instance, an S3-compatible bucket (AWS, MinIO...) when several instances share the files
+use std::{
+    collections::HashSet,
+    io::{self, Read},
+    sync::Arc,
+    time::Duration,
+};
+
+use serde::Serialize;
+use tracing::info;
+
+use crate::{
+    config::settings::{Settings, StorageBackend},
+    storage::{local::LocalStorage, s3::S3Storage},
+};
+
+// Headers of a download, kept by presigned URLs through the `response-*` parameters
+pub struct Download<'a> {
+    pub file_name: &'a str,
+    pub content_type: &'a str,
+}
+
+// Keys are `/` separated and validated by `CvStorage`. Blocking, callers run it on the blocking
+// thread pool. A missing object is an `io::ErrorKind::NotFound` error
+pub trait Storage: Send + Sync {
+    fn name(&self) -> &'static str;
+
+    // Replace the object if it exists
+    fn put(&self, key: &str, content: &[u8]) -> io::Result<()>;
+
+    fn stream(&self, key: &str) -> io::Result<Box<dyn Read + Send>>;
+
+    fn get(&self, key: &str) -> io::Result<Vec<u8>> {
+        let mut content = Vec::new();
+        self.stream(key)?.read_to_end(&mut content)?;
+        Ok(content)
+    }
+
+    // Deleting a missing object succeeds
+    fn delete(&self, key: &str) -> io::Result<()>;
+
+    // Every key of the storage
+    fn list(&self) -> io::Result<Vec<String>>;
+
+    fn rename(&self, from: &str, to: &str) -> io::Result<()> {
+        self.put(to, &self.get(from)?)?;
+        self.delete(from)
+    }
+
+    // URL giving a temporary access to the object, `None` when the backend can not sign one
+    fn presign(
+        &self,
+        _key: &str,
+        _download: &Download,
+        _expires_in: Duration,
+    ) -> io::Result<Option<String>> {
+        Ok(None)
+    }
+}
+
+pub fn open(backend: StorageBackend, settings: &Settings) -> Arc<dyn Storage> {
+    match backend {
+        StorageBackend::Local => Arc::new(LocalStorage::new(&settings.upload.cv_path)),
+        StorageBackend::S3 => Arc::new(S3Storage::from_settings(&settings.storage.s3)),
+    }
+}
+
+#[derive(Debug, Default, PartialEq, Serialize)]
+pub struct MigrationReport {
+    pub copied: usize,
+    // Already present in the destination
+    pub skipped: usize,
+    pub deleted: usize,
+}
+
+// Copy every object missing from `to`, then delete it from `from` when `delete_source` is set and
+// the copy reads back identical. Interrupted migrations can be run again
+pub fn migrate(
+    from: &dyn Storage,
+    to: &dyn Storage,
+    delete_source: bool,
+) -> io::Result<MigrationReport> {
+    let existing: HashSet<String> = to.list()?.into_iter().collect();
+    let mut report = MigrationReport::default();
+    for key in from.list()? {
+        let content = from.get(&key)?;
+        if existing.contains(&key) {
+            report.skipped += 1;
+        } else {
+            to.put(&key, &content)?;
+            report.copied += 1;
+        }
+        if delete_source {
+            if to.get(&key)? != content {
+                return Err(io::Error::other(format!(
+                    "{} differs in the {} storage, the source is kept",
+                    key,
+                    to.name()
+                )));
+            }
+            from.delete(&key)?;
+            report.deleted += 1;
+        }
+    }
+    info!(
+        "Storage migrated from {} to {}: {:?}",
+        from.name(),
+        to.name(),
+        report
+    );
+    Ok(report)
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use uuid::Uuid;
+
+    // Contract shared by every backend
+    pub(crate) fn exercise(storage: &dyn Storage) {
+        let key = format!("{}/{}.pdf", Uuid::new_v4(), Uuid::new_v4());
+        assert_eq!(
+            storage.get(&key).unwrap_err().kind(),
+            io::ErrorKind::NotFound
+        );
+        storage.put(&key, b"%PDF-1.4 first").unwrap();
+        storage.put(&key, b"%PDF-1.4 second").unwrap();
+        assert_eq!(storage.get(&key).unwrap(), b"%PDF-1.4 second");
+        let mut streamed = Vec::new();
+        storage
+            .stream(&key)
+            .unwrap()
+            .read_to_end(&mut streamed)
+            .unwrap();
+        assert_eq!(streamed, b"%PDF-1.4 second");
+        assert!(storage.list().unwrap().contains(&key));
+
+        let renamed = format!("quarantine/{}", key);
+        storage.rename(&key, &renamed).unwrap();
+        assert_eq!(
+            storage.get(&key).unwrap_err().kind(),
+            io::ErrorKind::NotFound
+        );
+        assert_eq!(storage.get(&renamed).unwrap(), b"%PDF-1.4 second");
+
+        storage.delete(&renamed).unwrap();
+        storage.delete(&renamed).unwrap();
+        assert!(!storage.list().unwrap().contains(&renamed));
+    }
+
+    #[test]
+    fn test_migrate_between_local_storages() {
+        let root = std::env::temp_dir().join(format!("platform-cv-migrate-{}", Uuid::new_v4()));
+        let from = LocalStorage::new(root.join("from"));
+        let to = LocalStorage::new(root.join("to"));
+        from.put("a/1.pdf", b"one").unwrap();
+        from.put("b/2.pdf", b"two").unwrap();
+        to.put("b/2.pdf", b"two").unwrap();
+
+        let report = migrate(&from, &to, false).unwrap();
+        assert_eq!(
+            report,
+            MigrationReport {
+                copied: 1,
+                skipped: 1,
+                deleted: 0
+            }
+        );
+        assert_eq!(to.get("a/1.pdf").unwrap(), b"one");
+
+        // Replayed with the deletion of the source
+        let report = migrate(&from, &to, true).unwrap();
+        assert_eq!(report.copied, 0);
+        assert_eq!(report.deleted, 2);
+        assert!(from.list().unwrap().is_empty());
+        assert_eq!(to.list().unwrap().len(), 2);
+
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+}
@@ -0,0 +1,73 @@
+// ZIP archive of a subject access export: `data.json` and the CV files scanned clean, named
+// `cv/{application id}.{extension}`. Written to an unnamed temporary file, like the CVs the archive
+// is never held whole in memory
+use std::{
+    fs::File,
+    io::{self, Seek, Write},
+};
+
+use tracing::warn;
+use uuid::Uuid;
+use zip::{write::SimpleFileOptions, CompressionMethod, ZipWriter};
+
+use crate::{
+    models::{candidate::ScanStatus, data_export::SubjectAccessExport},
+    storage::{validation::CvFormat, CvError, CvStorage},
+};
+
+// CV files of the applications and of the applications merged into them
+fn cv_files(export: &SubjectAccessExport) -> Vec<(Uuid, String)> {
+    let mut files = Vec::new();
+    for application in &export.applications {
+        let candidate = &application.candidate;
+        if candidate.cv_scan_status == ScanStatus::Clean {
+            files.push((candidate.id, candidate.file_name.clone()));
+        }
+        for (i_merged, file_name) in &application.merged_files {
+            let clean = application.merged_applications.iter().any(|merge| {
+                merge.merged_id == *i_merged
+                    && merge.application.cv_scan_status == ScanStatus::Clean
+            });
+            if clean {
+                files.push((*i_merged, file_name.clone()));
+            }
+        }
+    }
+    files
+}
+
+// The archive read from its start, the file is deleted once closed
+pub fn archive(storage: &CvStorage, export: &SubjectAccessExport) -> Result<File, CvError> {
+    let zip_error = |e: zip::result::ZipError| CvError::Io(io::Error::from(e));
+    let mut zip = ZipWriter::new(tempfile::tempfile()?);
+    let options = SimpleFileOptions::default().compression_method(CompressionMethod::Deflated);
+
+    zip.start_file("data.json", options).map_err(zip_error)?;
+    let data = serde_json::to_vec_pretty(export).map_err(io::Error::from)?;
+    zip.write_all(&data)?;
+
+    for (i_application, file_name) in cv_files(export) {
+        let mut cv = match storage.open(&file_name) {
+            Ok(cv) => cv,
+            // Listed in `data.json` all the same
+            Err(CvError::Io(e)) if e.kind() == io::ErrorKind::NotFound => {
+                warn!("CV {} of the export is missing from the storage", file_name);
+                continue;
+            }
+            Err(e) => return Err(e),
+        };
+        let extension = CvFormat::from_file_name(&file_name)
+            .map(|format| format.extension())
+            .unwrap_or("bin");
+        // Documents and images are compressed already
+        zip.start_file(
+            format!("cv/{}.{}", i_application, extension),
+            options.compression_method(CompressionMethod::Stored),
+        )
+        .map_err(zip_error)?;
+        io::copy(&mut cv, &mut zip)?;
+    }
+    let mut file = zip.finish().map_err(zip_error)?;
+    file.rewind()?;
+    Ok(file)
+}
@@ -0,0 +1,355 @@
+// Plain text of the CV documents, for the full-text search. PDF files are parsed with lopdf,
+// DOCX files are read from their `word/document.xml` part. Images and ODT files have no text
+// extracted, scanned documents would need an OCR
+use std::{
+    fmt,
+    io::{Cursor, Read},
+    panic::{self, AssertUnwindSafe},
+};
+
+use lopdf::Document;
+use quick_xml::{events::Event, Reader};
+use zip::ZipArchive;
+
+use crate::storage::validation::{CvFormat, MAX_INFLATED_SIZE};
+
+// Text kept per CV, in bytes. Postgres refuses a `tsvector` over 1MB and a CV is far shorter
+pub const MAX_TEXT_LENGTH: usize = 256 * 1024;
+const DOCX_DOCUMENT: &str = "word/document.xml";
+const DOCX_PROPERTIES: &str = "docProps/app.xml";
+
+#[derive(Debug, PartialEq)]
+pub struct ExtractedText {
+    pub text: String,
+    // From the page tree of a PDF, from the properties saved by the editor of a DOCX
+    pub page_count: Option<i32>,
+}
+
+#[derive(Debug)]
+pub enum ExtractionError {
+    // Nothing to extract from this format, never retried
+    Unsupported(CvFormat),
+    Malformed(String),
+}
+
+impl fmt::Display for ExtractionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ExtractionError::Unsupported(format) => {
+                write!(f, "no text extraction for .{} files", format.extension())
+            }
+            ExtractionError::Malformed(reason) => write!(f, "unreadable document: {}", reason),
+        }
+    }
+}
+
+impl std::error::Error for ExtractionError {}
+
+fn malformed(error: impl fmt::Display) -> ExtractionError {
+    ExtractionError::Malformed(error.to_string())
+}
+
+// One line per line of the document, inner whitespace collapsed, empty lines dropped, cut at
+// `MAX_TEXT_LENGTH` between two words
+fn normalize(text: &str) -> String {
+    let mut normalized = String::new();
+    'lines: for line in text.lines() {
+        let mut words = line
+            .split(|c: char| c.is_whitespace() || c.is_control())
+            .filter(|word| !word.is_empty())
+            .peekable();
+        if words.peek().is_none() {
+            continue;
+        }
+        for word in words {
+            if normalized.len() + word.len() + 1 > MAX_TEXT_LENGTH {
+                break 'lines;
+            }
+            normalized.push_str(word);
+            normalized.push(' ');
+        }
+        normalized.pop();
+        normalized.push('\n');
+    }
+    normalized.truncate(normalized.trim_end().len());
+    normalized
+}
+
+fn extract_pdf(bytes: &[u8]) -> Result<ExtractedText, ExtractionError> {
+    let document = Document::load_mem(bytes).map_err(malformed)?;
+    let pages: Vec<u32> = document.get_pages().keys().copied().collect();
+    if pages.is_empty() {
+        return Err(malformed("the PDF has no page"));
+    }
+    // Pages are extracted one by one, a page lopdf can not decode does not lose the others
+    let mut text = String::new();
+    let mut failures = 0;
+    for page in &pages {
+        match document.extract_text(&[*page]) {
+            Ok(page_text) => {
+                text.push_str(&page_text);
+                text.push('\n');
+            }
+            Err(_) => failures += 1,
+        }
+    }
+    if failures == pages.len() {
+        return Err(malformed("no page could be decoded"));
+    }
+    Ok(ExtractedText {
+        text: normalize(&text),
+        page_count: i32::try_from(pages.len()).ok(),
+    })
+}
+
+fn read_zip_entry(
+    archive: &mut ZipArchive<Cursor<&[u8]>>,
+    name: &str,
+) -> Result<Option<String>, ExtractionError> {
+    let entry = match archive.by_name(name) {
+        Ok(entry) => entry,
+        Err(zip::result::ZipError::FileNotFound) => return Ok(None),
+        Err(e) => return Err(malformed(e)),
+    };
+    let mut content = String::new();
+    entry
+        .take(MAX_INFLATED_SIZE)
+        .read_to_string(&mut content)
+        .map_err(malformed)?;
+    Ok(Some(content))
+}
+
+// Runs of `<w:t>`, paragraphs and breaks become new lines, tabs become spaces
+fn docx_text(xml: &str) -> Result<String, ExtractionError> {
+    let mut reader = Reader::from_str(xml);
+    let mut text = String::new();
+    let mut in_text = false;
+    loop {
+        match reader.read_event().map_err(malformed)? {
+            Event::Start(element) if element.name().as_ref() == b"w:t" => in_text = true,
+            Event::End(element) => match element.name().as_ref() {
+                b"w:t" => in_text = false,
+                b"w:p" => text.push('\n'),
+                _ => {}
+            },
+            Event::Empty(element) => match element.name().as_ref() {
+                b"w:tab" => text.push(' '),
+                b"w:br" | b"w:cr" => text.push('\n'),
+                _ => {}
+            },
+            Event::Text(content) if in_text => {
+                text.push_str(&content.unescape().map_err(malformed)?)
+            }
+            Event::Eof => break,
+            _ => {}
+        }
+    }
+    Ok(text)
+}
+
+// `<Pages>` of the extended properties, only as accurate as the editor that saved the file
+fn docx_page_count(xml: &str) -> Option<i32> {
+    let mut reader = Reader::from_str(xml);
+    let mut in_pages = false;
+    loop {
+        match reader.read_event().ok()? {
+            Event::Start(element) => in_pages = element.name().as_ref() == b"Pages",
+            Event::Text(content) if in_pages => {
+                return content.unescape().ok()?.trim().parse().ok()
+            }
+            Event::Eof => return None,
+            _ => {}
+        }
+    }
+}
+
+fn extract_docx(bytes: &[u8]) -> Result<ExtractedText, ExtractionError> {
+    let mut archive = ZipArchive::new(Cursor::new(bytes)).map_err(malformed)?;
+    let document = read_zip_entry(&mut archive, DOCX_DOCUMENT)?
+        .ok_or_else(|| malformed(format!("{} is missing", DOCX_DOCUMENT)))?;
+    let page_count = read_zip_entry(&mut archive, DOCX_PROPERTIES)?
+        .as_deref()
+        .and_then(docx_page_count);
+    Ok(ExtractedText {
+        text: normalize(&docx_text(&document)?),
+        page_count,
+    })
+}
+
+pub fn supports(format: CvFormat) -> bool {
+    matches!(format, CvFormat::Pdf | CvFormat::Docx)
+}
+
+// Blocking and CPU bound, callers run it on the blocking thread pool. `bytes` were accepted by
+// `validation::validate`, a parser panic on a hostile file is reported as a malformed document
+pub fn extract(format: CvFormat, bytes: &[u8]) -> Result<ExtractedText, ExtractionError> {
+    let parse = match format {
+        CvFormat::Pdf => extract_pdf,
+        CvFormat::Docx => extract_docx,
+        _ => return Err(ExtractionError::Unsupported(format)),
+    };
+    panic::catch_unwind(AssertUnwindSafe(|| parse(bytes)))
+        .unwrap_or_else(|_| Err(malformed("the parser panicked")))
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use lopdf::{content::Content, content::Operation, dictionary, Object, Stream};
+    use std::io::Write;
+    use zip::{write::SimpleFileOptions, ZipWriter};
+
+    // A PDF with one page per entry, each line written with a standard font
+    pub(crate) fn pdf(pages: &[&str]) -> Vec<u8> {
+        let mut document = Document::with_version("1.5");
+        let pages_id = document.new_object_id();
+        let font_id = document.add_object(dictionary! {
+            "Type" => "Font",
+            "Subtype" => "Type1",
+            "BaseFont" => "Helvetica",
+            "Encoding" => "WinAnsiEncoding",
+        });
+        let resources_id = document.add_object(dictionary! {
+            "Font" => dictionary! { "F1" => font_id },
+        });
+        let mut kids = Vec::new();
+        for page in pages {
+            let mut operations = vec![
+                Operation::new("BT", vec![]),
+                Operation::new("Tf", vec!["F1".into(), 12.into()]),
+                Operation::new("Td", vec![72.into(), 760.into()]),
+                Operation::new("TL", vec![14.into()]),
+            ];
+            for line in page.lines() {
+                operations.push(Operation::new("Tj", vec![Object::string_literal(line)]));
+                operations.push(Operation::new("T*", vec![]));
+            }
+            operations.push(Operation::new("ET", vec![]));
+            let content = Content { operations };
+            let content_id =
+                document.add_object(Stream::new(dictionary! {}, content.encode().unwrap()));
+            kids.push(
+                document
+                    .add_object(dictionary! {
+                        "Type" => "Page",
+                        "Parent" => pages_id,
+                        "Contents" => content_id,
+                    })
+                    .into(),
+            );
+        }
+        document.objects.insert(
+            pages_id,
+            Object::Dictionary(dictionary! {
+                "Type" => "Pages",
+                "Count" => kids.len() as i64,
+                "Kids" => kids,
+                "Resources" => resources_id,
+                "MediaBox" => vec![0.into(), 0.into(), 595.into(), 842.into()],
+            }),
+        );
+        let catalog_id = document.add_object(dictionary! {
+            "Type" => "Catalog",
+            "Pages" => pages_id,
+        });
+        document.trailer.set("Root", catalog_id);
+        let mut bytes = Vec::new();
+        document.save_to(&mut bytes).unwrap();
+        bytes
+    }
+
+    // A DOCX with one paragraph per line of `text`
+    pub(crate) fn docx(text: &str, pages: Option<i32>) -> Vec<u8> {
+        let paragraphs: String = text
+            .lines()
+            .map(|line| {
+                let line = line
+                    .replace('&', "&amp;")
+                    .replace('<', "&lt;")
+                    .replace('>', "&gt;");
+                let runs: Vec<String> = line
+                    .split('\t')
+                    .map(|run| format!("<w:r><w:t xml:space=\"preserve\">{}</w:t></w:r>", run))
+                    .collect();
+                format!("<w:p>{}</w:p>", runs.join("<w:r><w:tab/></w:r>"))
+            })
+            .collect();
+        let mut writer = ZipWriter::new(Cursor::new(Vec::new()));
+        let options = SimpleFileOptions::default();
+        writer.start_file("[Content_Types].xml", options).unwrap();
+        writer
+            .write_all(b"<?xml version=\"1.0\"?><Types/>")
+            .unwrap();
+        writer.start_file(DOCX_DOCUMENT, options).unwrap();
+        write!(
+            writer,
+            "<?xml version=\"1.0\"?><w:document xmlns:w=\"http://schemas.openxmlformats.org/wordprocessingml/2006/main\"><w:body>{}</w:body></w:document>",
+            paragraphs
+        )
+        .unwrap();
+        if let Some(pages) = pages {
+            writer.start_file(DOCX_PROPERTIES, options).unwrap();
+            write!(
+                writer,
+                "<?xml version=\"1.0\"?><Properties><Pages>{}</Pages></Properties>",
+                pages
+            )
+            .unwrap();
+        }
+        writer.finish().unwrap().into_inner()
+    }
+
+    #[test]
+    fn test_extract_pdf() {
+        let bytes = pdf(&["Jane Doe\nRust developer", "Experience\n  Acme   2020-2024"]);
+        let extracted = extract(CvFormat::Pdf, &bytes).unwrap();
+        assert_eq!(extracted.page_count, Some(2));
+        assert!(extracted.text.contains("Jane Doe"), "{}", extracted.text);
+        assert!(extracted.text.contains("Rust developer"));
+        assert!(extracted.text.contains("Acme 2020-2024"));
+
+        assert!(matches!(
+            extract(CvFormat::Pdf, b"%PDF-1.4\ngarbage"),
+            Err(ExtractionError::Malformed(_))
+        ));
+    }
+
+    #[test]
+    fn test_extract_docx() {
+        let bytes = docx("Jane Doe\nSkills:\tRust & SQL\n\n<Lead> developer", Some(3));
+        let extracted = extract(CvFormat::Docx, &bytes).unwrap();
+        assert_eq!(
+            extracted,
+            ExtractedText {
+                text: "Jane Doe\nSkills: Rust & SQL\n<Lead> developer".to_string(),
+                page_count: Some(3),
+            }
+        );
+        assert_eq!(
+            extract(CvFormat::Docx, &docx("CV", None))
+                .unwrap()
+                .page_count,
+            None
+        );
+        assert!(matches!(
+            extract(CvFormat::Docx, b"PK\x03\x04word/document.xml"),
+            Err(ExtractionError::Malformed(_))
+        ));
+    }
+
+    #[test]
+    fn test_unsupported_formats_and_length_limit() {
+        assert!(supports(CvFormat::Docx));
+        assert!(!supports(CvFormat::Odt));
+        assert!(matches!(
+            extract(CvFormat::Png, b"\x89PNG\r\n\x1a\n"),
+            Err(ExtractionError::Unsupported(CvFormat::Png))
+        ));
+        let long = "word ".repeat(MAX_TEXT_LENGTH / 4);
+        let normalized = normalize(&format!("short line\n{}", long));
+        assert!(normalized.len() <= MAX_TEXT_LENGTH);
+        assert!(normalized.starts_with("short line\nword word"));
+        assert!(normalized.ends_with("word"));
+        assert_eq!(normalize(" a \t b\u{0}c \n\n\n d "), "a b c\nd");
+    }
+}
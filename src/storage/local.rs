@@ -0,0 +1,159 @@
+// Files under a local directory, `{root}/{key}`
+use std::{
+    fs::{self, File, OpenOptions},
+    io::{self, Read, Write},
+    os::unix::fs::{DirBuilderExt, OpenOptionsExt},
+    path::{Component, Path, PathBuf},
+};
+
+use uuid::Uuid;
+
+use crate::storage::backend::Storage;
+
+// Prefix of the files being written, never listed
+const TEMPORARY_PREFIX: &str = ".tmp-";
+
+pub struct LocalStorage {
+    root: PathBuf,
+}
+
+impl LocalStorage {
+    pub fn new(root: impl Into<PathBuf>) -> LocalStorage {
+        LocalStorage { root: root.into() }
+    }
+
+    // Relative paths without `..` only, whatever the caller validated
+    fn path(&self, key: &str) -> io::Result<PathBuf> {
+        let relative = Path::new(key);
+        let valid = !key.is_empty()
+            && !key.contains('\\')
+            && relative
+                .components()
+                .all(|component| matches!(component, Component::Normal(_)));
+        if !valid {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("invalid storage key '{}'", key),
+            ));
+        }
+        Ok(self.root.join(relative))
+    }
+
+    fn create_parent(&self, path: &Path) -> io::Result<()> {
+        match path.parent() {
+            Some(parent) => fs::DirBuilder::new()
+                .recursive(true)
+                .mode(0o750)
+                .create(parent),
+            None => Ok(()),
+        }
+    }
+
+    fn list_directory(&self, directory: &Path, keys: &mut Vec<String>) -> io::Result<()> {
+        for entry in fs::read_dir(directory)? {
+            let entry = entry?;
+            let path = entry.path();
+            if entry.file_type()?.is_dir() {
+                self.list_directory(&path, keys)?;
+            } else if !entry.file_name().to_string_lossy().starts_with('.') {
+                if let Ok(relative) = path.strip_prefix(&self.root) {
+                    keys.push(relative.to_string_lossy().into_owned());
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Storage for LocalStorage {
+    fn name(&self) -> &'static str {
+        "local"
+    }
+
+    // Written to a temporary file then renamed, readers never see a partial file
+    fn put(&self, key: &str, content: &[u8]) -> io::Result<()> {
+        let path = self.path(key)?;
+        self.create_parent(&path)?;
+        let temporary = path.with_file_name(format!("{}{}", TEMPORARY_PREFIX, Uuid::new_v4()));
+        // `create_new` never follows nor replaces an existing file
+        let mut file = OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .mode(0o640)
+            .open(&temporary)?;
+        let written = file
+            .write_all(content)
+            .and_then(|_| file.sync_all())
+            .and_then(|_| fs::rename(&temporary, &path));
+        if written.is_err() {
+            let _ = fs::remove_file(&temporary);
+        }
+        written
+    }
+
+    fn stream(&self, key: &str) -> io::Result<Box<dyn Read + Send>> {
+        Ok(Box::new(File::open(self.path(key)?)?))
+    }
+
+    fn get(&self, key: &str) -> io::Result<Vec<u8>> {
+        fs::read(self.path(key)?)
+    }
+
+    fn delete(&self, key: &str) -> io::Result<()> {
+        match fs::remove_file(self.path(key)?) {
+            Err(e) if e.kind() != io::ErrorKind::NotFound => Err(e),
+            _ => Ok(()),
+        }
+    }
+
+    fn list(&self) -> io::Result<Vec<String>> {
+        let mut keys = Vec::new();
+        match self.list_directory(&self.root, &mut keys) {
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(Vec::new()),
+            Err(e) => Err(e),
+            Ok(()) => {
+                keys.sort();
+                Ok(keys)
+            }
+        }
+    }
+
+    fn rename(&self, from: &str, to: &str) -> io::Result<()> {
+        let target = self.path(to)?;
+        self.create_parent(&target)?;
+        fs::rename(self.path(from)?, target)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::backend::tests::exercise;
+    use std::os::unix::fs::PermissionsExt;
+
+    #[test]
+    fn test_local_storage() {
+        let root = std::env::temp_dir().join(format!("platform-cv-local-{}", Uuid::new_v4()));
+        let storage = LocalStorage::new(&root);
+        assert!(storage.list().unwrap().is_empty());
+        exercise(&storage);
+
+        storage.put("company/cv.pdf", b"%PDF-1.4").unwrap();
+        let path = root.join("company/cv.pdf");
+        assert_eq!(
+            fs::metadata(&path).unwrap().permissions().mode() & 0o777,
+            0o640
+        );
+        assert_eq!(storage.list().unwrap(), vec!["company/cv.pdf"]);
+
+        for key in ["", "../secret.key", "/etc/passwd", "a/../../b", "a\\b"] {
+            assert_eq!(
+                storage.put(key, b"x").unwrap_err().kind(),
+                io::ErrorKind::InvalidInput,
+                "{}",
+                key
+            );
+        }
+        fs::remove_dir_all(&root).unwrap();
+    }
+}
@@ -0,0 +1,444 @@
+// CV files: validated by content, written to `quarantine/{company_id}/{uuid}.{extension}` in the
+// configured storage and moved to `{company_id}/{uuid}.{extension}` once the malware scan reports
+// them clean. The name sent by the client is not kept
+pub mod backend;
+pub mod export;
+pub mod extraction;
+pub mod local;
+pub mod s3;
+pub mod scanner;
+pub mod validation;
+
+use std::{fmt, io, io::Read, path::PathBuf, sync::Arc, time::Duration};
+
+use tracing::warn;
+use uuid::Uuid;
+
+use crate::{
+    config::settings::Settings,
+    constants,
+    error::{AppError, FieldError},
+    models::candidate::ScanStatus,
+    storage::{
+        backend::{Download, Storage},
+        local::LocalStorage,
+        scanner::{ScanVerdict, Scanner},
+        validation::{CvFormat, SizeLimits},
+    },
+};
+
+// Not a UUID, never taken for a company shard by `key`
+const QUARANTINE_DIRECTORY: &str = "quarantine";
+
+#[derive(Debug)]
+pub enum CvError {
+    Empty,
+    TooLarge,
+    UnsupportedType,
+    PdfJavaScript,
+    PdfEncrypted,
+    Infected,
+    // A stored name that does not follow `{company_id}/{uuid}.{extension}`
+    InvalidName,
+    Io(io::Error),
+}
+
+impl fmt::Display for CvError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CvError::Empty => write!(f, "{}", constants::MESSAGE_CV_EMPTY),
+            CvError::TooLarge => write!(f, "{}", constants::MESSAGE_CV_TOO_LARGE),
+            CvError::UnsupportedType => write!(f, "{}", constants::MESSAGE_CV_UNSUPPORTED_TYPE),
+            CvError::PdfJavaScript => write!(f, "{}", constants::MESSAGE_CV_PDF_JAVASCRIPT),
+            CvError::PdfEncrypted => write!(f, "{}", constants::MESSAGE_CV_PDF_ENCRYPTED),
+            CvError::Infected => write!(f, "{}", constants::MESSAGE_CV_INFECTED),
+            CvError::InvalidName => write!(f, "invalid stored CV name"),
+            CvError::Io(e) => write!(f, "CV storage error: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for CvError {}
+
+impl From<io::Error> for CvError {
+    fn from(error: io::Error) -> Self {
+        CvError::Io(error)
+    }
+}
+
+// Refused uploads are reported on the `file` field of the form
+impl From<CvError> for AppError {
+    fn from(error: CvError) -> Self {
+        match error {
+            CvError::TooLarge => AppError::PayloadTooLarge {
+                error_message: error.to_string(),
+            },
+            CvError::Empty
+            | CvError::UnsupportedType
+            | CvError::PdfJavaScript
+            | CvError::PdfEncrypted
+            | CvError::Infected => AppError::validation(vec![FieldError {
+                field: "file".to_string(),
+                message: error.to_string(),
+            }]),
+            CvError::InvalidName => AppError::NotFound {
+                error_message: constants::MESSAGE_RESOURCE_NOT_FOUND.to_string(),
+            },
+            CvError::Io(e) if e.kind() == io::ErrorKind::NotFound => AppError::NotFound {
+                error_message: constants::MESSAGE_RESOURCE_NOT_FOUND.to_string(),
+            },
+            CvError::Io(_) => AppError::InternalServerError {
+                error_message: error.to_string(),
+            },
+        }
+    }
+}
+
+// CV written by `CvStorage::store`, `file_name` is the value saved in `candidate.file_name`
+#[derive(Debug)]
+pub struct StoredCv {
+    pub file_name: String,
+    pub format: CvFormat,
+    pub size: usize,
+}
+
+// Status saved on the candidate after `CvStorage::scan`
+#[derive(Debug, PartialEq)]
+pub struct ScanOutcome {
+    pub status: ScanStatus,
+    pub signature: Option<String>,
+}
+
+pub struct CvStorage {
+    backend: Arc<dyn Storage>,
+    limits: SizeLimits,
+    // Downloads are redirected to a presigned URL valid this long, when the backend signs one
+    presign_expiry: Option<Duration>,
+}
+
+impl CvStorage {
+    pub fn new(backend: Arc<dyn Storage>, limits: SizeLimits) -> CvStorage {
+        CvStorage {
+            backend,
+            limits,
+            presign_expiry: None,
+        }
+    }
+
+    pub fn local(root: impl Into<PathBuf>, limits: SizeLimits) -> CvStorage {
+        CvStorage::new(Arc::new(LocalStorage::new(root)), limits)
+    }
+
+    pub fn from_settings(settings: &Settings) -> CvStorage {
+        let mut storage = CvStorage::new(
+            backend::open(settings.storage.backend, settings),
+            SizeLimits {
+                document: settings.upload.max_file_size,
+                image: settings.upload.max_image_size,
+            },
+        );
+        storage.presign_expiry = match settings.storage.s3.presign_expiry_seconds {
+            0 => None,
+            seconds => Some(Duration::from_secs(seconds)),
+        };
+        storage
+    }
+
+    pub fn backend(&self) -> &dyn Storage {
+        self.backend.as_ref()
+    }
+
+    // Validate and write a CV to the quarantine, nothing is written when it is refused
+    pub fn store(&self, company_id: Uuid, bytes: &[u8]) -> Result<StoredCv, CvError> {
+        let format = validation::validate(bytes, self.limits)?;
+        let file_name = format!("{}/{}.{}", company_id, Uuid::new_v4(), format.extension());
+        self.backend.put(&self.quarantine_key(&file_name)?, bytes)?;
+        Ok(StoredCv {
+            file_name,
+            format,
+            size: bytes.len(),
+        })
+    }
+
+    // Storage key of a released CV, only names produced by `store` are accepted
+    pub fn key(&self, file_name: &str) -> Result<String, CvError> {
+        let (company, name) = file_name.split_once('/').ok_or(CvError::InvalidName)?;
+        let (id, extension) = name.split_once('.').ok_or(CvError::InvalidName)?;
+        let valid = Uuid::parse_str(company).is_ok_and(|uuid| uuid.to_string() == company)
+            && Uuid::parse_str(id).is_ok_and(|uuid| uuid.to_string() == id)
+            && CvFormat::from_extension(extension).is_some();
+        if !valid {
+            return Err(CvError::InvalidName);
+        }
+        Ok(file_name.to_string())
+    }
+
+    // Storage key of a CV written by `store` and not released yet
+    pub fn quarantine_key(&self, file_name: &str) -> Result<String, CvError> {
+        Ok(format!("{}/{}", QUARANTINE_DIRECTORY, self.key(file_name)?))
+    }
+
+    pub fn read_quarantined(&self, file_name: &str) -> Result<Vec<u8>, CvError> {
+        Ok(self.backend.get(&self.quarantine_key(file_name)?)?)
+    }
+
+    // Move a CV scanned clean out of the quarantine
+    pub fn release(&self, file_name: &str) -> Result<(), CvError> {
+        let quarantined = self.quarantine_key(file_name)?;
+        Ok(self.backend.rename(&quarantined, &self.key(file_name)?)?)
+    }
+
+    pub fn discard(&self, file_name: &str) -> Result<(), CvError> {
+        Ok(self.backend.delete(&self.quarantine_key(file_name)?)?)
+    }
+
+    // Released and quarantined copies, when the personal data of the candidate is erased
+    pub fn delete(&self, file_name: &str) -> Result<(), CvError> {
+        self.backend.delete(&self.quarantine_key(file_name)?)?;
+        Ok(self.backend.delete(&self.key(file_name)?)?)
+    }
+
+    // Released CV, whole
+    pub fn read(&self, file_name: &str) -> Result<Vec<u8>, CvError> {
+        Ok(self.backend.get(&self.key(file_name)?)?)
+    }
+
+    // Released CV, read as it is sent
+    pub fn open(&self, file_name: &str) -> Result<Box<dyn Read + Send>, CvError> {
+        Ok(self.backend.stream(&self.key(file_name)?)?)
+    }
+
+    // Presigned URL of a released CV, `None` when downloads go through the server
+    pub fn download_url(
+        &self,
+        file_name: &str,
+        download: &Download,
+    ) -> Result<Option<String>, CvError> {
+        match self.presign_expiry {
+            Some(expiry) => Ok(self
+                .backend
+                .presign(&self.key(file_name)?, download, expiry)?),
+            None => Ok(None),
+        }
+    }
+
+    // Scan a quarantined CV: released when clean, deleted when infected, and kept in quarantine,
+    // `pending`, when the scanner fails so that `platform-cv-admin scan-pending` retries it
+    pub fn scan(
+        &self,
+        file_name: &str,
+        content: &[u8],
+        scanner: &dyn Scanner,
+    ) -> Result<ScanOutcome, CvError> {
+        match scanner.scan(content) {
+            Ok(ScanVerdict::Clean) => {
+                self.release(file_name)?;
+                Ok(ScanOutcome {
+                    status: ScanStatus::Clean,
+                    signature: None,
+                })
+            }
+            Ok(ScanVerdict::Infected { signature }) => {
+                warn!("CV {} infected by {}, deleted", file_name, signature);
+                self.discard(file_name)?;
+                Ok(ScanOutcome {
+                    status: ScanStatus::Infected,
+                    signature: Some(signature),
+                })
+            }
+            Err(e) => {
+                warn!("CV {} left in quarantine: {}", file_name, e);
+                Ok(ScanOutcome {
+                    status: ScanStatus::Pending,
+                    signature: None,
+                })
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    fn storage(name: &str) -> (CvStorage, PathBuf) {
+        let root = std::env::temp_dir().join(format!("platform-cv-{}-{}", name, Uuid::new_v4()));
+        let storage = CvStorage::local(
+            &root,
+            SizeLimits {
+                document: 4096,
+                image: 1024,
+            },
+        );
+        (storage, root)
+    }
+
+    fn exists(storage: &CvStorage, key: &str) -> bool {
+        match storage.backend().get(key) {
+            Ok(_) => true,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => false,
+            Err(e) => panic!("{}", e),
+        }
+    }
+
+    // Fixed verdict, `None` for a scanner that can not be reached
+    struct Verdict(Option<ScanVerdict>);
+
+    impl Scanner for Verdict {
+        fn scan(&self, _content: &[u8]) -> Result<ScanVerdict, scanner::ScanError> {
+            self.0
+                .clone()
+                .ok_or_else(|| scanner::ScanError::Io(io::ErrorKind::TimedOut.into()))
+        }
+    }
+
+    #[test]
+    fn test_store_under_company_shard() {
+        let (storage, root) = storage("store");
+        let company = Uuid::new_v4();
+        let stored = storage.store(company, b"%PDF-1.4\n<< >>\n%%EOF").unwrap();
+
+        assert_eq!(stored.format, CvFormat::Pdf);
+        assert!(stored.file_name.starts_with(&format!("{}/", company)));
+        assert!(stored.file_name.ends_with(".pdf"));
+        // Quarantined until scanned
+        assert!(!exists(&storage, &stored.file_name));
+        storage.release(&stored.file_name).unwrap();
+        let mut released = Vec::new();
+        storage
+            .open(&stored.file_name)
+            .unwrap()
+            .read_to_end(&mut released)
+            .unwrap();
+        assert_eq!(released, b"%PDF-1.4\n<< >>\n%%EOF");
+        assert_eq!(
+            storage.backend().list().unwrap(),
+            vec![stored.file_name.clone()]
+        );
+
+        // A refused file leaves nothing behind
+        assert!(matches!(
+            storage.store(company, b"#!/bin/sh\nrm -rf /"),
+            Err(CvError::UnsupportedType)
+        ));
+        assert_eq!(storage.backend().list().unwrap().len(), 1);
+        // Local storage: no presigned URL
+        let download = Download {
+            file_name: "cv.pdf",
+            content_type: "application/pdf",
+        };
+        assert!(storage
+            .download_url(&stored.file_name, &download)
+            .unwrap()
+            .is_none());
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn test_scan_releases_discards_or_keeps() {
+        let (storage, root) = storage("scan");
+        let company = Uuid::new_v4();
+        let content = b"%PDF-1.4\n<< >>\n%%EOF";
+
+        let clean = storage.store(company, content).unwrap();
+        let outcome = storage
+            .scan(
+                &clean.file_name,
+                content,
+                &Verdict(Some(ScanVerdict::Clean)),
+            )
+            .unwrap();
+        assert_eq!(outcome.status, ScanStatus::Clean);
+        assert!(exists(&storage, &clean.file_name));
+        assert!(!exists(
+            &storage,
+            &storage.quarantine_key(&clean.file_name).unwrap()
+        ));
+
+        let infected = storage.store(company, content).unwrap();
+        let outcome = storage
+            .scan(
+                &infected.file_name,
+                content,
+                &Verdict(Some(ScanVerdict::Infected {
+                    signature: "Eicar-Signature".to_string(),
+                })),
+            )
+            .unwrap();
+        assert_eq!(outcome.status, ScanStatus::Infected);
+        assert_eq!(outcome.signature.as_deref(), Some("Eicar-Signature"));
+        assert!(!exists(
+            &storage,
+            &storage.quarantine_key(&infected.file_name).unwrap()
+        ));
+        assert!(!exists(&storage, &infected.file_name));
+
+        // Scanner down: kept in quarantine for a later scan
+        let pending = storage.store(company, content).unwrap();
+        let outcome = storage
+            .scan(&pending.file_name, content, &Verdict(None))
+            .unwrap();
+        assert_eq!(outcome.status, ScanStatus::Pending);
+        assert_eq!(
+            storage.read_quarantined(&pending.file_name).unwrap(),
+            content
+        );
+        assert!(!exists(&storage, &pending.file_name));
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn test_key_rejects_foreign_names() {
+        let (storage, _) = storage("key");
+        let company = Uuid::new_v4();
+        let id = Uuid::new_v4();
+        assert_eq!(
+            storage
+                .quarantine_key(&format!("{}/{}.docx", company, id))
+                .unwrap(),
+            format!("quarantine/{}/{}.docx", company, id)
+        );
+        for name in [
+            "../secret.key".to_string(),
+            format!("{}/../../{}.pdf", company, id),
+            format!("{}/{}.sh", company, id),
+            format!("{}/{}.pdf/..", company, id),
+            format!("/{}.pdf", id),
+            "cv.pdf".to_string(),
+        ] {
+            assert!(
+                matches!(storage.key(&name), Err(CvError::InvalidName)),
+                "{}",
+                name
+            );
+        }
+    }
+
+    #[actix_web::test]
+    async fn test_refusals_map_to_problem_details() {
+        use actix_web::{http::StatusCode, ResponseError};
+        assert_eq!(
+            AppError::from(CvError::TooLarge).status_code(),
+            StatusCode::PAYLOAD_TOO_LARGE
+        );
+        assert_eq!(
+            AppError::from(CvError::PdfJavaScript).status_code(),
+            StatusCode::UNPROCESSABLE_ENTITY
+        );
+        assert_eq!(
+            AppError::from(CvError::Infected).status_code(),
+            StatusCode::UNPROCESSABLE_ENTITY
+        );
+        assert_eq!(
+            AppError::from(CvError::Io(io::ErrorKind::PermissionDenied.into())).status_code(),
+            StatusCode::INTERNAL_SERVER_ERROR
+        );
+        assert_eq!(
+            AppError::from(CvError::Io(io::ErrorKind::NotFound.into())).status_code(),
+            StatusCode::NOT_FOUND
+        );
+    }
+}
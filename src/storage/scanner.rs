@@ -0,0 +1,226 @@
+// Malware scan of the uploaded CVs. `ClamdScanner` speaks the clamd `INSTREAM` protocol over TCP,
+// `NoopScanner` reports every file clean and is only meant for development
+use std::{
+    fmt,
+    io::{self, Read, Write},
+    net::{TcpStream, ToSocketAddrs},
+    sync::Arc,
+    time::Duration,
+};
+
+use crate::config::settings::{ScannerBackend, ScannerSettings};
+
+// Below the default `StreamMaxLength` chunking of clamd
+const CLAMD_CHUNK_SIZE: usize = 64 * 1024;
+// Longest reply read, clamd answers with a single line
+const CLAMD_MAX_REPLY_LENGTH: usize = 4096;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum ScanVerdict {
+    Clean,
+    Infected { signature: String },
+}
+
+#[derive(Debug)]
+pub enum ScanError {
+    // Scanner unreachable or too slow, the file stays in quarantine
+    Io(io::Error),
+    // Reply that is neither clean nor infected, e.g. "INSTREAM size limit exceeded. ERROR"
+    Response(String),
+}
+
+impl fmt::Display for ScanError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ScanError::Io(e) => write!(f, "scanner unavailable: {}", e),
+            ScanError::Response(reply) => write!(f, "unexpected scanner reply: {}", reply),
+        }
+    }
+}
+
+impl std::error::Error for ScanError {}
+
+impl From<io::Error> for ScanError {
+    fn from(error: io::Error) -> Self {
+        ScanError::Io(error)
+    }
+}
+
+// Blocking, callers run it on the blocking thread pool
+pub trait Scanner: Send + Sync {
+    fn scan(&self, content: &[u8]) -> Result<ScanVerdict, ScanError>;
+}
+
+pub struct NoopScanner;
+
+impl Scanner for NoopScanner {
+    fn scan(&self, _content: &[u8]) -> Result<ScanVerdict, ScanError> {
+        Ok(ScanVerdict::Clean)
+    }
+}
+
+pub struct ClamdScanner {
+    address: String,
+    timeout: Duration,
+}
+
+impl ClamdScanner {
+    pub fn new(address: impl Into<String>, timeout: Duration) -> ClamdScanner {
+        ClamdScanner {
+            address: address.into(),
+            timeout,
+        }
+    }
+
+    fn connect(&self) -> io::Result<TcpStream> {
+        let mut last_error = None;
+        for address in self.address.to_socket_addrs()? {
+            match TcpStream::connect_timeout(&address, self.timeout) {
+                Ok(stream) => {
+                    stream.set_read_timeout(Some(self.timeout))?;
+                    stream.set_write_timeout(Some(self.timeout))?;
+                    return Ok(stream);
+                }
+                Err(e) => last_error = Some(e),
+            }
+        }
+        Err(last_error.unwrap_or_else(|| {
+            io::Error::new(io::ErrorKind::NotFound, "clamd address did not resolve")
+        }))
+    }
+}
+
+// "stream: OK" or "stream: Eicar-Signature FOUND"
+fn parse_reply(reply: &str) -> Result<ScanVerdict, ScanError> {
+    let result = reply.strip_prefix("stream:").unwrap_or(reply).trim();
+    if result == "OK" {
+        return Ok(ScanVerdict::Clean);
+    }
+    match result.strip_suffix(" FOUND") {
+        Some(signature) if !signature.trim().is_empty() => Ok(ScanVerdict::Infected {
+            signature: signature.trim().to_string(),
+        }),
+        _ => Err(ScanError::Response(reply.to_string())),
+    }
+}
+
+impl Scanner for ClamdScanner {
+    fn scan(&self, content: &[u8]) -> Result<ScanVerdict, ScanError> {
+        let mut stream = self.connect()?;
+        // `z` commands and replies are terminated by a NUL byte
+        stream.write_all(b"zINSTREAM\0")?;
+        for chunk in content.chunks(CLAMD_CHUNK_SIZE) {
+            stream.write_all(&(chunk.len() as u32).to_be_bytes())?;
+            stream.write_all(chunk)?;
+        }
+        stream.write_all(&[0; 4])?;
+        stream.flush()?;
+
+        let mut reply = Vec::new();
+        let mut buffer = [0; 256];
+        while reply.len() < CLAMD_MAX_REPLY_LENGTH {
+            let read = stream.read(&mut buffer)?;
+            if read == 0 {
+                break;
+            }
+            reply.extend_from_slice(&buffer[..read]);
+            if reply.contains(&0) {
+                break;
+            }
+        }
+        let end = reply.iter().position(|&b| b == 0).unwrap_or(reply.len());
+        parse_reply(String::from_utf8_lossy(&reply[..end]).trim())
+    }
+}
+
+pub fn from_settings(settings: &ScannerSettings) -> Arc<dyn Scanner> {
+    match settings.backend {
+        ScannerBackend::None => Arc::new(NoopScanner),
+        ScannerBackend::Clamd => Arc::new(ClamdScanner::new(
+            &settings.clamd_address,
+            Duration::from_secs(settings.timeout_seconds),
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{net::TcpListener, thread};
+
+    // Stand-in for clamd: reads one INSTREAM request and answers with `reply`
+    fn fake_clamd(reply: &'static str) -> (String, thread::JoinHandle<Vec<u8>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap().to_string();
+        let handle = thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut command = [0; 10];
+            stream.read_exact(&mut command).unwrap();
+            assert_eq!(&command, b"zINSTREAM\0");
+            let mut content = Vec::new();
+            loop {
+                let mut length = [0; 4];
+                stream.read_exact(&mut length).unwrap();
+                let length = u32::from_be_bytes(length) as usize;
+                if length == 0 {
+                    break;
+                }
+                let mut chunk = vec![0; length];
+                stream.read_exact(&mut chunk).unwrap();
+                content.extend(chunk);
+            }
+            stream.write_all(reply.as_bytes()).unwrap();
+            stream.write_all(b"\0").unwrap();
+            content
+        });
+        (address, handle)
+    }
+
+    #[test]
+    fn test_clamd_instream() {
+        let content = vec![b'x'; CLAMD_CHUNK_SIZE * 2 + 10];
+        let (address, handle) = fake_clamd("stream: OK");
+        let scanner = ClamdScanner::new(address, Duration::from_secs(5));
+        assert_eq!(scanner.scan(&content).unwrap(), ScanVerdict::Clean);
+        // Sent in several chunks, received whole
+        assert_eq!(handle.join().unwrap(), content);
+
+        let (address, handle) = fake_clamd("stream: Win.Test.EICAR_HDB-1 FOUND");
+        let scanner = ClamdScanner::new(address, Duration::from_secs(5));
+        assert_eq!(
+            scanner.scan(b"X5O!P%@AP").unwrap(),
+            ScanVerdict::Infected {
+                signature: "Win.Test.EICAR_HDB-1".to_string()
+            }
+        );
+        handle.join().unwrap();
+
+        let (address, handle) = fake_clamd("INSTREAM size limit exceeded. ERROR");
+        let scanner = ClamdScanner::new(address, Duration::from_secs(5));
+        assert!(matches!(
+            scanner.scan(b"%PDF-1.4"),
+            Err(ScanError::Response(_))
+        ));
+        handle.join().unwrap();
+    }
+
+    #[test]
+    fn test_clamd_unreachable() {
+        // Bound then released, nothing listens there anymore
+        let address = TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap()
+            .to_string();
+        let scanner = ClamdScanner::new(address, Duration::from_secs(1));
+        assert!(matches!(scanner.scan(b"%PDF-1.4"), Err(ScanError::Io(_))));
+    }
+
+    #[test]
+    fn test_parse_reply() {
+        assert_eq!(parse_reply("stream: OK").unwrap(), ScanVerdict::Clean);
+        assert!(parse_reply("stream:  FOUND").is_err());
+        assert!(parse_reply("").is_err());
+        assert_eq!(NoopScanner.scan(b"anything").unwrap(), ScanVerdict::Clean);
+    }
+}
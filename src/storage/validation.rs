@@ -0,0 +1,366 @@
+// Content checks of uploaded CVs: the type comes from the magic bytes, never from the extension
+// or the content type sent by the client
+use std::io::Read;
+
+use flate2::read::ZlibDecoder;
+
+use crate::storage::CvError;
+
+const PDF_MAGIC: &[u8] = b"%PDF-";
+// The PDF header may be preceded by garbage, readers look for it in the first kilobyte
+const PDF_HEADER_WINDOW: usize = 1024;
+const ZIP_MAGIC: &[u8] = b"PK\x03\x04";
+const PNG_MAGIC: &[u8] = b"\x89PNG\r\n\x1a\n";
+const JPEG_MAGIC: &[u8] = b"\xff\xd8\xff";
+const ODT_MIMETYPE: &[u8] = b"application/vnd.oasis.opendocument.text";
+// Decompressed bytes inspected per upload, bounds the cost of a compression bomb
+pub(crate) const MAX_INFLATED_SIZE: u64 = 64 * 1024 * 1024;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum CvFormat {
+    Pdf,
+    Docx,
+    Odt,
+    Png,
+    Jpeg,
+    Webp,
+}
+
+impl CvFormat {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            CvFormat::Pdf => "pdf",
+            CvFormat::Docx => "docx",
+            CvFormat::Odt => "odt",
+            CvFormat::Png => "png",
+            CvFormat::Jpeg => "jpg",
+            CvFormat::Webp => "webp",
+        }
+    }
+
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            CvFormat::Pdf => "application/pdf",
+            CvFormat::Docx => {
+                "application/vnd.openxmlformats-officedocument.wordprocessingml.document"
+            }
+            CvFormat::Odt => "application/vnd.oasis.opendocument.text",
+            CvFormat::Png => "image/png",
+            CvFormat::Jpeg => "image/jpeg",
+            CvFormat::Webp => "image/webp",
+        }
+    }
+
+    pub fn from_extension(extension: &str) -> Option<CvFormat> {
+        [
+            CvFormat::Pdf,
+            CvFormat::Docx,
+            CvFormat::Odt,
+            CvFormat::Png,
+            CvFormat::Jpeg,
+            CvFormat::Webp,
+        ]
+        .into_iter()
+        .find(|format| format.extension() == extension)
+    }
+
+    // Format of a name written by `CvStorage::store`
+    pub fn from_file_name(file_name: &str) -> Option<CvFormat> {
+        let (_, extension) = file_name.rsplit_once('.')?;
+        CvFormat::from_extension(extension)
+    }
+
+    pub fn is_image(&self) -> bool {
+        matches!(self, CvFormat::Png | CvFormat::Jpeg | CvFormat::Webp)
+    }
+}
+
+// Size limits, in bytes
+#[derive(Clone, Copy, Debug)]
+pub struct SizeLimits {
+    pub document: usize,
+    pub image: usize,
+}
+
+fn contains(haystack: &[u8], needle: &[u8]) -> bool {
+    haystack
+        .windows(needle.len())
+        .any(|window| window == needle)
+}
+
+fn find(haystack: &[u8], needle: &[u8], from: usize) -> Option<usize> {
+    haystack
+        .get(from..)?
+        .windows(needle.len())
+        .position(|window| window == needle)
+        .map(|position| from + position)
+}
+
+// OpenDocument requires an uncompressed `mimetype` first entry, DOCX a `[Content_Types].xml`
+// and a `word/` part. Entry names are stored uncompressed in the zip headers
+fn sniff_zip(bytes: &[u8]) -> Option<CvFormat> {
+    let header = |offset: usize| u16::from_le_bytes([bytes[offset], bytes[offset + 1]]) as usize;
+    if bytes.len() >= 30 {
+        let name_end = 30 + header(26);
+        let data_start = name_end + header(28);
+        if bytes.get(30..name_end) == Some(b"mimetype".as_slice())
+            && bytes.get(data_start..data_start + ODT_MIMETYPE.len()) == Some(ODT_MIMETYPE)
+            && bytes.get(data_start + ODT_MIMETYPE.len()) != Some(&b'-')
+        {
+            return Some(CvFormat::Odt);
+        }
+    }
+    if contains(bytes, b"[Content_Types].xml") && contains(bytes, b"word/document.xml") {
+        return Some(CvFormat::Docx);
+    }
+    None
+}
+
+pub fn sniff(bytes: &[u8]) -> Option<CvFormat> {
+    if bytes.starts_with(ZIP_MAGIC) {
+        sniff_zip(bytes)
+    } else if bytes.starts_with(PNG_MAGIC) {
+        Some(CvFormat::Png)
+    } else if bytes.starts_with(JPEG_MAGIC) {
+        Some(CvFormat::Jpeg)
+    } else if bytes.len() >= 12 && &bytes[..4] == b"RIFF" && &bytes[8..12] == b"WEBP" {
+        Some(CvFormat::Webp)
+    } else if contains(&bytes[..bytes.len().min(PDF_HEADER_WINDOW)], PDF_MAGIC) {
+        Some(CvFormat::Pdf)
+    } else {
+        None
+    }
+}
+
+fn is_delimiter(byte: u8) -> bool {
+    byte.is_ascii_whitespace() || b"()<>[]{}/%".contains(&byte)
+}
+
+// PDF names of `content`, with the `#xx` escapes decoded (`/J#61vaScript` is `/JavaScript`)
+fn pdf_names(content: &[u8]) -> impl Iterator<Item = Vec<u8>> + '_ {
+    content
+        .iter()
+        .enumerate()
+        .filter(|(_, byte)| **byte == b'/')
+        .map(move |(start, _)| {
+            let mut name = Vec::new();
+            let mut i = start + 1;
+            while i < content.len() && !is_delimiter(content[i]) {
+                let hex = content
+                    .get(i + 1..i + 3)
+                    .and_then(|hex| std::str::from_utf8(hex).ok())
+                    .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+                match (content[i], hex) {
+                    (b'#', Some(decoded)) => {
+                        name.push(decoded);
+                        i += 3;
+                    }
+                    (byte, _) => {
+                        name.push(byte);
+                        i += 1;
+                    }
+                }
+            }
+            name
+        })
+}
+
+fn check_pdf_names(content: &[u8]) -> Result<(), CvError> {
+    for name in pdf_names(content) {
+        match name.as_slice() {
+            b"JavaScript" | b"JS" => return Err(CvError::PdfJavaScript),
+            b"Encrypt" => return Err(CvError::PdfEncrypted),
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+// Streams, object streams included, may hide the dictionaries: inflate them before looking
+fn inflated_streams(bytes: &[u8]) -> Vec<Vec<u8>> {
+    let mut streams = Vec::new();
+    let mut budget = MAX_INFLATED_SIZE;
+    let mut position = 0;
+    while let Some(keyword) = find(bytes, b"stream", position) {
+        let mut start = keyword + b"stream".len();
+        if bytes.get(start) == Some(&b'\r') {
+            start += 1;
+        }
+        if bytes.get(start) != Some(&b'\n') {
+            position = start;
+            continue;
+        }
+        start += 1;
+        let Some(end) = find(bytes, b"endstream", start) else {
+            break;
+        };
+        let mut inflated = Vec::new();
+        // Streams that are not zlib data are already covered by the scan of the raw bytes
+        let _ = ZlibDecoder::new(&bytes[start..end])
+            .take(budget)
+            .read_to_end(&mut inflated);
+        budget = budget.saturating_sub(inflated.len() as u64);
+        if !inflated.is_empty() {
+            streams.push(inflated);
+        }
+        if budget == 0 {
+            break;
+        }
+        position = end + b"endstream".len();
+    }
+    streams
+}
+
+pub fn inspect_pdf(bytes: &[u8]) -> Result<(), CvError> {
+    check_pdf_names(bytes)?;
+    for stream in inflated_streams(bytes) {
+        check_pdf_names(&stream)?;
+    }
+    Ok(())
+}
+
+// Format of an acceptable CV, or why it is refused
+pub fn validate(bytes: &[u8], limits: SizeLimits) -> Result<CvFormat, CvError> {
+    if bytes.is_empty() {
+        return Err(CvError::Empty);
+    }
+    if bytes.len() > limits.document.max(limits.image) {
+        return Err(CvError::TooLarge);
+    }
+    let format = sniff(bytes).ok_or(CvError::UnsupportedType)?;
+    let limit = if format.is_image() {
+        limits.image
+    } else {
+        limits.document
+    };
+    if bytes.len() > limit {
+        return Err(CvError::TooLarge);
+    }
+    if format == CvFormat::Pdf {
+        inspect_pdf(bytes)?;
+    }
+    Ok(format)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use flate2::{write::ZlibEncoder, Compression};
+    use std::io::Write;
+
+    const LIMITS: SizeLimits = SizeLimits {
+        document: 1024 * 1024,
+        image: 1024,
+    };
+
+    fn pdf(body: &[u8]) -> Vec<u8> {
+        [
+            b"%PDF-1.7\n1 0 obj\n".as_slice(),
+            body,
+            b"\nendobj\n%%EOF\n",
+        ]
+        .concat()
+    }
+
+    fn zip_entry(name: &str, data: &[u8]) -> Vec<u8> {
+        let mut entry = ZIP_MAGIC.to_vec();
+        entry.extend_from_slice(&[0; 22]);
+        entry.extend_from_slice(&(name.len() as u16).to_le_bytes());
+        entry.extend_from_slice(&0u16.to_le_bytes());
+        entry.extend_from_slice(name.as_bytes());
+        entry.extend_from_slice(data);
+        entry
+    }
+
+    #[test]
+    fn test_sniff_by_magic_bytes() {
+        assert_eq!(sniff(&pdf(b"<< >>")), Some(CvFormat::Pdf));
+        assert_eq!(
+            sniff(&[b"\x00\x00junk".as_slice(), &pdf(b"<< >>")].concat()),
+            Some(CvFormat::Pdf)
+        );
+        assert_eq!(
+            sniff(&zip_entry("mimetype", ODT_MIMETYPE)),
+            Some(CvFormat::Odt)
+        );
+        // An OpenDocument spreadsheet is not a CV
+        assert_eq!(
+            sniff(&zip_entry(
+                "mimetype",
+                b"application/vnd.oasis.opendocument.spreadsheet"
+            )),
+            None
+        );
+        assert_eq!(
+            sniff(&zip_entry(
+                "mimetype",
+                b"application/vnd.oasis.opendocument.text-template"
+            )),
+            None
+        );
+        let docx = [
+            zip_entry("[Content_Types].xml", b"<Types/>"),
+            zip_entry("word/document.xml", b"<w:document/>"),
+        ]
+        .concat();
+        assert_eq!(sniff(&docx), Some(CvFormat::Docx));
+        assert_eq!(sniff(&zip_entry("xl/workbook.xml", b"")), None);
+        assert_eq!(sniff(b"\x89PNG\r\n\x1a\n...."), Some(CvFormat::Png));
+        assert_eq!(sniff(b"\xff\xd8\xff\xe0...."), Some(CvFormat::Jpeg));
+        assert_eq!(sniff(b"RIFF\x10\x00\x00\x00WEBPVP8 "), Some(CvFormat::Webp));
+        // The extension or a text payload never decide
+        assert_eq!(sniff(b"<html><script>alert(1)</script></html>"), None);
+        assert_eq!(sniff(b"MZ\x90\x00"), None);
+    }
+
+    #[test]
+    fn test_size_limits() {
+        assert!(matches!(validate(b"", LIMITS), Err(CvError::Empty)));
+        let image = [PNG_MAGIC, &[0; 2048]].concat();
+        assert!(matches!(validate(&image, LIMITS), Err(CvError::TooLarge)));
+        // The document limit applies to PDF files
+        let document = pdf(&[b' '; 2048]);
+        assert_eq!(validate(&document, LIMITS).unwrap(), CvFormat::Pdf);
+        let huge = vec![0; 2 * 1024 * 1024];
+        assert!(matches!(validate(&huge, LIMITS), Err(CvError::TooLarge)));
+    }
+
+    #[test]
+    fn test_pdf_javascript_and_encryption_are_rejected() {
+        assert!(validate(&pdf(b"<< /Type /Catalog /Pages 2 0 R >>"), LIMITS).is_ok());
+        for body in [
+            b"<< /OpenAction << /S /JavaScript /JS (app.alert(1)) >> >>".as_slice(),
+            b"<< /Names << /J#61vaScript 3 0 R >> >>",
+            b"<< /AA << /O << /JS 4 0 R >> >> >>",
+        ] {
+            assert!(matches!(
+                validate(&pdf(body), LIMITS),
+                Err(CvError::PdfJavaScript)
+            ));
+        }
+        let encrypted = pdf(b"<< >>\ntrailer\n<< /Root 1 0 R /Encrypt 5 0 R >>");
+        assert!(matches!(
+            validate(&encrypted, LIMITS),
+            Err(CvError::PdfEncrypted)
+        ));
+
+        // Hidden in a compressed object stream
+        let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+        encoder
+            .write_all(b"<< /S /JavaScript /JS (app.alert(1)) >>")
+            .unwrap();
+        let compressed = encoder.finish().unwrap();
+        let body = [
+            b"<< /Type /ObjStm /Filter /FlateDecode >>\nstream\r\n".as_slice(),
+            &compressed,
+            b"\nendstream",
+        ]
+        .concat();
+        assert!(matches!(
+            validate(&pdf(&body), LIMITS),
+            Err(CvError::PdfJavaScript)
+        ));
+        // Names merely starting like a forbidden one are fine
+        assert!(validate(&pdf(b"<< /JSON 1 /Encrypted 2 >>"), LIMITS).is_ok());
+    }
+}
@@ -3,3 +3,18 @@ use askama::Template;
 #[derive(Template)]
 #[template(path = "homepage/index.html")]
 pub struct HomeTemplate {}
+
+#[derive(Template)]
+#[template(path = "error/index.html")]
+pub struct ErrorTemplate {
+    pub status: u16,
+    pub title: String,
+    pub detail: String,
+    pub correlation_id: Option<String>,
+}
+
+#[derive(Template)]
+#[template(path = "api_docs/index.html")]
+pub struct ApiDocsTemplate<'a> {
+    pub spec_url: &'a str,
+}
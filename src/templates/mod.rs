@@ -1 +1,13 @@
+use askama::Template;
+use tracing::{debug, debug_span};
+
 pub mod front_template;
+
+// Render `template` inside a span named after its type
+pub fn render<T: Template>(template: &T) -> askama::Result<String> {
+    debug_span!("template.render", template = std::any::type_name::<T>()).in_scope(|| {
+        let rendered = template.render();
+        debug!(success = rendered.is_ok(), "template rendered");
+        rendered
+    })
+}
@@ -0,0 +1,102 @@
+use actix_web::{dev::Payload, web, FromRequest, HttpRequest};
+use futures::future::LocalBoxFuture;
+
+use crate::{
+    config::{
+        db::{self, Pool},
+        signing_keys::SigningKeys,
+    },
+    constants,
+    error::AppError,
+    models::{
+        user::{RoleType, User},
+        user_session::UserSession,
+    },
+    utils::token_utils,
+};
+
+// Caller authenticated through a valid bearer token bound to an active session
+pub struct AuthenticatedUser {
+    pub user: User,
+    pub session: UserSession,
+}
+
+// Same as `AuthenticatedUser` without the 2FA policy, so that enrolment stays reachable
+pub struct SessionUser {
+    pub user: User,
+    pub session: UserSession,
+}
+
+impl AuthenticatedUser {
+    // SuperAdmin manages every user, Admin only the users of its own company
+    pub fn can_manage(&self, other: &User) -> bool {
+        match other.company_id {
+            Some(i_company) => self.can_manage_company(i_company),
+            None => self.user.role == RoleType::SuperAdmin,
+        }
+    }
+
+    // Same rule for the data of a company, e.g. its candidates
+    pub fn can_manage_company(&self, i_company: uuid::Uuid) -> bool {
+        match self.user.role {
+            RoleType::SuperAdmin => true,
+            RoleType::Admin => self.user.company_id == Some(i_company),
+            RoleType::User => false,
+        }
+    }
+}
+
+fn unauthorized() -> AppError {
+    AppError::Unauthorized {
+        error_message: constants::MESSAGE_INVALID_TOKEN.to_string(),
+    }
+}
+
+async fn authenticate(req: HttpRequest) -> Result<(User, UserSession), AppError> {
+    let missing_app_data = || AppError::InternalServerError {
+        error_message: "Pool or signing keys missing from the application data".to_string(),
+    };
+    let pool = req
+        .app_data::<web::Data<Pool>>()
+        .ok_or_else(missing_app_data)?;
+    let keys = req
+        .app_data::<web::Data<SigningKeys>>()
+        .ok_or_else(missing_app_data)?;
+    let token = token_utils::extract_bearer_token(&req).ok_or_else(unauthorized)?;
+    let token_data = token_utils::decode_token(token, keys).map_err(|_| unauthorized())?;
+    db::run(pool, move |conn| {
+        token_utils::verify_token(&token_data, conn)
+    })
+    .await
+}
+
+impl FromRequest for AuthenticatedUser {
+    type Error = AppError;
+    type Future = LocalBoxFuture<'static, Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, _: &mut Payload) -> Self::Future {
+        let req = req.clone();
+        Box::pin(async move {
+            let (user, session) = authenticate(req).await?;
+            if user.role.requires_two_factor() && !user.totp_enabled {
+                return Err(AppError::Forbidden {
+                    error_message: constants::MESSAGE_TWO_FACTOR_ENROLMENT_REQUIRED.to_string(),
+                });
+            }
+            Ok(AuthenticatedUser { user, session })
+        })
+    }
+}
+
+impl FromRequest for SessionUser {
+    type Error = AppError;
+    type Future = LocalBoxFuture<'static, Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, _: &mut Payload) -> Self::Future {
+        let req = req.clone();
+        Box::pin(async move {
+            let (user, session) = authenticate(req).await?;
+            Ok(SessionUser { user, session })
+        })
+    }
+}
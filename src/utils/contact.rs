@@ -0,0 +1,146 @@
+// Normalised emails, phone numbers and names, stored by the DTO validation and compared to find
+// the candidates who applied twice
+use std::sync::LazyLock;
+
+use regex::Regex;
+
+// Longest address accepted by the SMTP path limit
+const MAX_EMAIL_LENGTH: usize = 254;
+// E.164 numbers have at most 15 digits, the country code included
+const MAX_PHONE_DIGITS: usize = 15;
+
+// Dot-atom local part and a domain of at least two labels, checked once lowercased
+static EMAIL: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(
+        r"^[a-z0-9!#$%&'*+/=?^_`{|}~-]+(?:\.[a-z0-9!#$%&'*+/=?^_`{|}~-]+)*@(?:[a-z0-9](?:[a-z0-9-]{0,61}[a-z0-9])?\.)+[a-z0-9](?:[a-z0-9-]{0,61}[a-z0-9])?$",
+    )
+    .unwrap()
+});
+
+// Trimmed and lowercased, `None` when the syntax is not the one of an address
+pub fn normalize_email(address: &str) -> Option<String> {
+    let address = address.trim().to_lowercase();
+    (address.len() <= MAX_EMAIL_LENGTH && EMAIL.is_match(&address)).then_some(address)
+}
+
+// E.164 number, `+33612345678`. Numbers without a `+` or `00` prefix are French ones:
+// `06 12 34 56 78`, `+33 (0)6 12 34 56 78` and `0033 6 12 34 56 78` are the same number
+pub fn normalize_phone(number: &str) -> Option<String> {
+    let number = number.trim();
+    if !number
+        .chars()
+        .all(|c| c.is_ascii_digit() || matches!(c, ' ' | '.' | '-' | '/' | '(' | ')' | '+'))
+    {
+        return None;
+    }
+    let digits: String = number.chars().filter(char::is_ascii_digit).collect();
+    let french = |national: &str| {
+        let national = national.strip_prefix('0').unwrap_or(national);
+        (national.len() == 9 && !national.starts_with('0')).then(|| format!("+33{}", national))
+    };
+    let international = if number.starts_with('+') {
+        Some(digits.as_str())
+    } else {
+        digits.strip_prefix("00")
+    };
+    match international {
+        Some(international) => match international.strip_prefix("33") {
+            Some(national) => french(national),
+            None if (8..=MAX_PHONE_DIGITS).contains(&international.len())
+                && !international.starts_with('0') =>
+            {
+                Some(format!("+{}", international))
+            }
+            None => None,
+        },
+        None => digits.strip_prefix('0').and_then(french),
+    }
+}
+
+// Lowercased, without accents, for the comparisons with the headings, keywords and names
+pub fn fold(text: &str) -> String {
+    text.to_lowercase()
+        .chars()
+        .map(|c| match c {
+            'à' | 'â' | 'ä' => 'a',
+            'é' | 'è' | 'ê' | 'ë' => 'e',
+            'î' | 'ï' => 'i',
+            'ô' | 'ö' => 'o',
+            'ù' | 'û' | 'ü' => 'u',
+            'ç' => 'c',
+            '’' => '\'',
+            c => c,
+        })
+        .collect()
+}
+
+// Folded words of the full name in alphabetical order, "DUPONT Jean-Pierre" and
+// "Jean Pierre Dupont" are both `dupont jean pierre`
+pub fn name_key(firstname: &str, lastname: &str) -> String {
+    let full = fold(&format!("{} {}", firstname, lastname));
+    let mut words: Vec<&str> = full
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .collect();
+    words.sort_unstable();
+    words.join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_normalize_email() {
+        assert_eq!(
+            normalize_email(" Jane.Doe@Example.COM ").as_deref(),
+            Some("jane.doe@example.com")
+        );
+        assert_eq!(normalize_email("jane.doe"), None);
+        assert_eq!(normalize_email("@example.com"), None);
+        assert_eq!(normalize_email("jane@localhost"), None);
+        assert_eq!(normalize_email("jane..doe@example.com"), None);
+        assert_eq!(normalize_email("jane doe@example.com"), None);
+        assert_eq!(normalize_email("jane@example-.com"), None);
+        assert_eq!(normalize_email("jane@@example.com"), None);
+        assert_eq!(
+            normalize_email("o'neil+cv@mail.example.co.uk").as_deref(),
+            Some("o'neil+cv@mail.example.co.uk")
+        );
+        assert_eq!(normalize_email(""), None);
+    }
+
+    #[test]
+    fn test_normalize_phone() {
+        for number in [
+            "06 12 34 56 78",
+            "06.12.34.56.78",
+            "+33 6 12 34 56 78",
+            "+33 (0)6 12 34 56 78",
+            "0033612345678",
+        ] {
+            assert_eq!(normalize_phone(number).as_deref(), Some("+33612345678"));
+        }
+        assert_eq!(
+            normalize_phone("+44 20 7946 0958").as_deref(),
+            Some("+442079460958")
+        );
+        assert_eq!(
+            normalize_phone("0044 20 7946 0958").as_deref(),
+            Some("+442079460958")
+        );
+        assert_eq!(normalize_phone("+33 6 12"), None);
+        assert_eq!(normalize_phone("612345678"), None);
+        assert_eq!(normalize_phone("06 12 34 56 78 ext"), None);
+        assert_eq!(normalize_phone("+1234567890123456"), None);
+        assert_eq!(normalize_phone(""), None);
+    }
+
+    #[test]
+    fn test_name_key() {
+        assert_eq!(name_key("Jean-Pierre", "DUPONT"), "dupont jean pierre");
+        assert_eq!(name_key("Dupont", "Jean Pierre"), "dupont jean pierre");
+        assert_eq!(name_key("Hélène", "d'Aubigné"), "aubigne d helene");
+        assert_eq!(name_key(" ", ""), "");
+    }
+}
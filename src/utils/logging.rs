@@ -0,0 +1,204 @@
+// Structured logging: JSON lines on stdout, one span per request carrying its `X-Request-Id`
+use std::{
+    env,
+    io::{self, Write},
+    sync::LazyLock,
+    time::Instant,
+};
+
+use actix_web::{
+    body::MessageBody,
+    dev::{ServiceRequest, ServiceResponse},
+    http::header::{HeaderName, HeaderValue},
+    middleware::Next,
+    Error,
+};
+use regex::Regex;
+use tracing::{info, info_span, Instrument};
+use tracing_subscriber::{fmt::MakeWriter, EnvFilter};
+use uuid::Uuid;
+
+pub const REQUEST_ID_HEADER: HeaderName = HeaderName::from_static("x-request-id");
+// Filter used when RUST_LOG is not set
+const DEFAULT_LOG_FILTER: &str = "info";
+// Longest request id accepted from a client, longer ones are replaced
+const MAX_REQUEST_ID_LENGTH: usize = 128;
+
+static EMAIL: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"[A-Za-z0-9._%+-]+@[A-Za-z0-9.-]+\.[A-Za-z]{2,}").unwrap());
+// French numbers (`06 12 34 56 78`, `+33 6 12 34 56 78`) and other E.164 numbers
+static PHONE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"(?:\+33[\s.-]?|\b0)[1-9](?:[\s.-]?\d{2}){4}\b|\+[1-9]\d{7,14}\b").unwrap()
+});
+
+// Mask the emails and phone numbers of a log line
+pub fn redact(line: &str) -> String {
+    let line = EMAIL.replace_all(line, "[email]");
+    PHONE.replace_all(&line, "[phone]").into_owned()
+}
+
+// Writer redacting every line before it is written to stdout, or stderr for the admin CLI
+#[derive(Clone, Copy)]
+pub struct RedactingWriter {
+    stderr: bool,
+}
+
+impl RedactingWriter {
+    pub const STDOUT: RedactingWriter = RedactingWriter { stderr: false };
+    // Keeps stdout for the output of the command
+    pub const STDERR: RedactingWriter = RedactingWriter { stderr: true };
+}
+
+impl Write for RedactingWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let line = redact(&String::from_utf8_lossy(buf));
+        if self.stderr {
+            io::stderr().write_all(line.as_bytes())?;
+        } else {
+            io::stdout().write_all(line.as_bytes())?;
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        if self.stderr {
+            io::stderr().flush()
+        } else {
+            io::stdout().flush()
+        }
+    }
+}
+
+impl<'a> MakeWriter<'a> for RedactingWriter {
+    type Writer = RedactingWriter;
+
+    fn make_writer(&'a self) -> Self::Writer {
+        *self
+    }
+}
+
+pub fn init() {
+    init_with_writer(RedactingWriter::STDOUT);
+}
+
+// Levels come from RUST_LOG (e.g. `info,platform_cv=debug`), LOG_FORMAT=pretty for local development
+pub fn init_with_writer(writer: RedactingWriter) {
+    let filter =
+        EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new(DEFAULT_LOG_FILTER));
+    let builder = tracing_subscriber::fmt()
+        .with_env_filter(filter)
+        .with_writer(writer);
+    if env::var("LOG_FORMAT").is_ok_and(|format| format == "pretty") {
+        builder.init();
+    } else {
+        builder
+            .json()
+            .with_current_span(true)
+            .with_span_list(false)
+            .init();
+    }
+}
+
+fn request_id(req: &ServiceRequest) -> String {
+    req.headers()
+        .get(&REQUEST_ID_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .map(str::trim)
+        .filter(|id| {
+            !id.is_empty()
+                && id.len() <= MAX_REQUEST_ID_LENGTH
+                && id
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || "-_.:".contains(c))
+        })
+        .map(str::to_string)
+        .unwrap_or_else(|| Uuid::new_v4().to_string())
+}
+
+// Middleware opening the request span, logging its outcome and echoing `X-Request-Id`
+pub async fn trace_requests(
+    req: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<impl MessageBody>, Error> {
+    let id = request_id(&req);
+    let span = info_span!(
+        "http_request",
+        request_id = %id,
+        method = %req.method(),
+        path = %req.path(),
+    );
+    let started = Instant::now();
+    let res = next.call(req).instrument(span.clone()).await;
+    // Whole microseconds, a float could be mistaken for a phone number by `redact`
+    let elapsed_us = started.elapsed().as_micros() as u64;
+    let _entered = span.enter();
+    match res {
+        Ok(mut res) => {
+            info!(
+                status = res.status().as_u16(),
+                elapsed_us, "request completed"
+            );
+            if let Ok(value) = HeaderValue::from_str(&id) {
+                res.headers_mut().insert(REQUEST_ID_HEADER, value);
+            }
+            Ok(res)
+        }
+        Err(error) => {
+            let status = error.as_response_error().status_code();
+            info!(status = status.as_u16(), elapsed_us, "request failed");
+            Err(error)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::{middleware::from_fn, test, web, App, HttpResponse};
+
+    #[actix_web::test]
+    async fn test_redact_emails_and_phones() {
+        assert_eq!(
+            redact(r#"{"message":"login of jane.doe+cv@example.fr failed"}"#),
+            r#"{"message":"login of [email] failed"}"#
+        );
+        assert_eq!(
+            redact("call 06 12 34 56 78 or +33 6 12 34 56 78 or 0612345678 or +14155552671"),
+            "call [phone] or [phone] or [phone] or [phone]"
+        );
+        // Ids and timestamps are kept
+        let line = "session 3f2b0612-3456-7890-abcd-ef0123456789 at 2026-10-19T10:00:00";
+        assert_eq!(redact(line), line);
+    }
+
+    #[actix_web::test]
+    async fn test_request_id_generated_or_propagated() {
+        let app = test::init_service(
+            App::new()
+                .wrap(from_fn(trace_requests))
+                .route("/", web::get().to(HttpResponse::Ok)),
+        )
+        .await;
+
+        let resp = test::TestRequest::get()
+            .uri("/")
+            .insert_header((REQUEST_ID_HEADER, "abc-123"))
+            .send_request(&app)
+            .await;
+        assert_eq!(resp.headers().get(REQUEST_ID_HEADER).unwrap(), "abc-123");
+
+        // Invalid ids are replaced by a generated one
+        let resp = test::TestRequest::get()
+            .uri("/")
+            .insert_header((REQUEST_ID_HEADER, "bad id"))
+            .send_request(&app)
+            .await;
+        let id = resp
+            .headers()
+            .get(REQUEST_ID_HEADER)
+            .unwrap()
+            .to_str()
+            .unwrap();
+        assert!(Uuid::parse_str(id).is_ok());
+    }
+}
@@ -0,0 +1,262 @@
+// Prometheus metrics, exposed in the text format on `GET /metrics`
+use std::sync::LazyLock;
+
+use actix_web::{
+    body::MessageBody,
+    dev::{ServiceRequest, ServiceResponse},
+    middleware::Next,
+    Error,
+};
+use diesel::r2d2::{
+    event::{CheckoutEvent, TimeoutEvent},
+    HandleEvent,
+};
+use prometheus::{
+    Encoder, Histogram, HistogramOpts, HistogramVec, IntCounter, IntCounterVec, IntGauge, Opts,
+    Registry, TextEncoder,
+};
+use uuid::Uuid;
+
+use crate::config::db::Pool;
+
+const NAMESPACE: &str = "platform_cv";
+// Label of requests answered by the fallback route, keeps the cardinality bounded
+const UNMATCHED_ROUTE: &str = "unmatched";
+// Label of events not tied to a company (super admins, unknown users)
+const NO_COMPANY: &str = "none";
+
+pub struct Metrics {
+    registry: Registry,
+    http_requests: IntCounterVec,
+    http_request_duration: HistogramVec,
+    db_pool_connections: IntGauge,
+    db_pool_idle_connections: IntGauge,
+    db_pool_wait: Histogram,
+    db_pool_timeouts: IntCounter,
+    applications_received: IntCounterVec,
+    offers_published: IntCounterVec,
+    logins: IntCounterVec,
+}
+
+pub static METRICS: LazyLock<Metrics> = LazyLock::new(Metrics::new);
+
+impl Metrics {
+    fn new() -> Metrics {
+        let registry = Registry::new_custom(Some(NAMESPACE.to_string()), None)
+            .expect("Invalid metrics namespace");
+        let metrics = Metrics {
+            http_requests: IntCounterVec::new(
+                Opts::new("http_requests_total", "HTTP requests by route and status"),
+                &["method", "route", "status"],
+            )
+            .unwrap(),
+            http_request_duration: HistogramVec::new(
+                HistogramOpts::new(
+                    "http_request_duration_seconds",
+                    "HTTP request latency by route",
+                ),
+                &["method", "route"],
+            )
+            .unwrap(),
+            db_pool_connections: IntGauge::new(
+                "db_pool_connections",
+                "Connections opened by the database pool",
+            )
+            .unwrap(),
+            db_pool_idle_connections: IntGauge::new(
+                "db_pool_idle_connections",
+                "Idle connections of the database pool",
+            )
+            .unwrap(),
+            db_pool_wait: Histogram::with_opts(
+                HistogramOpts::new(
+                    "db_pool_wait_seconds",
+                    "Time spent waiting for a database connection",
+                )
+                .buckets(vec![
+                    0.0005, 0.001, 0.005, 0.01, 0.05, 0.1, 0.5, 1.0, 5.0, 30.0,
+                ]),
+            )
+            .unwrap(),
+            db_pool_timeouts: IntCounter::new(
+                "db_pool_timeouts_total",
+                "Checkouts of a database connection that timed out",
+            )
+            .unwrap(),
+            applications_received: IntCounterVec::new(
+                Opts::new(
+                    "applications_received_total",
+                    "Applications received by company",
+                ),
+                &["company"],
+            )
+            .unwrap(),
+            offers_published: IntCounterVec::new(
+                Opts::new("offers_published_total", "Job offers published by company"),
+                &["company"],
+            )
+            .unwrap(),
+            logins: IntCounterVec::new(
+                Opts::new("logins_total", "Login attempts by company and outcome"),
+                &["company", "outcome"],
+            )
+            .unwrap(),
+            registry,
+        };
+        metrics.register();
+        metrics
+    }
+
+    fn register(&self) {
+        let collectors: [Box<dyn prometheus::core::Collector>; 9] = [
+            Box::new(self.http_requests.clone()),
+            Box::new(self.http_request_duration.clone()),
+            Box::new(self.db_pool_connections.clone()),
+            Box::new(self.db_pool_idle_connections.clone()),
+            Box::new(self.db_pool_wait.clone()),
+            Box::new(self.db_pool_timeouts.clone()),
+            Box::new(self.applications_received.clone()),
+            Box::new(self.offers_published.clone()),
+            Box::new(self.logins.clone()),
+        ];
+        for collector in collectors {
+            self.registry
+                .register(collector)
+                .expect("Metric registered twice");
+        }
+    }
+
+    // Snapshot of the pool taken at scrape time
+    pub fn observe_pool(&self, pool: &Pool) {
+        let state = pool.state();
+        self.db_pool_connections.set(state.connections as i64);
+        self.db_pool_idle_connections
+            .set(state.idle_connections as i64);
+    }
+
+    // The business counters are incremented by the handlers once the change is committed
+    pub fn record_application(&self, company: Uuid) {
+        self.applications_received
+            .with_label_values(&[&company.to_string()])
+            .inc();
+    }
+
+    pub fn record_offer_published(&self, company: Uuid) {
+        self.offers_published
+            .with_label_values(&[&company.to_string()])
+            .inc();
+    }
+
+    pub fn record_login(&self, company: Option<Uuid>, success: bool) {
+        let company = company.map_or_else(|| NO_COMPANY.to_string(), |id| id.to_string());
+        let outcome = if success { "success" } else { "failure" };
+        self.logins.with_label_values(&[&company, outcome]).inc();
+    }
+
+    pub fn render(&self) -> String {
+        let mut buffer = Vec::new();
+        TextEncoder::new()
+            .encode(&self.registry.gather(), &mut buffer)
+            .expect("Failed to encode metrics");
+        String::from_utf8(buffer).expect("Metrics are not valid UTF-8")
+    }
+}
+
+// Middleware counting every request under its route pattern, e.g. `/api/auth/sessions/{id}`
+pub async fn track_requests(
+    req: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<impl MessageBody>, Error> {
+    let method = req.method().to_string();
+    let route = req
+        .match_pattern()
+        .unwrap_or_else(|| UNMATCHED_ROUTE.to_string());
+    let timer = METRICS
+        .http_request_duration
+        .with_label_values(&[&method, &route])
+        .start_timer();
+    let res = next.call(req).await;
+    timer.observe_duration();
+    // Handler errors are turned into a response further up, count them with their status
+    let status = match &res {
+        Ok(res) => res.status(),
+        Err(error) => error.as_response_error().status_code(),
+    };
+    METRICS
+        .http_requests
+        .with_label_values(&[&method, &route, status.as_str()])
+        .inc();
+    res
+}
+
+// Records how long checkouts wait for a connection
+#[derive(Debug)]
+pub struct PoolEventHandler;
+
+impl HandleEvent for PoolEventHandler {
+    fn handle_checkout(&self, event: CheckoutEvent) {
+        METRICS.db_pool_wait.observe(event.duration().as_secs_f64());
+    }
+
+    fn handle_timeout(&self, _event: TimeoutEvent) {
+        METRICS.db_pool_timeouts.inc();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::{middleware::from_fn, test, web, App, HttpResponse};
+
+    #[actix_web::test]
+    async fn test_requests_are_labelled_by_route_pattern() {
+        let app = test::init_service(
+            App::new()
+                .wrap(from_fn(track_requests))
+                .route(
+                    "/metrics-test/{id}",
+                    web::get().to(|| async { HttpResponse::Ok().finish() }),
+                )
+                .default_service(web::to(|| async { HttpResponse::NotFound().finish() })),
+        )
+        .await;
+        for uri in ["/metrics-test/1", "/metrics-test/2", "/metrics-test"] {
+            test::call_service(&app, test::TestRequest::get().uri(uri).to_request()).await;
+        }
+
+        let output = METRICS.render();
+        assert!(output.contains(
+            "platform_cv_http_requests_total{method=\"GET\",route=\"/metrics-test/{id}\",status=\"200\"} 2"
+        ));
+        assert!(output.contains("route=\"unmatched\",status=\"404\""));
+        assert!(output.contains(
+            "platform_cv_http_request_duration_seconds_count{method=\"GET\",route=\"/metrics-test/{id}\"} 2"
+        ));
+    }
+
+    #[actix_web::test]
+    async fn test_business_counters_by_company() {
+        let company = Uuid::new_v4();
+        METRICS.record_application(company);
+        METRICS.record_application(company);
+        METRICS.record_offer_published(company);
+        METRICS.record_login(Some(company), true);
+        METRICS.record_login(Some(company), false);
+        METRICS.record_login(None, false);
+
+        let output = METRICS.render();
+        assert!(output.contains(&format!(
+            "platform_cv_applications_received_total{{company=\"{}\"}} 2",
+            company
+        )));
+        assert!(output.contains(&format!(
+            "platform_cv_offers_published_total{{company=\"{}\"}} 1",
+            company
+        )));
+        assert!(output.contains(&format!(
+            "platform_cv_logins_total{{company=\"{}\",outcome=\"success\"}} 1",
+            company
+        )));
+        assert!(output.contains("platform_cv_logins_total{company=\"none\",outcome=\"failure\"}"));
+    }
+}
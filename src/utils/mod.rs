@@ -0,0 +1,7 @@
+pub mod auth;
+pub mod contact;
+pub mod logging;
+pub mod metrics;
+pub mod token_utils;
+pub mod totp;
+pub mod validation;
@@ -0,0 +1,61 @@
+use actix_web::HttpRequest;
+use jsonwebtoken::TokenData;
+
+use crate::{
+    config::{db::Connection, signing_keys::SigningKeys},
+    constants,
+    error::AppError,
+    models::{
+        user::User,
+        user_session::{ClientInfo, UserSession},
+        user_token::UserToken,
+    },
+};
+
+pub fn decode_token(
+    token: String,
+    keys: &SigningKeys,
+) -> jsonwebtoken::errors::Result<TokenData<UserToken>> {
+    keys.decode::<UserToken>(&token)
+}
+
+pub fn verify_token(
+    token_data: &TokenData<UserToken>,
+    conn: &mut Connection,
+) -> Result<(User, UserSession), AppError> {
+    match User::find_session_by_token(&token_data.claims, conn) {
+        Ok((user, session)) => {
+            let _ = UserSession::touch(session.id, conn);
+            Ok((user, session))
+        }
+        Err(diesel::result::Error::NotFound) => Err(AppError::Unauthorized {
+            error_message: constants::MESSAGE_INVALID_TOKEN.to_string(),
+        }),
+        Err(e) => Err(e.into()),
+    }
+}
+
+// Extract the bearer token of the `Authorization` header
+pub fn extract_bearer_token(req: &HttpRequest) -> Option<String> {
+    let header = req.headers().get(constants::AUTHORIZATION)?.to_str().ok()?;
+    let (scheme, token) = header.split_once(' ')?;
+    if scheme.eq_ignore_ascii_case(constants::BEARER) && !token.trim().is_empty() {
+        Some(token.trim().to_string())
+    } else {
+        None
+    }
+}
+
+pub fn client_info(req: &HttpRequest) -> ClientInfo {
+    ClientInfo {
+        user_agent: req
+            .headers()
+            .get(actix_web::http::header::USER_AGENT)
+            .and_then(|value| value.to_str().ok())
+            .map(str::to_string),
+        ip_address: req
+            .connection_info()
+            .realip_remote_addr()
+            .map(str::to_string),
+    }
+}
@@ -0,0 +1,198 @@
+// Time-based one-time passwords (RFC 6238) on top of HOTP (RFC 4226), HMAC-SHA1
+use data_encoding::BASE32_NOPAD;
+use hmac::{Hmac, Mac};
+use rand::{distributions::Alphanumeric, Rng, RngCore};
+use sha1::Sha1;
+use sha2::{Digest, Sha256};
+
+pub const TOTP_DIGITS: u32 = 6;
+pub const TOTP_PERIOD: i64 = 30;
+// Accepted clock drift, in periods, on each side of the current one
+pub const TOTP_SKEW: i64 = 1;
+pub const SECRET_LENGTH: usize = 20;
+pub const RECOVERY_CODES_COUNT: usize = 10;
+
+pub fn generate_secret() -> String {
+    let mut secret = [0u8; SECRET_LENGTH];
+    rand::thread_rng().fill_bytes(&mut secret);
+    BASE32_NOPAD.encode(&secret)
+}
+
+pub fn otpauth_uri(secret: &str, account: &str, issuer: &str) -> String {
+    format!(
+        "otpauth://totp/{issuer}:{account}?secret={secret}&issuer={issuer}&algorithm=SHA1&digits={TOTP_DIGITS}&period={TOTP_PERIOD}",
+        issuer = percent_encode(issuer),
+        account = percent_encode(account),
+        secret = secret,
+    )
+}
+
+pub fn time_step(timestamp: i64) -> i64 {
+    timestamp.div_euclid(TOTP_PERIOD)
+}
+
+pub fn hotp(key: &[u8], counter: u64, digits: u32) -> String {
+    let mut mac = Hmac::<Sha1>::new_from_slice(key).expect("HMAC accepts keys of any size");
+    mac.update(&counter.to_be_bytes());
+    let hash = mac.finalize().into_bytes();
+
+    let offset = (hash[hash.len() - 1] & 0x0f) as usize;
+    let binary = ((hash[offset] as u32 & 0x7f) << 24)
+        | ((hash[offset + 1] as u32) << 16)
+        | ((hash[offset + 2] as u32) << 8)
+        | (hash[offset + 3] as u32);
+
+    format!(
+        "{:0width$}",
+        binary % 10u32.pow(digits),
+        width = digits as usize
+    )
+}
+
+// Return the time step matched by `code`, steps up to `last_step` are refused to prevent replays
+pub fn verify_code(
+    secret: &str,
+    code: &str,
+    timestamp: i64,
+    last_step: Option<i64>,
+) -> Option<i64> {
+    let code = code.trim().replace(' ', "");
+    if code.len() != TOTP_DIGITS as usize || !code.chars().all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+    let key = BASE32_NOPAD.decode(secret.as_bytes()).ok()?;
+    let current = time_step(timestamp);
+    (current - TOTP_SKEW..=current + TOTP_SKEW)
+        .filter(|step| *step >= 0 && last_step.is_none_or(|last| *step > last))
+        .find(|step| {
+            constant_time_eq(
+                hotp(&key, *step as u64, TOTP_DIGITS).as_bytes(),
+                code.as_bytes(),
+            )
+        })
+}
+
+pub fn generate_recovery_codes() -> Vec<String> {
+    (0..RECOVERY_CODES_COUNT)
+        .map(|_| {
+            let raw: String = rand::thread_rng()
+                .sample_iter(&Alphanumeric)
+                .take(10)
+                .map(|c| (c as char).to_ascii_lowercase())
+                .collect();
+            format!("{}-{}", &raw[..5], &raw[5..])
+        })
+        .collect()
+}
+
+// Recovery codes are random and high entropy, a plain SHA-256 digest is enough to store them
+pub fn hash_recovery_code(code: &str) -> String {
+    let normalized = code.trim().to_ascii_lowercase();
+    data_encoding::HEXLOWER.encode(&Sha256::digest(normalized.as_bytes()))
+}
+
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+fn percent_encode(value: &str) -> String {
+    value
+        .bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => {
+                (b as char).to_string()
+            }
+            _ => format!("%{:02X}", b),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // RFC 6238 appendix B, SHA1 shared secret
+    const RFC_SECRET: &[u8] = b"12345678901234567890";
+
+    fn generate_code(secret: &str, timestamp: i64) -> Option<String> {
+        let key = BASE32_NOPAD.decode(secret.as_bytes()).ok()?;
+        Some(hotp(&key, time_step(timestamp) as u64, TOTP_DIGITS))
+    }
+
+    #[test]
+    fn test_hotp_rfc6238_vectors() {
+        let vectors = [
+            (59, "94287082"),
+            (1111111109, "07081804"),
+            (1111111111, "14050471"),
+            (1234567890, "89005924"),
+            (2000000000, "69279037"),
+            (20000000000, "65353130"),
+        ];
+        for (timestamp, expected) in vectors {
+            assert_eq!(hotp(RFC_SECRET, time_step(timestamp) as u64, 8), expected);
+        }
+    }
+
+    #[test]
+    fn test_generate_code_six_digits() {
+        let secret = BASE32_NOPAD.encode(RFC_SECRET);
+        assert_eq!(generate_code(&secret, 59).unwrap(), "287082");
+        assert_eq!(generate_code(&secret, 1111111109).unwrap(), "081804");
+        assert_eq!(generate_code(&secret, 1234567890).unwrap(), "005924");
+    }
+
+    #[test]
+    fn test_verify_code_accepts_skew_and_rejects_replay() {
+        let secret = BASE32_NOPAD.encode(RFC_SECRET);
+        let now = 1111111111;
+        let previous = generate_code(&secret, now - TOTP_PERIOD).unwrap();
+        let current = generate_code(&secret, now).unwrap();
+
+        assert_eq!(
+            verify_code(&secret, &previous, now, None),
+            Some(time_step(now) - 1)
+        );
+        assert_eq!(
+            verify_code(&secret, &current, now, None),
+            Some(time_step(now))
+        );
+        assert_eq!(
+            verify_code(&secret, &current, now, Some(time_step(now))),
+            None
+        );
+        assert_eq!(
+            verify_code(&secret, &current, now + 3 * TOTP_PERIOD, None),
+            None
+        );
+        assert_eq!(verify_code(&secret, "12a456", now, None), None);
+    }
+
+    #[test]
+    fn test_otpauth_uri() {
+        assert_eq!(
+            otpauth_uri("JBSWY3DPEHPK3PXP", "super admin@mail.com", "Platform CV"),
+            "otpauth://totp/Platform%20CV:super%20admin%40mail.com?secret=JBSWY3DPEHPK3PXP&issuer=Platform%20CV&algorithm=SHA1&digits=6&period=30"
+        );
+    }
+
+    #[test]
+    fn test_recovery_codes() {
+        let codes = generate_recovery_codes();
+        assert_eq!(codes.len(), RECOVERY_CODES_COUNT);
+        assert!(codes.iter().all(|code| code.len() == 11));
+        assert_eq!(
+            hash_recovery_code(&codes[0].to_uppercase()),
+            hash_recovery_code(&codes[0])
+        );
+    }
+
+    #[test]
+    fn test_generate_secret_is_valid_base32() {
+        let secret = generate_secret();
+        assert_eq!(
+            BASE32_NOPAD.decode(secret.as_bytes()).unwrap().len(),
+            SECRET_LENGTH
+        );
+    }
+}
@@ -0,0 +1,234 @@
+// Checks of the DTOs received from the API, the admin CLI, the seeds and the data imports, before
+// they are stored: texts are trimmed and capped, emails lowercased and phone numbers stored in E.164
+use crate::{
+    admin::data::UserRecord,
+    constants,
+    error::FieldError,
+    models::{
+        candidate::{Candidate, CandidateDTO},
+        user::UserDTO,
+    },
+    utils::contact::{normalize_email, normalize_phone},
+};
+
+// Characters, not bytes
+pub const MAX_NAME_LENGTH: usize = 100;
+pub const MAX_USERNAME_LENGTH: usize = 64;
+pub const MAX_MOTIVATION_LENGTH: usize = 5000;
+
+pub trait Validate: Sized {
+    // The DTO with its fields normalised, or an error for every invalid field
+    fn validate(self) -> Result<Self, Vec<FieldError>>;
+}
+
+// Errors collected field by field, each check returns the normalised value
+#[derive(Default)]
+pub struct Checks {
+    errors: Vec<FieldError>,
+}
+
+impl Checks {
+    fn fail(&mut self, field: &str, message: String) {
+        self.errors.push(FieldError {
+            field: field.to_string(),
+            message,
+        });
+    }
+
+    // Trimmed, blank only when not `required`
+    pub fn text(&mut self, field: &str, value: String, max: usize, required: bool) -> String {
+        let value = value.trim().to_string();
+        if required && value.is_empty() {
+            self.fail(field, constants::MESSAGE_FIELD_REQUIRED.to_string());
+        } else if value.chars().count() > max {
+            self.fail(
+                field,
+                format!(
+                    "{} ({} characters at most)",
+                    constants::MESSAGE_FIELD_TOO_LONG,
+                    max
+                ),
+            );
+        }
+        value
+    }
+
+    pub fn email(&mut self, field: &str, value: String, required: bool) -> String {
+        if value.trim().is_empty() {
+            if required {
+                self.fail(field, constants::MESSAGE_FIELD_REQUIRED.to_string());
+            }
+            return String::new();
+        }
+        normalize_email(&value).unwrap_or_else(|| {
+            self.fail(field, constants::MESSAGE_INVALID_EMAIL.to_string());
+            value
+        })
+    }
+
+    pub fn phone(&mut self, field: &str, value: String, required: bool) -> String {
+        if value.trim().is_empty() {
+            if required {
+                self.fail(field, constants::MESSAGE_FIELD_REQUIRED.to_string());
+            }
+            return String::new();
+        }
+        normalize_phone(&value).unwrap_or_else(|| {
+            self.fail(field, constants::MESSAGE_INVALID_PHONE.to_string());
+            value
+        })
+    }
+
+    pub fn finish<T>(self, value: T) -> Result<T, Vec<FieldError>> {
+        if self.errors.is_empty() {
+            Ok(value)
+        } else {
+            Err(self.errors)
+        }
+    }
+}
+
+// Names, email and phone may be left empty, they are prefilled from the parsed CV
+impl Validate for CandidateDTO {
+    fn validate(self) -> Result<CandidateDTO, Vec<FieldError>> {
+        let mut checks = Checks::default();
+        let application = CandidateDTO {
+            lastname: checks.text("lastname", self.lastname, MAX_NAME_LENGTH, false),
+            firstname: checks.text("firstname", self.firstname, MAX_NAME_LENGTH, false),
+            phone: checks.phone("phone", self.phone, false),
+            email: checks.email("email", self.email, false),
+            motivation: checks.text("motivation", self.motivation, MAX_MOTIVATION_LENGTH, false),
+            ..self
+        };
+        checks.finish(application)
+    }
+}
+
+impl Validate for UserDTO {
+    fn validate(self) -> Result<UserDTO, Vec<FieldError>> {
+        let mut checks = Checks::default();
+        let user = UserDTO {
+            username: checks.text("username", self.username, MAX_USERNAME_LENGTH, true),
+            email: checks.email("email", self.email, true),
+            ..self
+        };
+        checks.finish(user)
+    }
+}
+
+// Imported applications, the anonymised ones are blank already
+impl Validate for Candidate {
+    fn validate(self) -> Result<Candidate, Vec<FieldError>> {
+        let mut checks = Checks::default();
+        let application = Candidate {
+            lastname: checks.text("lastname", self.lastname, MAX_NAME_LENGTH, false),
+            firstname: checks.text("firstname", self.firstname, MAX_NAME_LENGTH, false),
+            phone: checks.phone("phone", self.phone, false),
+            email: checks.email("email", self.email, false),
+            motivation: checks.text("motivation", self.motivation, MAX_MOTIVATION_LENGTH, false),
+            ..self
+        };
+        checks.finish(application)
+    }
+}
+
+impl Validate for UserRecord {
+    fn validate(self) -> Result<UserRecord, Vec<FieldError>> {
+        let mut checks = Checks::default();
+        let user = UserRecord {
+            username: checks.text("username", self.username, MAX_USERNAME_LENGTH, true),
+            email: checks.email("email", self.email, true),
+            ..self
+        };
+        checks.finish(user)
+    }
+}
+
+// "email: The email address is invalid", for the errors of the admin CLI
+pub fn describe(errors: &[FieldError]) -> String {
+    errors
+        .iter()
+        .map(|error| format!("{}: {}", error.field, error.message))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::user::RoleType;
+    use uuid::Uuid;
+
+    fn application(phone: &str, email: &str) -> CandidateDTO {
+        CandidateDTO {
+            company_id: Uuid::new_v4(),
+            lastname: " Doe ".to_string(),
+            firstname: "Jane".to_string(),
+            file_name: "cv.pdf".to_string(),
+            phone: phone.to_string(),
+            email: email.to_string(),
+            motivation: "Motivated".to_string(),
+        }
+    }
+
+    fn fields(errors: Vec<FieldError>) -> Vec<String> {
+        errors.into_iter().map(|error| error.field).collect()
+    }
+
+    #[test]
+    fn test_validate_candidate() {
+        let valid = application("06 12 34 56 78", "Jane.Doe@Example.com")
+            .validate()
+            .unwrap();
+        assert_eq!(
+            (
+                valid.lastname.as_str(),
+                valid.phone.as_str(),
+                valid.email.as_str()
+            ),
+            ("Doe", "+33612345678", "jane.doe@example.com")
+        );
+        let empty = application(" ", "").validate().unwrap();
+        assert_eq!((empty.phone, empty.email), (String::new(), String::new()));
+
+        let errors = application("12 34", "jane@").validate().err().unwrap();
+        assert_eq!(fields(errors), vec!["phone", "email"]);
+        let long = CandidateDTO {
+            firstname: "J".repeat(MAX_NAME_LENGTH + 1),
+            motivation: "é".repeat(MAX_MOTIVATION_LENGTH),
+            ..application("", "")
+        };
+        let errors = long.validate().err().unwrap();
+        assert_eq!(errors[0].field, "firstname");
+        assert_eq!(
+            errors[0].message,
+            format!(
+                "{} (100 characters at most)",
+                constants::MESSAGE_FIELD_TOO_LONG
+            )
+        );
+        assert_eq!(errors.len(), 1);
+    }
+
+    #[test]
+    fn test_validate_user() {
+        let user = |username: &str, email: &str| UserDTO {
+            username: username.to_string(),
+            company_id: None,
+            email: email.to_string(),
+            password: None,
+            role: RoleType::User,
+        };
+        assert_eq!(
+            user("jane", " JANE@Acme.test").validate().unwrap().email,
+            "jane@acme.test"
+        );
+        assert_eq!(
+            fields(user(" ", "").validate().err().unwrap()),
+            vec!["username", "email"]
+        );
+        let errors = user("jane", "jane.acme.test").validate().err().unwrap();
+        assert_eq!(errors[0].message, constants::MESSAGE_INVALID_EMAIL);
+        assert_eq!(describe(&errors), "email: The email address is invalid");
+    }
+}